@@ -0,0 +1,183 @@
+// src/ata.rs
+// ATA PIO driver for the primary IDE bus
+
+use x86_64::instructions::port::Port;
+use crate::log_info;
+
+pub const SECTOR_SIZE: usize = 512;
+
+// Primary bus I/O ports
+const ATA_DATA: u16 = 0x1F0;
+const ATA_SECTOR_COUNT: u16 = 0x1F2;
+const ATA_LBA_LOW: u16 = 0x1F3;
+const ATA_LBA_MID: u16 = 0x1F4;
+const ATA_LBA_HIGH: u16 = 0x1F5;
+const ATA_DRIVE_SELECT: u16 = 0x1F6;
+const ATA_STATUS_COMMAND: u16 = 0x1F7;
+
+// Commands
+const CMD_READ_SECTORS: u8 = 0x20;
+const CMD_WRITE_SECTORS: u8 = 0x30;
+const CMD_CACHE_FLUSH: u8 = 0xE7;
+const CMD_IDENTIFY: u8 = 0xEC;
+
+// Status bits
+const STATUS_ERR: u8 = 0x01;
+const STATUS_DRQ: u8 = 0x08;
+const STATUS_DF: u8 = 0x20;
+const STATUS_BSY: u8 = 0x80;
+
+// Upper bound for status polling so a dead drive can't hang the kernel
+const MAX_POLLS: usize = 1_000_000;
+
+/// Which drive on the primary bus to talk to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AtaDrive {
+    Master,
+    Slave,
+}
+
+impl AtaDrive {
+    fn select_bits(self) -> u8 {
+        match self {
+            AtaDrive::Master => 0xE0,
+            AtaDrive::Slave => 0xF0,
+        }
+    }
+}
+
+fn status() -> u8 {
+    unsafe { Port::<u8>::new(ATA_STATUS_COMMAND).read() }
+}
+
+// Wait until the drive is no longer busy
+fn wait_not_busy() -> Result<u8, &'static str> {
+    for _ in 0..MAX_POLLS {
+        let status = status();
+        if status & STATUS_BSY == 0 {
+            return Ok(status);
+        }
+    }
+    Err("ATA drive timed out")
+}
+
+// Wait until the drive is ready to transfer data
+fn wait_data_ready() -> Result<(), &'static str> {
+    for _ in 0..MAX_POLLS {
+        let status = status();
+        if status & STATUS_BSY != 0 {
+            continue;
+        }
+        if status & (STATUS_ERR | STATUS_DF) != 0 {
+            return Err("ATA drive reported an error");
+        }
+        if status & STATUS_DRQ != 0 {
+            return Ok(());
+        }
+    }
+    Err("ATA drive timed out")
+}
+
+// Select the drive and program an LBA28 address and sector count
+fn setup_transfer(drive: AtaDrive, lba: u32, count: u8) -> Result<(), &'static str> {
+    if lba > 0x0FFF_FFFF {
+        return Err("LBA out of range");
+    }
+
+    wait_not_busy()?;
+    unsafe {
+        Port::<u8>::new(ATA_DRIVE_SELECT).write(drive.select_bits() | ((lba >> 24) & 0x0F) as u8);
+        Port::<u8>::new(ATA_SECTOR_COUNT).write(count);
+        Port::<u8>::new(ATA_LBA_LOW).write(lba as u8);
+        Port::<u8>::new(ATA_LBA_MID).write((lba >> 8) as u8);
+        Port::<u8>::new(ATA_LBA_HIGH).write((lba >> 16) as u8);
+    }
+    Ok(())
+}
+
+/// Check whether an ATA disk is attached using the IDENTIFY command
+pub fn identify(drive: AtaDrive) -> bool {
+    // A floating bus reads as 0xFF: no controller at all
+    if status() == 0xFF {
+        return false;
+    }
+
+    if setup_transfer(drive, 0, 0).is_err() {
+        return false;
+    }
+    unsafe { Port::<u8>::new(ATA_STATUS_COMMAND).write(CMD_IDENTIFY) };
+
+    // Status 0 means the drive doesn't exist
+    if status() == 0 || wait_not_busy().is_err() {
+        return false;
+    }
+
+    // ATAPI and SATA devices set the LBA mid/high registers
+    let signature = unsafe {
+        (Port::<u8>::new(ATA_LBA_MID).read(), Port::<u8>::new(ATA_LBA_HIGH).read())
+    };
+    if signature != (0, 0) {
+        return false;
+    }
+
+    if wait_data_ready().is_err() {
+        return false;
+    }
+
+    // Discard the identify data, we only care that the drive answered
+    let mut data = Port::<u16>::new(ATA_DATA);
+    for _ in 0..256 {
+        unsafe { data.read() };
+    }
+
+    log_info!("ATA: found {:?} drive on the primary bus", drive);
+    true
+}
+
+/// Read `buffer.len() / SECTOR_SIZE` sectors starting at `lba`
+pub fn read_sectors(drive: AtaDrive, lba: u32, buffer: &mut [u8]) -> Result<(), &'static str> {
+    let count = buffer.len() / SECTOR_SIZE;
+    if count == 0 || count > 255 || buffer.len() % SECTOR_SIZE != 0 {
+        return Err("Invalid sector buffer");
+    }
+
+    setup_transfer(drive, lba, count as u8)?;
+    unsafe { Port::<u8>::new(ATA_STATUS_COMMAND).write(CMD_READ_SECTORS) };
+
+    let mut data = Port::<u16>::new(ATA_DATA);
+    for sector in buffer.chunks_mut(SECTOR_SIZE) {
+        wait_data_ready()?;
+        for word in sector.chunks_mut(2) {
+            let value = unsafe { data.read() };
+            word[0] = value as u8;
+            word[1] = (value >> 8) as u8;
+        }
+    }
+
+    Ok(())
+}
+
+/// Write `buffer.len() / SECTOR_SIZE` sectors starting at `lba`
+pub fn write_sectors(drive: AtaDrive, lba: u32, buffer: &[u8]) -> Result<(), &'static str> {
+    let count = buffer.len() / SECTOR_SIZE;
+    if count == 0 || count > 255 || buffer.len() % SECTOR_SIZE != 0 {
+        return Err("Invalid sector buffer");
+    }
+
+    setup_transfer(drive, lba, count as u8)?;
+    unsafe { Port::<u8>::new(ATA_STATUS_COMMAND).write(CMD_WRITE_SECTORS) };
+
+    let mut data = Port::<u16>::new(ATA_DATA);
+    for sector in buffer.chunks(SECTOR_SIZE) {
+        wait_data_ready()?;
+        for word in sector.chunks(2) {
+            unsafe { data.write(word[0] as u16 | (word[1] as u16) << 8) };
+        }
+    }
+
+    // Make sure the data actually reaches the disk
+    unsafe { Port::<u8>::new(ATA_STATUS_COMMAND).write(CMD_CACHE_FLUSH) };
+    wait_not_busy()?;
+
+    Ok(())
+}
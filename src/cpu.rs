@@ -0,0 +1,74 @@
+// src/cpu.rs
+// Processor identification through the cpuid instruction
+
+use core::arch::x86_64::__cpuid;
+use crate::simple_fs::SimpleString;
+
+const LEAF_VENDOR: u32 = 0;
+const LEAF_FEATURES: u32 = 1;
+const LEAF_MAX_EXTENDED: u32 = 0x8000_0000;
+// The brand string is spread over three leaves, 16 bytes each
+const LEAF_BRAND_FIRST: u32 = 0x8000_0002;
+const LEAF_BRAND_LAST: u32 = 0x8000_0004;
+// Feature bit in EDX of leaf 1
+const FEATURE_TSC: u32 = 1 << 4;
+
+/// The vendor id, e.g. "GenuineIntel" or "AuthenticAMD"
+pub fn vendor() -> SimpleString {
+    // Every x86_64 CPU has cpuid, so this can't fault
+    let result = unsafe { __cpuid(LEAF_VENDOR) };
+    let mut bytes = [0u8; 12];
+    // The vendor is stored in EBX, EDX, ECX order
+    for (chunk, register) in bytes.chunks_mut(4).zip([result.ebx, result.edx, result.ecx]) {
+        chunk.copy_from_slice(&register.to_le_bytes());
+    }
+    text_from_bytes(&bytes)
+}
+
+/// Whether the CPU has a time stamp counter that `rdtsc` can read
+pub fn has_tsc() -> bool {
+    unsafe { __cpuid(LEAF_FEATURES) }.edx & FEATURE_TSC != 0
+}
+
+/// The processor's marketing name, or None if the CPU doesn't report one
+pub fn brand() -> Option<SimpleString> {
+    let max_extended = unsafe { __cpuid(LEAF_MAX_EXTENDED) }.eax;
+    if max_extended < LEAF_BRAND_LAST {
+        return None;
+    }
+    let mut bytes = [0u8; 48];
+    for (chunk, leaf) in bytes.chunks_mut(16).zip(LEAF_BRAND_FIRST..=LEAF_BRAND_LAST) {
+        let result = unsafe { __cpuid(leaf) };
+        for (part, register) in chunk.chunks_mut(4).zip([result.eax, result.ebx, result.ecx, result.edx]) {
+            part.copy_from_slice(&register.to_le_bytes());
+        }
+    }
+    let brand = text_from_bytes(&bytes);
+    if brand.is_empty() { None } else { Some(brand) }
+}
+
+// ASCII up to the first NUL, without the padding spaces some CPUs add
+fn text_from_bytes(bytes: &[u8]) -> SimpleString {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    let text = core::str::from_utf8(&bytes[..end]).unwrap_or("");
+    let mut result = SimpleString::new();
+    result.push_str(text.trim());
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn text_stops_at_nul_and_drops_padding() {
+        assert_eq!(text_from_bytes(b"  QEMU Virtual CPU  \0\0\0").as_str(), "QEMU Virtual CPU");
+        assert_eq!(text_from_bytes(b"GenuineIntel").as_str(), "GenuineIntel");
+        assert!(text_from_bytes(b"\0\0\0\0").is_empty());
+    }
+
+    #[test_case]
+    fn vendor_is_twelve_characters() {
+        assert_eq!(vendor().len(), 12);
+    }
+}
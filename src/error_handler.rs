@@ -2,11 +2,13 @@
 // Error handling system for ScreammOS with recovery mechanisms
 
 use core::fmt::{self, Display, Formatter};
+use core::sync::atomic::{AtomicBool, Ordering};
 use lazy_static::lazy_static;
 use spin::Mutex;
 use crate::{log_error, log_warn, log_info, log_crit};
 use crate::simple_fs::{SimpleString, FILESYSTEM};
-use crate::vga_buffer::{Color, WRITER};
+use crate::logger::LOGGER;
+use crate::vga_buffer::{get_current_theme, set_theme, Color, WRITER};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ErrorSeverity {
@@ -27,9 +29,71 @@ pub enum ErrorDomain {
     System,         // General system errors
 }
 
+/// Known error codes. The high nibble groups them roughly by area.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u16)]
+pub enum ErrorCode {
+    Warning = 0x1000,
+    SelfTestFailed = 0x1001,
+    Filesystem = 0x2000,
+    Memory = 0x3000,
+    KeyboardQueueUninitialized = 0x4001,
+    KeyboardControllerSelfTest = 0x4002,
+    KeyboardReset = 0x4003,
+    Critical = 0x5000,
+    PageFault = 0x5001,
+    Fatal = 0x9000,
+    Test = 0x9999,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 11] = [
+        ErrorCode::Warning,
+        ErrorCode::SelfTestFailed,
+        ErrorCode::Filesystem,
+        ErrorCode::Memory,
+        ErrorCode::KeyboardQueueUninitialized,
+        ErrorCode::KeyboardControllerSelfTest,
+        ErrorCode::KeyboardReset,
+        ErrorCode::Critical,
+        ErrorCode::PageFault,
+        ErrorCode::Fatal,
+        ErrorCode::Test,
+    ];
+
+    pub fn code(self) -> u16 {
+        self as u16
+    }
+
+    pub fn from_code(code: u16) -> Option<ErrorCode> {
+        ErrorCode::ALL.iter().copied().find(|error| error.code() == code)
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            ErrorCode::Warning => "General warning",
+            ErrorCode::SelfTestFailed => "Self-test failed",
+            ErrorCode::Filesystem => "File system error",
+            ErrorCode::Memory => "Memory error",
+            ErrorCode::KeyboardQueueUninitialized => "Keyboard queue not initialized",
+            ErrorCode::KeyboardControllerSelfTest => "Keyboard controller self-test failed",
+            ErrorCode::KeyboardReset => "Keyboard reset failed",
+            ErrorCode::Critical => "Critical error",
+            ErrorCode::PageFault => "Unrecoverable page fault",
+            ErrorCode::Fatal => "Fatal error",
+            ErrorCode::Test => "Test error",
+        }
+    }
+}
+
+/// Human-readable description of a numeric error code
+pub fn describe(code: u16) -> &'static str {
+    ErrorCode::from_code(code).map_or("Unknown error", ErrorCode::description)
+}
+
 #[derive(Debug, Clone)]
 pub struct SystemError {
-    pub code: u16,
+    pub code: ErrorCode,
     pub domain: ErrorDomain,
     pub severity: ErrorSeverity,
     pub message: SimpleString,
@@ -37,14 +101,14 @@ pub struct SystemError {
 }
 
 impl SystemError {
-    pub fn new(code: u32, domain: ErrorDomain, severity: ErrorSeverity, message: &str) -> Self {
+    pub fn new(code: ErrorCode, domain: ErrorDomain, severity: ErrorSeverity, message: &str) -> Self {
         let recoverable = severity != ErrorSeverity::Fatal;
         
         let mut msg = SimpleString::new();
         msg.push_str(message);
         
         Self {
-            code: code as u16,
+            code,
             domain,
             severity,
             message: msg,
@@ -53,7 +117,11 @@ impl SystemError {
     }
     
     pub fn get_code(&self) -> u32 {
-        self.code as u32
+        self.code.code() as u32
+    }
+    
+    pub fn describe(&self) -> &'static str {
+        self.code.description()
     }
     
     pub fn get_domain(&self) -> ErrorDomain {
@@ -75,8 +143,9 @@ impl SystemError {
 
 impl Display for SystemError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "[{:04X}] {:?}/{:?}: {}", 
+        write!(f, "[{:04X} {}] {:?}/{:?}: {}", 
             self.get_code(), 
+            self.describe(),
             self.get_domain(), 
             self.get_severity(), 
             self.get_message()
@@ -84,11 +153,28 @@ impl Display for SystemError {
     }
 }
 
+// Speaker alerts, a short high beep for critical errors and a long low
+// one for fatal errors
+const CRITICAL_BEEP_HZ: u32 = 880;
+const CRITICAL_BEEP_MS: u32 = 100;
+const FATAL_BEEP_HZ: u32 = 220;
+const FATAL_BEEP_MS: u32 = 500;
+
+// Mirrors ErrorHandler::safe_mode so code that can't take the
+// ERROR_HANDLER lock (like the heap allocator) can still check it
+static SAFE_MODE: AtomicBool = AtomicBool::new(false);
+
+/// Whether risky features should stay off
+pub fn is_in_safe_mode() -> bool {
+    SAFE_MODE.load(Ordering::Relaxed)
+}
+
 pub struct ErrorHandler {
     error_count: [u32; 4],  // Count by severity
     last_errors: [Option<SystemError>; 10],
     last_index: usize,
     safe_mode: bool,
+    logged_to_file: bool,   // File logging setting to restore on exit
 }
 
 impl ErrorHandler {
@@ -99,6 +185,7 @@ impl ErrorHandler {
             last_errors: [NONE_ERROR; 10],
             last_index: 0,
             safe_mode: false,
+            logged_to_file: false,
         }
     }
     
@@ -160,12 +247,19 @@ impl ErrorHandler {
         match error.domain {
             ErrorDomain::Filesystem => {
                 log_info!("Attempting filesystem recovery");
-                // Attempt to verify filesystem integrity
-                let mut fs = FILESYSTEM.lock();
-                // In a real implementation, we would have fs.check_integrity() etc.
-                // For now, just report we tried
-                drop(fs);
-                log_info!("Filesystem recovery completed");
+                // The error may come from inside the file system, which
+                // still holds its lock
+                match FILESYSTEM.try_lock() {
+                    Some(mut fs) => match fs.check_integrity() {
+                        Ok(()) => log_info!("Filesystem is consistent"),
+                        Err(problem) => {
+                            log_warn!("Filesystem check failed: {}", problem);
+                            let cleared = fs.repair();
+                            log_info!("Filesystem repaired, {} entries cleared", cleared);
+                        }
+                    },
+                    None => log_warn!("Filesystem busy, recovery skipped"),
+                }
             },
             ErrorDomain::Memory => {
                 log_info!("Attempting memory management recovery");
@@ -186,16 +280,23 @@ impl ErrorHandler {
     
     pub fn enter_safe_mode(&mut self) {
         self.safe_mode = true;
+        SAFE_MODE.store(true, Ordering::Relaxed);
         log_warn!("Entering SAFE MODE due to repeated system errors");
         
-        // In safe mode:
-        // 1. Disable non-critical features
-        // 2. Use conservative settings
-        // 3. Perform extra validation
+        // In safe mode the heap stops growing (see memory::grow_heap),
+        // the log stays off the file system and the CRT effect stays off
+        {
+            let mut logger = LOGGER.lock();
+            self.logged_to_file = logger.logs_to_file();
+            logger.set_log_to_file(false);
+        }
         
         // Change screen colors to indicate safe mode
-        let mut writer = WRITER.lock();
-        writer.set_color(Color::Yellow, Color::Blue);
+        {
+            let mut writer = WRITER.lock();
+            writer.set_crt_effect(false);
+            writer.set_color(Color::Yellow, Color::Blue);
+        }
         
         // Display safe mode banner
         crate::println!("");
@@ -210,14 +311,15 @@ impl ErrorHandler {
     pub fn exit_safe_mode(&mut self) {
         if self.safe_mode {
             self.safe_mode = false;
+            SAFE_MODE.store(false, Ordering::Relaxed);
             log_info!("Exiting safe mode");
             
             // Reset error counters
             self.error_count = [0; 4];
             
             // Restore normal operation
-            let mut writer = WRITER.lock();
-            writer.set_color(Color::LightGray, Color::Black);
+            LOGGER.lock().set_log_to_file(self.logged_to_file);
+            set_theme(get_current_theme());
         }
     }
     
@@ -237,7 +339,7 @@ impl ErrorHandler {
         crate::println!("*                 SYSTEM FAILURE                   *");
         crate::println!("****************************************************");
         crate::println!("");
-        crate::println!("Error {:04X}: {:?}", error.get_code(), error.domain);
+        crate::println!("Error {:04X} ({}): {:?}", error.get_code(), error.describe(), error.domain);
         crate::println!("{}", error.message.as_str());
         crate::println!("");
         crate::println!("The system cannot continue and needs to restart.");
@@ -261,24 +363,28 @@ impl ErrorHandler {
         self.error_count.iter().sum()
     }
     
-    pub fn dump_error_history(&self) {
-        crate::println!("\nError History:");
-        crate::println!("--------------");
+    /// Recorded errors, oldest first
+    pub fn history(&self) -> impl Iterator<Item = &SystemError> + '_ {
+        let len = self.last_errors.len();
+        (0..len).filter_map(move |i| self.last_errors[(self.last_index + i) % len].as_ref())
+    }
+    
+    /// Write the recorded errors, oldest first, to `out`
+    pub fn dump_error_history(&self, out: &mut impl fmt::Write) -> fmt::Result {
+        writeln!(out, "Error History:")?;
+        writeln!(out, "--------------")?;
         
         let mut count = 0;
-        for i in 0..self.last_errors.len() {
-            let index = (self.last_index + i) % self.last_errors.len();
-            if let Some(error) = &self.last_errors[index] {
-                crate::println!("{}", error);
-                count += 1;
-            }
+        for error in self.history() {
+            writeln!(out, "{}", error)?;
+            count += 1;
         }
         
         if count == 0 {
-            crate::println!("No errors recorded.");
+            writeln!(out, "No errors recorded.")?;
         }
         
-        crate::println!("");
+        writeln!(out)
     }
 }
 
@@ -288,33 +394,98 @@ lazy_static! {
 }
 
 // Functions to create and report errors
-pub fn report_error(code: u32, domain: ErrorDomain, severity: ErrorSeverity, message: &str) -> Result<(), ()> {
+pub fn report_error(code: ErrorCode, domain: ErrorDomain, severity: ErrorSeverity, message: &str) -> Result<(), ()> {
     let error = SystemError::new(code, domain, severity, message);
-    let mut handler = ERROR_HANDLER.lock();
-    handler.handle_error(error)
+    let result = ERROR_HANDLER.lock().handle_error(error);
+    
+    // Beep once the lock is released, the wait sleeps through interrupts
+    // that may report errors of their own
+    match severity {
+        ErrorSeverity::Critical => crate::sound::beep(CRITICAL_BEEP_HZ, CRITICAL_BEEP_MS),
+        ErrorSeverity::Fatal => crate::sound::beep(FATAL_BEEP_HZ, FATAL_BEEP_MS),
+        _ => {}
+    }
+    result
 }
 
 pub fn report_warning(domain: ErrorDomain, message: &str) -> Result<(), ()> {
-    report_error(0x1000, domain, ErrorSeverity::Warning, message)
+    report_error(ErrorCode::Warning, domain, ErrorSeverity::Warning, message)
 }
 
 pub fn report_filesystem_error(message: &str) -> Result<(), ()> {
-    report_error(0x2000, ErrorDomain::Filesystem, ErrorSeverity::Error, message)
+    report_error(ErrorCode::Filesystem, ErrorDomain::Filesystem, ErrorSeverity::Error, message)
 }
 
 pub fn report_memory_error(message: &str) -> Result<(), ()> {
-    report_error(0x3000, ErrorDomain::Memory, ErrorSeverity::Error, message)
+    report_error(ErrorCode::Memory, ErrorDomain::Memory, ErrorSeverity::Error, message)
 }
 
 pub fn report_critical_error(domain: ErrorDomain, message: &str) -> Result<(), ()> {
-    report_error(0x5000, domain, ErrorSeverity::Critical, message)
+    report_error(ErrorCode::Critical, domain, ErrorSeverity::Critical, message)
 }
 
 pub fn report_fatal_error(domain: ErrorDomain, message: &str) -> Result<(), ()> {
-    report_error(0x9000, domain, ErrorSeverity::Fatal, message)
+    report_error(ErrorCode::Fatal, domain, ErrorSeverity::Fatal, message)
 }
 
 // Initialize the error handling system
 pub fn init() {
     log_info!("Error handling system initialized");
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn error_codes_round_trip() {
+        for &code in ErrorCode::ALL.iter() {
+            assert_eq!(ErrorCode::from_code(code.code()), Some(code));
+            assert_eq!(describe(code.code()), code.description());
+        }
+    }
+
+    #[test_case]
+    fn unknown_codes_are_described() {
+        assert_eq!(ErrorCode::from_code(0x0042), None);
+        assert_eq!(describe(0x0042), "Unknown error");
+    }
+
+    #[test_case]
+    fn empty_history_dumps_placeholder() {
+        let handler = ErrorHandler::new();
+        let mut out = SimpleString::new();
+        assert!(handler.dump_error_history(&mut out).is_ok());
+        assert_eq!(out.as_str(), "Error History:\n--------------\nNo errors recorded.\n\n");
+    }
+
+    #[test_case]
+    fn three_critical_errors_enter_safe_mode() {
+        let mut handler = ErrorHandler::new();
+        for _ in 0..3 {
+            let error = SystemError::new(ErrorCode::Test, ErrorDomain::System, ErrorSeverity::Critical, "test");
+            assert_eq!(handler.handle_error(error), Ok(()));
+        }
+        assert!(handler.is_in_safe_mode());
+        assert!(is_in_safe_mode());
+        assert!(!LOGGER.lock().logs_to_file());
+
+        handler.exit_safe_mode();
+        assert!(!handler.is_in_safe_mode());
+        assert!(!is_in_safe_mode());
+        assert_eq!(handler.get_total_error_count(), 0);
+    }
+
+    #[test_case]
+    fn history_is_oldest_first() {
+        let mut handler = ErrorHandler::new();
+        handler.last_errors[0] = Some(SystemError::new(ErrorCode::Test, ErrorDomain::System, ErrorSeverity::Warning, "first"));
+        handler.last_errors[1] = Some(SystemError::new(ErrorCode::Test, ErrorDomain::System, ErrorSeverity::Warning, "second"));
+        handler.last_index = 2;
+
+        let mut history = handler.history().map(|error| error.get_message());
+        assert_eq!(history.next(), Some("first"));
+        assert_eq!(history.next(), Some("second"));
+        assert_eq!(history.next(), None);
+    }
+}
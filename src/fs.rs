@@ -12,6 +12,7 @@ use crate::println;
 use spin::Mutex;
 use lazy_static::lazy_static;
 use core::fmt::Write;
+use crate::simple_fs::SimpleString;
 
 // File entry types
 #[derive(Debug, Clone, PartialEq)]
@@ -513,24 +514,9 @@ pub fn format_dir_listing(entries: &[FSEntry]) -> String {
     for entry in entries {
         let type_str = if entry.is_directory() { "DIR" } else { "FILE" };
         let size_str = if entry.is_directory() { String::from("<DIR>") } else { 
-            let mut bytes_str = String::new();
-            let mut num = entry.metadata.size;
-            if num == 0 {
-                bytes_str.push('0');
-            } else {
-                while num > 0 {
-                    let digit = (num % 10) as u8;
-                    bytes_str.push((b'0' + digit) as char);
-                    num /= 10;
-                }
-            }
-            // Reverse the string
-            let mut reversed = String::new();
-            for c in bytes_str.chars().rev() {
-                reversed.push(c);
-            }
-            reversed.push_str(" bytes");
-            reversed
+            let mut bytes_str = String::from(SimpleString::from_usize(entry.metadata.size).as_str());
+            bytes_str.push_str(" bytes");
+            bytes_str
         };
         
         let mut line = String::new();
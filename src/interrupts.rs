@@ -3,6 +3,9 @@ use lazy_static::lazy_static;
 use pic8259::ChainedPics;
 use spin;
 use crate::println;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::arch::x86_64::_rdtsc;
+use x86_64::instructions::port::Port;
 
 // PIC configuration (Primary and Secondary Programmable Interrupt Controllers)
 pub const PIC_1_OFFSET: u8 = 32;
@@ -26,6 +29,92 @@ impl InterruptIndex {
     }
 }
 
+// The PIT is left at its power-on divisor of 65536, so the timer fires
+// 1193182 / 65536 ≈ 18.2 times per second
+pub const PIT_BASE_FREQUENCY: u64 = 1_193_182;
+const PIT_DIVISOR: u64 = 65_536;
+
+// PIT channel 2 is wired to the PC speaker, and its gate and output
+// show up on the speaker port
+pub const PIT_CHANNEL_2: u16 = 0x42;
+pub const PIT_COMMAND: u16 = 0x43;
+pub const SPEAKER_PORT: u16 = 0x61;
+
+// Channel 2, low byte then high byte, mode 0 (interrupt on terminal count)
+const CHANNEL_2_ONE_SHOT: u8 = 0xB0;
+const GATE_BIT: u8 = 0x01;
+const SPEAKER_BIT: u8 = 0x02;
+const OUTPUT_BIT: u8 = 0x20;
+// About 10ms of PIT input clock
+const CALIBRATION_COUNT: u16 = 11_932;
+// Give up on a PIT that never finishes the count
+const CALIBRATION_MAX_POLLS: u32 = 10_000_000;
+
+// Timer interrupts since boot
+static TICKS: AtomicU64 = AtomicU64::new(0);
+
+/// Number of timer interrupts since boot
+pub fn ticks() -> u64 {
+    TICKS.load(Ordering::Relaxed)
+}
+
+/// Whole seconds since boot, derived from the PIT tick count
+pub fn uptime_seconds() -> u64 {
+    ticks() * PIT_DIVISOR / PIT_BASE_FREQUENCY
+}
+
+/// Milliseconds since boot, in steps of one tick (~55ms)
+pub fn uptime_ms() -> u64 {
+    ticks() * PIT_DIVISOR * 1000 / PIT_BASE_FREQUENCY
+}
+
+/// Microseconds since boot, in steps of one tick (~55ms)
+pub fn uptime_us() -> u64 {
+    ticks() * PIT_DIVISOR * 1_000_000 / PIT_BASE_FREQUENCY
+}
+
+/// Number of ticks that cover at least `ms` milliseconds
+pub fn ticks_for_ms(ms: u64) -> u64 {
+    let per_second = PIT_DIVISOR * 1000;
+    (ms * PIT_BASE_FREQUENCY + per_second - 1) / per_second
+}
+
+/// Time a one-shot count on PIT channel 2 with the TSC. Returns the TSC
+/// cycles per microsecond and the TSC value the count started at, or None
+/// without a TSC or if the PIT never finishes. Works before interrupts are on.
+pub fn calibrate_tsc() -> Option<(u64, u64)> {
+    if !crate::cpu::has_tsc() {
+        return None;
+    }
+    let mut command = Port::<u8>::new(PIT_COMMAND);
+    let mut channel = Port::<u8>::new(PIT_CHANNEL_2);
+    let mut speaker = Port::<u8>::new(SPEAKER_PORT);
+
+    x86_64::instructions::interrupts::without_interrupts(|| unsafe {
+        // Gate channel 2 on, keep the speaker itself quiet
+        let old = speaker.read();
+        speaker.write((old & !SPEAKER_BIT) | GATE_BIT);
+        command.write(CHANNEL_2_ONE_SHOT);
+        channel.write(CALIBRATION_COUNT as u8);
+        channel.write((CALIBRATION_COUNT >> 8) as u8);
+
+        let start = _rdtsc();
+        let mut polls = 0;
+        while speaker.read() & OUTPUT_BIT == 0 {
+            polls += 1;
+            if polls == CALIBRATION_MAX_POLLS {
+                speaker.write(old);
+                return None;
+            }
+        }
+        let end = _rdtsc();
+        speaker.write(old);
+
+        let elapsed_us = u64::from(CALIBRATION_COUNT) * 1_000_000 / PIT_BASE_FREQUENCY;
+        Some((((end - start) / elapsed_us).max(1), start))
+    })
+}
+
 // Configure the PICs to handle interrupts
 pub static PICS: spin::Mutex<ChainedPics> =
     spin::Mutex::new(unsafe { ChainedPics::new(PIC_1_OFFSET, PIC_2_OFFSET) });
@@ -35,6 +124,12 @@ lazy_static! {
         let mut idt = InterruptDescriptorTable::new();
         idt.breakpoint.set_handler_fn(breakpoint_handler);
         idt.page_fault.set_handler_fn(page_fault_handler);
+        // Runs on its own stack so a kernel stack overflow can be reported
+        unsafe {
+            idt.double_fault
+                .set_handler_fn(double_fault_handler)
+                .set_stack_index(crate::gdt::DOUBLE_FAULT_IST_INDEX);
+        }
         
         // Set up hardware interrupt handlers
         idt[InterruptIndex::Timer.as_usize()]
@@ -66,24 +161,52 @@ extern "x86-interrupt" fn breakpoint_handler(stack_frame: InterruptStackFrame) {
     println!("EXCEPTION: BREAKPOINT\n{:#?}", stack_frame);
 }
 
+// Double fault handler, reached when another exception handler faults,
+// e.g. the page fault from a kernel stack overflow
+extern "x86-interrupt" fn double_fault_handler(
+    stack_frame: InterruptStackFrame,
+    error_code: u64,
+) -> ! {
+    println!("EXCEPTION: DOUBLE FAULT (error code {})", error_code);
+    println!("{:#?}", stack_frame);
+    crate::hlt_loop();
+}
+
 // Page fault handler
 extern "x86-interrupt" fn page_fault_handler(
     stack_frame: InterruptStackFrame,
     error_code: x86_64::structures::idt::PageFaultErrorCode,
 ) {
     use x86_64::registers::control::Cr2;
+    use x86_64::structures::idt::PageFaultErrorCode;
+    use crate::error_handler::{report_error, ErrorCode, ErrorDomain, ErrorSeverity};
+    
+    let address = Cr2::read();
+    
+    // A missing heap page can be mapped on demand and the access retried.
+    // Protection violations mean the page exists, so they are never retried.
+    if !error_code.contains(PageFaultErrorCode::PROTECTION_VIOLATION)
+        && crate::memory::map_heap_page(address).is_ok()
+    {
+        // No logging here: the fault may have hit while the logger was locked
+        return;
+    }
     
     println!("EXCEPTION: PAGE FAULT");
-    println!("Accessed Address: {:?}", Cr2::read());
+    println!("Accessed Address: {:?}", address);
     println!("Error Code: {:?}", error_code);
     println!("{:#?}", stack_frame);
     
-    loop {}
+    report_error(ErrorCode::PageFault, ErrorDomain::Memory, ErrorSeverity::Critical, "Unrecoverable page fault").ok();
+    crate::hlt_loop();
 }
 
 // Timer interrupt handler
 extern "x86-interrupt" fn timer_interrupt_handler(_stack_frame: InterruptStackFrame) {
-    // For now, just acknowledge the interrupt
+    TICKS.fetch_add(1, Ordering::Relaxed);
+    crate::ui::screensaver::on_timer_tick();
+    crate::ui::splash_screen::on_timer_tick();
+    
     unsafe {
         PICS.lock().notify_end_of_interrupt(InterruptIndex::Timer.as_u8());
     }
@@ -93,14 +216,11 @@ extern "x86-interrupt" fn timer_interrupt_handler(_stack_frame: InterruptStackFr
 extern "x86-interrupt" fn keyboard_interrupt_handler(
     _stack_frame: InterruptStackFrame
 ) {
-    use x86_64::instructions::port::Port;
-    println!("DEBUG: Keyboard interrupt received!");
-
     let mut port = Port::new(0x60);
     let scancode: u8 = unsafe { port.read() };
     
-    // Pass to our keyboard handler
-    crate::keyboard::handle_scancode(scancode);
+    // Only queue the scancode, decoding happens in the main loop
+    crate::keyboard::add_scancode(scancode);
     
     unsafe {
         PICS.lock()
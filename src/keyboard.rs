@@ -1,24 +1,23 @@
 use lazy_static::lazy_static;
-use pc_keyboard::{layouts, DecodedKey, HandleControl, Keyboard, ScancodeSet1, KeyCode};
+use pc_keyboard::{DecodedKey, HandleControl, KeyCode, KeyState};
+use crate::keymap::{Layout, LayoutKeyboard};
 use spin::Mutex;
 use x86_64::instructions::port::Port;
-use crate::{print, println};
+use crate::println;
 use crate::simple_fs::SimpleString;
-use crate::vga_buffer::{clear_screen, get_current_theme, set_theme, Theme};
 use crate::ui::file_manager::FILE_MANAGER;
-use crate::ui::text_editor::TEXT_EDITOR;
+use crate::ui::menu::MenuEvent;
+use crate::ui::FOCUS;
 use crate::queue::ArrayQueue;
 use core::sync::atomic::{AtomicBool, Ordering};
 use crate::{log_info, log_warn, log_error};
-use crate::error_handler::{report_error, report_warning, ErrorDomain, ErrorSeverity};
-use crate::vga_buffer::Color;
-use crate::ui::command_line::CommandLine;
+use crate::error_handler::{report_error, report_warning, ErrorCode, ErrorDomain, ErrorSeverity};
+use crate::vga_buffer::{Color, BUFFER_HEIGHT};
 
 lazy_static! {
-    static ref KEYBOARD: Mutex<Keyboard<layouts::Us104Key, ScancodeSet1>> = 
-        Mutex::new(Keyboard::new(ScancodeSet1::new(), layouts::Us104Key, HandleControl::Ignore));
-    static ref CURRENT_LINE: Mutex<crate::simple_fs::SimpleString> = Mutex::new(crate::simple_fs::SimpleString::new());
-    static ref SCANCODE_QUEUE: Mutex<Option<ArrayQueue<u8>>> = Mutex::new(None);
+    static ref KEYBOARD: Mutex<LayoutKeyboard> = 
+        Mutex::new(LayoutKeyboard::new(Layout::Us, HandleControl::Ignore));
+    static ref SCANCODE_QUEUE: ArrayQueue<u8> = ArrayQueue::new(SCANCODE_QUEUE_SIZE);
     static ref KEYBOARD_COMMAND: Mutex<SimpleString> = Mutex::new(SimpleString::new());
     static ref KEYBOARD_STATE: Mutex<KeyboardState> = Mutex::new(KeyboardState::new());
 }
@@ -35,6 +34,7 @@ pub struct KeyboardState {
     pub is_shift_pressed: bool,
     pub is_ctrl_pressed: bool,
     pub is_alt_pressed: bool,
+    pub is_caps_lock: bool,
 }
 
 impl KeyboardState {
@@ -44,18 +44,152 @@ impl KeyboardState {
             is_shift_pressed: false,
             is_ctrl_pressed: false,
             is_alt_pressed: false,
+            is_caps_lock: false,
         }
     }
 }
 
+/// Modifier keys held down (and the CapsLock latch) at the time of a key
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub caps_lock: bool,
+}
+
+// Typematic repeat: delay before the first repeat and time between repeats
+const DEFAULT_REPEAT_DELAY_MS: u64 = 250;
+const DEFAULT_REPEAT_INTERVAL_MS: u64 = 30;
+// The timer only wakes us every ~55ms, so a few repeats may be due at once;
+// never fall further behind than this
+const MAX_REPEATS_PER_POLL: u64 = 4;
+
+static KEY_REPEAT: Mutex<KeyRepeat> = Mutex::new(KeyRepeat::new());
+
+/// The held key that is auto-repeated by `process_key_repeat`
+struct KeyRepeat {
+    code: Option<KeyCode>,
+    key: Option<DecodedKey>,
+    next_ms: u64,
+    delay_ms: u64,
+    interval_ms: u64,
+}
+
+impl KeyRepeat {
+    const fn new() -> Self {
+        KeyRepeat {
+            code: None,
+            key: None,
+            next_ms: 0,
+            delay_ms: DEFAULT_REPEAT_DELAY_MS,
+            interval_ms: DEFAULT_REPEAT_INTERVAL_MS,
+        }
+    }
+
+    /// Update the held key from a make/break event. Returns false for the
+    /// keyboard's own typematic make codes, which are dropped in favour of
+    /// our repeat timing.
+    fn track(&mut self, code: KeyCode, state: KeyState, key: Option<DecodedKey>, now_ms: u64) -> bool {
+        match state {
+            KeyState::Down if is_modifier(code) => true,
+            KeyState::Down if self.code == Some(code) => false,
+            KeyState::Down => {
+                self.code = Some(code);
+                self.key = key;
+                self.next_ms = now_ms + self.delay_ms;
+                true
+            }
+            KeyState::Up if self.code == Some(code) => {
+                self.code = None;
+                self.key = None;
+                true
+            }
+            _ => true,
+        }
+    }
+
+    /// The key to emit again if a repeat is due at `now_ms`
+    fn next_due(&mut self, now_ms: u64) -> Option<DecodedKey> {
+        let key = self.key?;
+        if now_ms < self.next_ms {
+            return None;
+        }
+        let oldest = now_ms.saturating_sub(self.interval_ms * MAX_REPEATS_PER_POLL);
+        self.next_ms = (self.next_ms + self.interval_ms).max(oldest);
+        Some(key)
+    }
+}
+
+// Modifier and lock keys never auto-repeat
+fn is_modifier(code: KeyCode) -> bool {
+    matches!(code,
+        KeyCode::LShift | KeyCode::RShift |
+        KeyCode::LControl | KeyCode::RControl |
+        KeyCode::LAlt | KeyCode::RAltGr |
+        KeyCode::CapsLock | KeyCode::NumpadLock | KeyCode::ScrollLock)
+}
+
+/// Switch the active keyboard layout
+pub fn set_layout(layout: Layout) {
+    *KEYBOARD.lock() = LayoutKeyboard::new(layout, HandleControl::Ignore);
+    log_info!("Keyboard layout set to {}", layout.description());
+}
+
+/// The active keyboard layout
+pub fn current_layout() -> Layout {
+    KEYBOARD.lock().layout()
+}
+
+/// Set the typematic delay before the first repeat and the repeat interval
+pub fn set_repeat_rate(delay_ms: u64, interval_ms: u64) {
+    let mut repeat = KEY_REPEAT.lock();
+    repeat.delay_ms = delay_ms;
+    repeat.interval_ms = interval_ms.max(1);
+}
+
+/// Emit repeats for the held key. Called from the main loop, which the
+/// timer interrupt wakes on every tick.
+pub fn process_key_repeat() {
+    let now = crate::interrupts::uptime_ms();
+    for _ in 0..MAX_REPEATS_PER_POLL {
+        let due = KEY_REPEAT.lock().next_due(now);
+        match due {
+            Some(key) => dispatch_key(key),
+            None => break,
+        }
+    }
+}
+
+// PS/2 controller ports and status bits
+const PS2_DATA_PORT: u16 = 0x60;
+const PS2_COMMAND_PORT: u16 = 0x64;
+const PS2_STATUS_OUTPUT_FULL: u8 = 0x01;
+const PS2_STATUS_INPUT_FULL: u8 = 0x02;
+
+// Upper bound for polling loops so a missing controller can't hang the boot
+const PS2_MAX_POLLS: usize = 100_000;
+const PS2_MAX_DRAIN_READS: usize = 64;
+
 /// Initialize the keyboard systems and set up interrupt handler
 pub fn init() {
     log_info!("Initializing keyboard");
-    
-    // Initialize the scancode queue
-    let mut scancode_queue = SCANCODE_QUEUE.lock();
-    *scancode_queue = Some(ArrayQueue::new(SCANCODE_QUEUE_SIZE));
-    drop(scancode_queue);
+
+    // Throw away stale bytes left in the controller by the BIOS
+    let drained = drain_output_buffer(
+        || unsafe { Port::<u8>::new(PS2_COMMAND_PORT).read() } & PS2_STATUS_OUTPUT_FULL != 0,
+        || unsafe { Port::<u8>::new(PS2_DATA_PORT).read() },
+    );
+    if drained > 0 {
+        log_warn!("Discarded {} stale bytes from the keyboard controller", drained);
+    }
+
+    controller_self_test();
+    reset_keyboard();
+
+    // Create the scancode queue now so the interrupt handler never runs
+    // the lazy initializer
+    lazy_static::initialize(&SCANCODE_QUEUE);
     
     KEYBOARD_INITIALIZED.store(true, Ordering::SeqCst);
     
@@ -67,95 +201,173 @@ pub fn is_initialized() -> bool {
     KEYBOARD_INITIALIZED.load(Ordering::SeqCst)
 }
 
-/// Add a scancode to the queue, safely
-pub fn add_scancode(scancode: u8) {
-    if let Some(queue) = &mut *SCANCODE_QUEUE.lock() {
-        if queue.is_full() {
-            // Queue is full, this could happen if processing can't keep up
-            log_warn!("Keyboard scancode queue overflow");
-            report_warning(ErrorDomain::IO, "Keyboard scancode queue overflow").ok();
-            return;
+/// Read and discard pending bytes while `has_data` reports the output buffer
+/// as full. Bounded so a controller that never clears can't hang the boot.
+/// Returns the number of bytes discarded.
+fn drain_output_buffer(mut has_data: impl FnMut() -> bool, mut read: impl FnMut() -> u8) -> usize {
+    let mut drained = 0;
+    while drained < PS2_MAX_DRAIN_READS && has_data() {
+        read();
+        drained += 1;
+    }
+    drained
+}
+
+// Wait until the controller is ready to accept a byte
+fn wait_for_input_empty() -> bool {
+    let mut status_port = Port::<u8>::new(PS2_COMMAND_PORT);
+    for _ in 0..PS2_MAX_POLLS {
+        if unsafe { status_port.read() } & PS2_STATUS_INPUT_FULL == 0 {
+            return true;
         }
-        
-        queue.push(scancode);
-    } else {
+    }
+    false
+}
+
+// Wait for a response byte from the controller or keyboard
+fn read_response() -> Option<u8> {
+    let mut status_port = Port::<u8>::new(PS2_COMMAND_PORT);
+    let mut data_port = Port::<u8>::new(PS2_DATA_PORT);
+    for _ in 0..PS2_MAX_POLLS {
+        if unsafe { status_port.read() } & PS2_STATUS_OUTPUT_FULL != 0 {
+            return Some(unsafe { data_port.read() });
+        }
+    }
+    None
+}
+
+fn send_controller_command(command: u8) -> bool {
+    if !wait_for_input_empty() {
+        return false;
+    }
+    unsafe { Port::<u8>::new(PS2_COMMAND_PORT).write(command) };
+    true
+}
+
+fn send_data(data: u8) -> bool {
+    if !wait_for_input_empty() {
+        return false;
+    }
+    unsafe { Port::<u8>::new(PS2_DATA_PORT).write(data) };
+    true
+}
+
+/// Run the controller self-test (0xAA, expect 0x55). The self-test can reset
+/// the configuration byte on some chipsets, so it's saved and restored.
+fn controller_self_test() {
+    let config = if send_controller_command(0x20) { read_response() } else { None };
+
+    let result = if send_controller_command(0xAA) { read_response() } else { None };
+    match result {
+        Some(0x55) => log_info!("Keyboard controller self-test passed"),
+        Some(code) => {
+            log_error!("Keyboard controller self-test failed: {:#04x}", code);
+            report_error(ErrorCode::KeyboardControllerSelfTest, ErrorDomain::Hardware, ErrorSeverity::Error,
+                "Keyboard controller self-test failed").ok();
+        },
+        None => {
+            log_error!("Keyboard controller did not answer self-test");
+            report_error(ErrorCode::KeyboardControllerSelfTest, ErrorDomain::Hardware, ErrorSeverity::Error,
+                "Keyboard controller did not answer self-test").ok();
+        }
+    }
+
+    if let Some(config) = config {
+        if send_controller_command(0x60) {
+            send_data(config);
+        }
+    }
+}
+
+/// Reset the keyboard (0xFF). It acknowledges with 0xFA, then reports 0xAA
+/// once its own self-test has passed.
+fn reset_keyboard() {
+    if !send_data(0xFF) {
+        log_error!("Keyboard controller not ready for reset");
+        report_warning(ErrorDomain::Hardware, "Keyboard controller not ready for reset").ok();
+        return;
+    }
+
+    let mut response = read_response();
+    if response == Some(0xFA) {
+        response = read_response();
+    }
+
+    match response {
+        Some(0xAA) => log_info!("Keyboard reset completed"),
+        Some(code) => {
+            log_error!("Keyboard reset failed: {:#04x}", code);
+            report_error(ErrorCode::KeyboardReset, ErrorDomain::Hardware, ErrorSeverity::Error,
+                "Keyboard reset failed").ok();
+        },
+        None => {
+            log_warn!("Keyboard did not answer reset");
+            report_warning(ErrorDomain::Hardware, "Keyboard did not answer reset").ok();
+        }
+    }
+}
+
+/// Add a scancode to the queue. Called from the keyboard interrupt, which
+/// is the queue's only producer.
+pub fn add_scancode(scancode: u8) {
+    if !is_initialized() {
         // This should never happen if init is called properly
         log_error!("Keyboard scancode queue uninitialized");
         report_error(
-            0x4001, 
+            ErrorCode::KeyboardQueueUninitialized,
             ErrorDomain::IO, 
             ErrorSeverity::Error, 
             "Keyboard scancode queue uninitialized"
         ).ok();
+        return;
+    }
+    
+    if SCANCODE_QUEUE.push(scancode).is_err() {
+        // Queue is full, this could happen if processing can't keep up
+        log_warn!("Keyboard scancode queue overflow");
+        report_warning(ErrorDomain::IO, "Keyboard scancode queue overflow").ok();
     }
 }
 
-/// Process keyboard input with error recovery
-pub fn process_keypress() {
-    let mut keyboard = KEYBOARD.lock();
-    
-    let scancode = match get_scancode() {
-        Some(code) => code,
-        None => return,
-    };
-    
-    if let Ok(Some(key_event)) = keyboard.add_byte(scancode) {
-        if let Some(key) = keyboard.process_keyevent(key_event) {
-            match key {
-                DecodedKey::Unicode(character) => {
-                    // Handle the character
-                    handle_character(character);
-                }
-                DecodedKey::RawKey(key) => {
-                    // Handle special keys if needed
-                    // print!("{:?}", key);
-                }
+/// Take the next decoded key from the queue without dispatching it, for
+/// modal loops such as dialogs that read the keyboard themselves
+pub fn read_key() -> Option<DecodedKey> {
+    while let Some(scancode) = get_scancode() {
+        let mut keyboard = KEYBOARD.lock();
+        if let Ok(Some(key_event)) = keyboard.add_byte(scancode) {
+            let (code, state) = (key_event.code, key_event.state);
+            track_modifiers(code, state);
+            let decoded = keyboard.process_keyevent(key_event);
+            drop(keyboard);
+
+            // Håll KEY_REPEAT i takt, annars upprepas tangenten som öppnade
+            // dialogen när den stängs. Tangentbordets egna upprepningar
+            // släpps igenom, här finns ingen process_key_repeat som ersätter dem.
+            let now = crate::interrupts::uptime_ms();
+            KEY_REPEAT.lock().track(code, state, decoded, now);
+            if decoded.is_some() {
+                return decoded;
             }
         }
-    } else {
-        // Error decoding scancode, log but continue
-        log_warn!("Failed to decode scancode: {}", scancode);
+    }
+    None
+}
+
+/// Decode and handle every scancode queued by the interrupt handler
+pub fn process_keypress() {
+    while let Some(scancode) = get_scancode() {
+        handle_scancode(scancode);
     }
 }
 
 /// Get a scancode from the queue, or None if empty
 fn get_scancode() -> Option<u8> {
-    // Check if keyboard is initialized
     if !is_initialized() {
-        log_error!("Keyboard not initialized in get_scancode");
         return None;
     }
     
-    // Try to get a scancode from the queue
-    let mut queue_guard = SCANCODE_QUEUE.try_lock();
-    
-    if let Some(queue_guard) = queue_guard.as_mut() {
-        if let Some(queue) = queue_guard.as_mut() {
-            return queue.pop();
-        }
-    }
-    
-    // If we couldn't lock the queue, it might be in use by another interrupt
-    // Just skip this scancode to prevent deadlocks
-    None
-}
-
-/// Handle a character input
-fn handle_character(character: char) {
-    use crate::ui::UI;
-    
-    // Get a lock on the UI
-    match crate::ui::UI_STATE.try_lock() {
-        Some(mut ui) => {
-            // Handle the character in the UI
-            ui.handle_input(character);
-        },
-        None => {
-            // We couldn't get a lock on the UI
-            // This could happen during context switches or if there's contention
-            log_warn!("Could not get UI lock in keyboard handler");
-        }
-    }
+    // The main loop is the queue's only consumer
+    SCANCODE_QUEUE.pop()
 }
 
 /// Read a scancode directly from the keyboard controller
@@ -164,379 +376,146 @@ pub fn read_scancode() -> u8 {
     unsafe { port.read() }
 }
 
-// Print the command prompt
-fn print_prompt() {
-    print!("> ");
-}
-
 // Handle a scancode from the keyboard controller
 pub fn handle_scancode(scancode: u8) {
     let mut keyboard = KEYBOARD.lock();
     
     if let Ok(Some(key_event)) = keyboard.add_byte(scancode) {
-        if let Some(key) = keyboard.process_keyevent(key_event) {
-            process_special_key(key);
-            process_normal_key(key);
+        let (code, state) = (key_event.code, key_event.state);
+        track_modifiers(code, state);
+        
+        let decoded = keyboard.process_keyevent(key_event);
+        drop(keyboard);
+        
+        let now = crate::interrupts::uptime_ms();
+        if !KEY_REPEAT.lock().track(code, state, decoded, now) {
+            return;
+        }
+        
+        if let Some(key) = decoded {
+            dispatch_key(key);
         }
     }
 }
 
-/// Hantera 'speciella' tangentkombinationer och kortkommandon
-fn process_special_key(key: DecodedKey) {
-    match key {
-        DecodedKey::Unicode(c) => match c {
-            '\u{0003}' => println!("\nCtrl+C: Avbrott!"),
-            '\u{0008}' => {
-                if let Some(mut text_editor) = TEXT_EDITOR.try_lock() {
-                    if text_editor.visible {
-                        text_editor.handle_backspace();
-                        return;
-                    }
-                }
-                handle_backspace();
-            },
-            // F1 - hjälp
-            '\u{0011}' => {
-                if let Some(mut file_manager) = FILE_MANAGER.try_lock() {
-                    if file_manager.visible {
-                        file_manager.hide();
-                    } else {
-                        file_manager.show();
-                    }
-                    return;
-                }
-            },
-            // Escape - stäng textredigerare
-            '\u{001B}' => {
-                if let Some(mut text_editor) = TEXT_EDITOR.try_lock() {
-                    if text_editor.visible {
-                        text_editor.hide();
-                        return;
-                    }
-                }
-            },
-            // Ctrl+S - spara fil i redigeraren
-            '\u{0013}' => {
-                if let Some(mut text_editor) = TEXT_EDITOR.try_lock() {
-                    if text_editor.visible {
-                        text_editor.save_file();
-                        return;
-                    }
-                }
-            },
-            _ => {}
-        },
-        DecodedKey::RawKey(key) => match key {
-            KeyCode::ArrowUp => {
-                if let Some(mut file_manager) = FILE_MANAGER.try_lock() {
-                    if file_manager.visible {
-                        file_manager.navigate_up();
-                        return;
-                    }
-                }
-                if let Some(mut text_editor) = TEXT_EDITOR.try_lock() {
-                    if text_editor.visible {
-                        text_editor.move_up();
-                        return;
-                    }
-                }
-            },
-            KeyCode::ArrowDown => {
-                if let Some(mut file_manager) = FILE_MANAGER.try_lock() {
-                    if file_manager.visible {
-                        file_manager.navigate_down();
-                        return;
-                    }
-                }
-                if let Some(mut text_editor) = TEXT_EDITOR.try_lock() {
-                    if text_editor.visible {
-                        text_editor.move_down();
-                        return;
-                    }
-                }
-            },
-            KeyCode::ArrowLeft => {
-                if let Some(mut text_editor) = TEXT_EDITOR.try_lock() {
-                    if text_editor.visible {
-                        text_editor.move_left();
-                        return;
-                    }
-                }
-            },
-            KeyCode::ArrowRight => {
-                if let Some(mut text_editor) = TEXT_EDITOR.try_lock() {
-                    if text_editor.visible {
-                        text_editor.move_right();
-                        return;
-                    }
-                }
-            },
-            KeyCode::Delete => {
-                if let Some(mut text_editor) = TEXT_EDITOR.try_lock() {
-                    if text_editor.visible {
-                        text_editor.handle_delete();
-                        return;
-                    }
-                }
-            },
-            KeyCode::Return => {
-                if let Some(mut file_manager) = FILE_MANAGER.try_lock() {
-                    if file_manager.visible {
-                        file_manager.open_selected();
-                        return;
-                    }
-                }
-                handle_enter();
-            },
-            _ => {}
-        },
+// Pass a decoded key on to the shortcut handler or the focused input
+fn dispatch_key(key: DecodedKey) {
+    // A key that wakes the screensaver is not passed on
+    if crate::ui::screensaver::notify_input() {
+        return;
     }
-}
-
-/// Hantera vanlig teckenimatning
-fn process_normal_key(key: DecodedKey) {
-    match key {
-        DecodedKey::Unicode(c) => {
-            // Om textredigeraren är aktiv, skicka tecknet dit
-            if let Some(mut text_editor) = TEXT_EDITOR.try_lock() {
-                if text_editor.visible {
-                    text_editor.insert_char(c);
-                    return;
-                }
-            }
-            
-            // Annars skriv tecknet i terminalen
-            handle_printable_character(c);
-        },
-        DecodedKey::RawKey(_) => {},
+    
+    // Keys consumed as shortcuts are not typed
+    if !process_special_key(key) {
+        process_normal_key(key);
     }
 }
 
-/// Hantera backspace
-fn handle_backspace() {
-    let mut current_line = CURRENT_LINE.lock();
-    
-    if current_line.len() > 0 {
-        current_line.pop();
-        print!("\u{0008} \u{0008}"); // Ta bort tecknet från skärmen
+// Keep KEYBOARD_STATE in sync with the modifier keys
+fn track_modifiers(code: KeyCode, state: KeyState) {
+    let pressed = state == KeyState::Down;
+    let mut keyboard_state = KEYBOARD_STATE.lock();
+    match code {
+        KeyCode::LShift | KeyCode::RShift => keyboard_state.is_shift_pressed = pressed,
+        KeyCode::LControl | KeyCode::RControl => keyboard_state.is_ctrl_pressed = pressed,
+        KeyCode::LAlt | KeyCode::RAltGr => keyboard_state.is_alt_pressed = pressed,
+        // CapsLock toggles on each press, releasing it does nothing
+        KeyCode::CapsLock if pressed => keyboard_state.is_caps_lock = !keyboard_state.is_caps_lock,
+        _ => {}
     }
 }
 
-/// Hantera enter
-fn handle_enter() {
-    println!();
-    
-    // Skapa en kopia av kommandot istället för att hålla en referens
-    let command_copy = {
-        let current_line = CURRENT_LINE.lock();
-        if current_line.is_empty() {
-            crate::simple_fs::SimpleString::new()
-        } else {
-            let mut copy = crate::simple_fs::SimpleString::new();
-            copy.push_str(current_line.as_str());
-            copy
-        }
-    };
-    
-    if !command_copy.is_empty() {
-        process_command(&command_copy);
-    } else {
-        print_prompt();
+/// Current state of Shift, Ctrl, Alt and CapsLock
+pub fn modifiers() -> Modifiers {
+    let keyboard_state = KEYBOARD_STATE.lock();
+    Modifiers {
+        shift: keyboard_state.is_shift_pressed,
+        ctrl: keyboard_state.is_ctrl_pressed,
+        alt: keyboard_state.is_alt_pressed,
+        caps_lock: keyboard_state.is_caps_lock,
     }
-    
-    CURRENT_LINE.lock().clear();
 }
 
-/// Hantera skrivbara tecken
-fn handle_printable_character(c: char) {
-    if c.is_control() {
-        return;
-    }
-    
-    // Lägg till tecknet i kommandoraden
-    CURRENT_LINE.lock().push(c);
-    print!("{}", c);
+fn is_shift_pressed() -> bool {
+    modifiers().shift
 }
 
-/// Hantera kommandon i kommandoraden
-fn process_command(command: &SimpleString) {
-    // Enkel parsing av kommandoraden
-    let mut parts = [""; 10]; // Max 10 argument
-    let mut current_part = 0;
-    let mut start = 0;
-    
-    // Hitta alla icke-tomma delar av kommandot
-    for (i, c) in command.as_str().char_indices() {
-        if c.is_whitespace() {
-            if i > start {
-                if current_part < parts.len() {
-                    parts[current_part] = &command.as_str()[start..i];
-                    current_part += 1;
-                }
-            }
-            start = i + 1;
+/// Hantera globala kortkommandon som gäller oavsett vilken komponent
+/// som har fokus
+fn process_special_key(key: DecodedKey) -> bool {
+    // Filhanterarens menyrad får se tangenten först. Valet körs efter att
+    // låset släppts, eftersom åtgärderna själva låser filhanteraren.
+    let event = match FILE_MANAGER.try_lock() {
+        Some(mut file_manager) if file_manager.visible => file_manager.menu_key(key, modifiers().alt),
+        _ => MenuEvent::Ignored,
+    };
+    match event {
+        MenuEvent::Ignored => {}
+        MenuEvent::Consumed => return true,
+        MenuEvent::Selected(action) => {
+            action();
+            return true;
         }
     }
     
-    // Lägg till sista delen om den finns
-    if start < command.len() && current_part < parts.len() {
-        parts[current_part] = &command.as_str()[start..];
-        current_part += 1;
-    }
-    
-    if current_part == 0 {
-        print_prompt();
-        return;
-    }
-
-    let mut handled = true;
-    
-    match parts[0] {
-        "help" => {
-            println!("Available commands:");
-            println!("  help     - Display this help");
-            println!("  clear    - Clear the screen");
-            println!("  exit     - Exit ScreammOS");
-            println!("  sysinfo  - Display system information");
-            println!("  about    - Show information about ScreammOS");
-            println!("  edit     - Open the text editor with a file (e.g., edit file.txt)");
-            println!("  files    - Open the file manager");
-            println!("  theme    - Change color theme (theme dark|light|retro)");
-            println!("  write    - Write text to a file (e.g., write file.txt Hello world)");
-            println!("  cat      - Display the contents of a file (e.g., cat file.txt)");
-            println!("  ls       - List files in the current directory");
-            println!("\nUpcoming features:");
-            println!("  pwd, cd, mkdir, touch, echo");
-        },
-        "clear" => {
-            clear_screen();
-        },
-        "exit" => {
-            println!("Shutting down ScreammOS...");
-            x86_64::instructions::hlt();
-        },
-        "sysinfo" => {
-            println!("ScreammOS System Information");
-            println!("---------------------------");
-            println!("Version: 0.2.0");
-            println!("Features: Keyboard, Text Mode, Filesystem");
-            println!("Color Theme: {}", get_current_theme());
-        },
-        "about" => {
-            println!("ScreammOS");
-            println!("--------");
-            println!("An experimental DOS-inspired operating system");
-            println!("developed in Rust for x86_64 architecture.");
-            println!("\nFeatures:");
-            println!("- Keyboard support");
-            println!("- Text editor");
-            println!("- File manager");
-            println!("- Customizable color themes");
-        },
-        "edit" => {
-            if parts[1] != "" {
-                let filename = parts[1];
-                if let Some(mut text_editor) = TEXT_EDITOR.try_lock() {
-                    if text_editor.open_file(filename) {
-                        text_editor.show();
-                    } else {
-                        println!("Could not open file: {}", filename);
-                    }
-                }
-            } else {
-                println!("Usage: edit <filename>");
-            }
-        },
-        "files" => {
-            if let Some(mut file_manager) = FILE_MANAGER.try_lock() {
-                file_manager.show();
+    match key {
+        // Ctrl+Tab flyttar fokus, Ctrl+Shift+Tab bakåt. Vanlig Tab
+        // används redan för komplettering och indrag.
+        DecodedKey::Unicode('\t') if modifiers().ctrl => {
+            // Komponenten med fokus får använda Ctrl+Tab själv, editorn
+            // byter buffert med den
+            if FOCUS.lock().dispatch(key) {
+                return true;
             }
-        },
-        "theme" => {
-            if parts[1] != "" {
-                match parts[1] {
-                    "dark" => set_theme(Theme::Modern),
-                    "light" => set_theme(Theme::Classic),
-                    "retro" => set_theme(Theme::Green),
-                    _ => println!("Invalid theme. Use: dark, light, or retro"),
-                }
+            let mut focus = FOCUS.lock();
+            if is_shift_pressed() {
+                focus.focus_prev();
             } else {
-                println!("Specify a theme: dark, light, or retro");
+                focus.focus_next();
             }
+            true
         },
-        "write" => {
-            if parts[1] != "" {
-                let filename = parts[1];
-                // Combine all remaining parts as text content
-                let mut content = SimpleString::new();
-                
-                for i in 2..parts.len() {
-                    if parts[i] == "" {
-                        break;
-                    }
-                    
-                    if i > 2 {
-                        content.push(' ');
-                    }
-                    content.push_str(parts[i]);
-                }
-                
-                let mut fs = crate::simple_fs::FILESYSTEM.lock();
-                match fs.create_file(filename, content.as_str()) {
-                    Ok(_) => println!("Wrote to file: {}", filename),
-                    Err(_) => println!("Could not write to file: {}", filename),
-                }
-            } else {
-                println!("Usage: write <filename> <content>");
-            }
+        DecodedKey::RawKey(KeyCode::PageUp) => {
+            crate::vga_buffer::WRITER.lock().scroll_up(BUFFER_HEIGHT / 2);
+            true
         },
-        "cat" => {
-            if parts[1] != "" {
-                let filename = parts[1];
-                let fs = crate::simple_fs::FILESYSTEM.lock();
-                match fs.read_file(filename) {
-                    Ok(content) => {
-                        println!("----- {} -----", filename);
-                        println!("{}", content);
-                        println!("----- End of {} -----", filename);
-                    },
-                    Err(_) => println!("Could not read file: {}", filename),
-                }
-            } else {
-                println!("Usage: cat <filename>");
-            }
+        DecodedKey::RawKey(KeyCode::PageDown) => {
+            crate::vga_buffer::WRITER.lock().scroll_down(BUFFER_HEIGHT / 2);
+            true
         },
-        "ls" => {
-            let fs = crate::simple_fs::FILESYSTEM.lock();
-            println!("Contents of current directory:");
-            let mut found = false;
-            
-            for (file_type, name, size) in fs.list_directory() {
-                let type_str = match file_type {
-                    crate::simple_fs::FileType::Regular => "File",
-                    crate::simple_fs::FileType::Directory => "Dir",
-                };
-                println!("{:<5} {:<20} {:>8} bytes", type_str, name, size);
-                found = true;
-            }
-            
-            if !found {
-                println!("(Directory is empty)");
+        _ => false,
+    }
+}
+
+/// Hantera Ctrl+bokstav som komponenten med fokus inte använde
+fn process_ctrl_key(c: char) {
+    match c {
+        'c' => println!("\nCtrl+C: Avbrott!"),
+        // Ctrl+Q - visa/dölj filhanteraren
+        'q' => {
+            if let Some(mut file_manager) = FILE_MANAGER.try_lock() {
+                if file_manager.visible {
+                    file_manager.hide();
+                } else {
+                    file_manager.show();
+                }
             }
         },
-        _ => {
-            handled = false;
-        }
+        _ => {}
     }
-    
-    // If the command wasn't handled, show an error message
-    if !handled {
-        println!("Unknown command: {}", command.as_str());
-        println!("Type 'help' for help");
+}
+
+/// Skicka tangenten till komponenten som har fokus
+fn process_normal_key(key: DecodedKey) {
+    if FOCUS.lock().dispatch(key) {
+        return;
     }
     
-    // Visa prompten igen efter kommandot
-    print_prompt();
+    if let DecodedKey::Unicode(c) = key {
+        if modifiers().ctrl {
+            process_ctrl_key(c.to_ascii_lowercase());
+        }
+    }
 }
 
 // Hjälpfunktion för att hämta nästa tecken från tangentbordet
@@ -568,38 +547,6 @@ fn next_character() -> Option<char> {
     None
 }
 
-// Hjälpfunktion för att dela upp kommandoraden i delar
-fn parse_command(command: &str) -> [&str; 16] {
-    let mut result = [""; 16];
-    let mut in_part = false;
-    let mut start = 0;
-    let mut index = 0;
-    
-    for (i, c) in command.char_indices() {
-        if c.is_whitespace() {
-            if in_part {
-                if index < result.len() {
-                    result[index] = &command[start..i];
-                    index += 1;
-                }
-                in_part = false;
-            }
-        } else {
-            if !in_part {
-                start = i;
-                in_part = true;
-            }
-        }
-    }
-    
-    // Lägg till den sista delen om det finns en
-    if in_part && index < result.len() {
-        result[index] = &command[start..];
-    }
-    
-    result
-}
-
 // Command buffer to store characters as they are typed
 pub struct CommandBuffer {
     buffer: [u8; 256], // Maximum command length
@@ -652,4 +599,64 @@ impl CommandBuffer {
         }
         self.position = 0;
     }
-} 
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn drain_reads_all_pending_bytes() {
+        use core::cell::Cell;
+
+        let pending = [0x1Cu8, 0x9C, 0xFA];
+        let index = Cell::new(0);
+        let drained = drain_output_buffer(|| index.get() < pending.len(), || {
+            let byte = pending[index.get()];
+            index.set(index.get() + 1);
+            byte
+        });
+        assert_eq!(drained, 3);
+    }
+
+    #[test_case]
+    fn drain_is_bounded_when_buffer_never_empties() {
+        let mut reads = 0;
+        let drained = drain_output_buffer(|| true, || {
+            reads += 1;
+            0xFF
+        });
+        assert_eq!(drained, PS2_MAX_DRAIN_READS);
+        assert_eq!(reads, PS2_MAX_DRAIN_READS);
+    }
+
+    #[test_case]
+    fn held_key_repeats_after_delay() {
+        let mut repeat = KeyRepeat::new();
+        let key = Some(DecodedKey::Unicode('a'));
+        assert!(repeat.track(KeyCode::A, KeyState::Down, key, 1000));
+
+        assert_eq!(repeat.next_due(1000 + DEFAULT_REPEAT_DELAY_MS - 1), None);
+        assert_eq!(repeat.next_due(1000 + DEFAULT_REPEAT_DELAY_MS), key);
+        assert_eq!(repeat.next_due(1000 + DEFAULT_REPEAT_DELAY_MS), None);
+        assert_eq!(repeat.next_due(1000 + DEFAULT_REPEAT_DELAY_MS + DEFAULT_REPEAT_INTERVAL_MS), key);
+    }
+
+    #[test_case]
+    fn release_cancels_repeat_and_hardware_repeats_are_dropped() {
+        let mut repeat = KeyRepeat::new();
+        let key = Some(DecodedKey::Unicode('a'));
+        repeat.track(KeyCode::A, KeyState::Down, key, 0);
+        assert!(!repeat.track(KeyCode::A, KeyState::Down, key, 10));
+
+        repeat.track(KeyCode::A, KeyState::Up, None, 20);
+        assert_eq!(repeat.next_due(10_000), None);
+    }
+
+    #[test_case]
+    fn modifiers_never_repeat() {
+        let mut repeat = KeyRepeat::new();
+        assert!(repeat.track(KeyCode::LShift, KeyState::Down, None, 0));
+        assert!(repeat.track(KeyCode::LShift, KeyState::Down, None, 10));
+        assert_eq!(repeat.next_due(10_000), None);
+    }
+}
@@ -0,0 +1,186 @@
+// src/keymap.rs
+// Runtime-selectable keyboard layouts
+
+use pc_keyboard::{
+    layouts, DecodedKey, Error, HandleControl, KeyCode, KeyEvent, Keyboard, KeyboardLayout,
+    Modifiers, ScancodeSet1,
+};
+
+/// Keyboard layouts that can be selected at runtime
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layout {
+    Us,
+    Swedish,
+    Dvorak,
+}
+
+impl Layout {
+    pub const ALL: [Layout; 3] = [Layout::Us, Layout::Swedish, Layout::Dvorak];
+
+    /// Short name used by the `keymap` command
+    pub fn name(self) -> &'static str {
+        match self {
+            Layout::Us => "us",
+            Layout::Swedish => "se",
+            Layout::Dvorak => "dvorak",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Layout::Us => "US 104-key",
+            Layout::Swedish => "Swedish 105-key",
+            Layout::Dvorak => "US Dvorak",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Layout> {
+        Layout::ALL.iter().copied().find(|layout| layout.name() == name)
+    }
+}
+
+/// `pc_keyboard::Keyboard` is generic over its layout, so switching layouts
+/// at runtime means switching between differently typed keyboards
+pub enum LayoutKeyboard {
+    Us(Keyboard<layouts::Us104Key, ScancodeSet1>),
+    Swedish(Keyboard<Swedish105Key, ScancodeSet1>),
+    Dvorak(Keyboard<layouts::Dvorak104Key, ScancodeSet1>),
+}
+
+impl LayoutKeyboard {
+    pub fn new(layout: Layout, handle_ctrl: HandleControl) -> Self {
+        match layout {
+            Layout::Us => LayoutKeyboard::Us(Keyboard::new(ScancodeSet1::new(), layouts::Us104Key, handle_ctrl)),
+            Layout::Swedish => LayoutKeyboard::Swedish(Keyboard::new(ScancodeSet1::new(), Swedish105Key, handle_ctrl)),
+            Layout::Dvorak => LayoutKeyboard::Dvorak(Keyboard::new(ScancodeSet1::new(), layouts::Dvorak104Key, handle_ctrl)),
+        }
+    }
+
+    pub fn layout(&self) -> Layout {
+        match self {
+            LayoutKeyboard::Us(_) => Layout::Us,
+            LayoutKeyboard::Swedish(_) => Layout::Swedish,
+            LayoutKeyboard::Dvorak(_) => Layout::Dvorak,
+        }
+    }
+
+    pub fn add_byte(&mut self, byte: u8) -> Result<Option<KeyEvent>, Error> {
+        match self {
+            LayoutKeyboard::Us(keyboard) => keyboard.add_byte(byte),
+            LayoutKeyboard::Swedish(keyboard) => keyboard.add_byte(byte),
+            LayoutKeyboard::Dvorak(keyboard) => keyboard.add_byte(byte),
+        }
+    }
+
+    pub fn process_keyevent(&mut self, event: KeyEvent) -> Option<DecodedKey> {
+        match self {
+            LayoutKeyboard::Us(keyboard) => keyboard.process_keyevent(event),
+            LayoutKeyboard::Swedish(keyboard) => keyboard.process_keyevent(event),
+            LayoutKeyboard::Dvorak(keyboard) => keyboard.process_keyevent(event),
+        }
+    }
+}
+
+/// Swedish ISO layout. Letters and most keys sit where they do on a US
+/// keyboard, so only the differing keys are mapped here.
+pub struct Swedish105Key;
+
+impl KeyboardLayout for Swedish105Key {
+    fn map_keycode(&self, keycode: KeyCode, modifiers: &Modifiers, handle_ctrl: HandleControl) -> DecodedKey {
+        let shifted = modifiers.is_shifted();
+        let pick = |plain: char, shift: char| DecodedKey::Unicode(if shifted { shift } else { plain });
+        let letter = |lower: char, upper: char| DecodedKey::Unicode(if modifiers.is_caps() { upper } else { lower });
+
+        // AltGr symbols
+        if modifiers.alt_gr {
+            let symbol = match keycode {
+                KeyCode::Key2 => Some('@'),
+                KeyCode::Key3 => Some('£'),
+                KeyCode::Key4 => Some('$'),
+                KeyCode::Key7 => Some('{'),
+                KeyCode::Key8 => Some('['),
+                KeyCode::Key9 => Some(']'),
+                KeyCode::Key0 => Some('}'),
+                KeyCode::OemMinus => Some('\\'),
+                KeyCode::Oem6 => Some('~'),
+                KeyCode::Oem5 => Some('|'),
+                _ => None,
+            };
+            if let Some(symbol) = symbol {
+                return DecodedKey::Unicode(symbol);
+            }
+        }
+
+        match keycode {
+            KeyCode::Oem8 => pick('§', '½'),
+            KeyCode::Key2 if shifted => DecodedKey::Unicode('"'),
+            KeyCode::Key3 if shifted => DecodedKey::Unicode('#'),
+            KeyCode::Key4 if shifted => DecodedKey::Unicode('¤'),
+            KeyCode::Key6 if shifted => DecodedKey::Unicode('&'),
+            KeyCode::Key7 if shifted => DecodedKey::Unicode('/'),
+            KeyCode::Key8 if shifted => DecodedKey::Unicode('('),
+            KeyCode::Key9 if shifted => DecodedKey::Unicode(')'),
+            KeyCode::Key0 if shifted => DecodedKey::Unicode('='),
+            KeyCode::OemMinus => pick('+', '?'),
+            KeyCode::OemPlus => pick('´', '`'),
+            KeyCode::Oem4 => letter('å', 'Å'),
+            KeyCode::Oem6 => pick('¨', '^'),
+            KeyCode::Oem1 => letter('ö', 'Ö'),
+            KeyCode::Oem3 => letter('ä', 'Ä'),
+            KeyCode::Oem7 => pick('\'', '*'),
+            KeyCode::Oem5 => pick('<', '>'),
+            KeyCode::OemComma => pick(',', ';'),
+            KeyCode::OemPeriod => pick('.', ':'),
+            KeyCode::Oem2 => pick('-', '_'),
+            _ => layouts::Us104Key.map_keycode(keycode, modifiers, handle_ctrl),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn layout_names_round_trip() {
+        for layout in Layout::ALL.iter() {
+            assert_eq!(Layout::from_name(layout.name()), Some(*layout));
+        }
+        assert_eq!(Layout::from_name("klingon"), None);
+    }
+
+    // Press a key by its set 1 make code and return what it decodes to
+    fn press(keyboard: &mut LayoutKeyboard, scancode: u8) -> Option<DecodedKey> {
+        let event = keyboard.add_byte(scancode).ok()??;
+        keyboard.process_keyevent(event)
+    }
+
+    #[test_case]
+    fn swedish_maps_national_letters() {
+        let mut keyboard = LayoutKeyboard::new(Layout::Swedish, HandleControl::Ignore);
+        assert_eq!(press(&mut keyboard, 0x1A), Some(DecodedKey::Unicode('å')));
+        assert_eq!(press(&mut keyboard, 0x28), Some(DecodedKey::Unicode('ä')));
+        assert_eq!(press(&mut keyboard, 0x27), Some(DecodedKey::Unicode('ö')));
+        assert_eq!(press(&mut keyboard, 0x1E), Some(DecodedKey::Unicode('a')));
+    }
+
+    #[test_case]
+    fn swedish_altgr_types_symbols() {
+        let mut keyboard = LayoutKeyboard::new(Layout::Swedish, HandleControl::Ignore);
+        // Right Alt is E0 38, its release E0 B8
+        assert_eq!(keyboard.add_byte(0xE0), Ok(None));
+        assert_eq!(press(&mut keyboard, 0x38), Some(DecodedKey::RawKey(KeyCode::RAltGr)));
+        assert_eq!(press(&mut keyboard, 0x03), Some(DecodedKey::Unicode('@')));
+        assert_eq!(press(&mut keyboard, 0x09), Some(DecodedKey::Unicode('[')));
+        assert_eq!(keyboard.add_byte(0xE0), Ok(None));
+        assert_eq!(press(&mut keyboard, 0xB8), None);
+        assert_eq!(press(&mut keyboard, 0x03), Some(DecodedKey::Unicode('2')));
+    }
+
+    #[test_case]
+    fn dvorak_moves_letters() {
+        // The key labelled S on a US keyboard types 'o' in Dvorak
+        let mut keyboard = LayoutKeyboard::new(Layout::Dvorak, HandleControl::Ignore);
+        assert_eq!(press(&mut keyboard, 0x1F), Some(DecodedKey::Unicode('o')));
+    }
+}
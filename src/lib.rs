@@ -7,38 +7,41 @@
 #![reexport_test_harness_main = "test_main"]
 #![allow(dead_code)]
 
+extern crate alloc;
+
 use core::panic::PanicInfo;
 use core::fmt::Arguments;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use crate::vga_buffer::_print;
 use crate::simple_fs::SimpleString;
 
 pub mod vga_buffer;
 pub mod interrupts;
+pub mod memory;
 pub mod keyboard;
+pub mod keymap;
 pub mod ui;
 pub mod simple_fs;
+pub mod ata;
+pub mod rtc;
 pub mod gdt;
 pub mod logger;
 pub mod queue;
 pub mod error_handler;
 pub mod string_ext;
+pub mod sound;
+pub mod cpu;
+pub mod serial;
 
-pub mod ui {
-    pub mod window_manager;
-    pub mod command_line;
-    pub mod text_editor;
-    pub mod file_manager;
-    pub mod splash_screen;
-    pub mod retro_commands;
+#[macro_export]
+macro_rules! format {
+    ($($arg:tt)*) => ($crate::vga_buffer::_format(format_args!($($arg)*)));
 }
 
+/// Like `format!`, but reuses the buffer of an existing `SimpleString`
 #[macro_export]
-macro_rules! format {
-    ($($arg:tt)*) => ({
-        let mut s = SimpleString::new();
-        s.write_fmt(format_args!($($arg)*)).unwrap();
-        s
-    });
+macro_rules! format_into {
+    ($buf:expr, $($arg:tt)*) => ($crate::vga_buffer::format_into($buf, format_args!($($arg)*)));
 }
 
 #[macro_export]
@@ -52,11 +55,37 @@ macro_rules! println {
     ($($arg:tt)*) => ($crate::print!("{}\n", format_args!($($arg)*)));
 }
 
+/// Like `print!`, but to the first serial port
+#[macro_export]
+macro_rules! serial_print {
+    ($($arg:tt)*) => ($crate::serial::_print(format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! serial_println {
+    () => ($crate::serial_print!("\n"));
+    ($($arg:tt)*) => ($crate::serial_print!("{}\n", format_args!($($arg)*)));
+}
+
+// Lets vec! name Vec without the caller importing it
+#[doc(hidden)]
+pub use alloc::vec::Vec as __Vec;
+
+/// `vec![a, b, c]` or `vec![elem; n]`, allocated at the final size up front
 #[macro_export]
 macro_rules! vec {
-    ($($x:expr),*) => (
+    (@unit $x:expr) => (());
+    ($elem:expr; $n:expr) => (
+        {
+            let count = $n;
+            let mut temp_vec = $crate::__Vec::with_capacity(count);
+            temp_vec.resize(count, $elem);
+            temp_vec
+        }
+    );
+    ($($x:expr),* $(,)?) => (
         {
-            let mut temp_vec = Vec::new();
+            let mut temp_vec = $crate::__Vec::with_capacity(<[()]>::len(&[$($crate::vec!(@unit $x)),*]));
             $(
                 temp_vec.push($x);
             )*
@@ -117,17 +146,28 @@ fn test_kernel_main(_boot_info: &'static BootInfo) -> ! {
     hlt_loop();
 }
 
-pub fn test_runner(tests: &[&dyn Fn()]) {
-    println!("Running {} tests", tests.len());
+// Counted across the run for the summary, which also prints on a failure
+static TESTS_TOTAL: AtomicUsize = AtomicUsize::new(0);
+static TESTS_PASSED: AtomicUsize = AtomicUsize::new(0);
+
+pub fn test_runner(tests: &[&dyn Testable]) {
+    serial_println!("Running {} tests", tests.len());
+    TESTS_TOTAL.store(tests.len(), Ordering::SeqCst);
     for test in tests {
-        test();
+        test.run();
     }
+    serial_println!("{} passed, 0 failed", TESTS_PASSED.load(Ordering::SeqCst));
     exit_qemu(QemuExitCode::Success);
 }
 
+/// Report the failed test and end the run. There is no unwinding, so a
+/// test that is meant to panic belongs in its own binary under `tests/`.
 pub fn test_panic_handler(info: &core::panic::PanicInfo) -> ! {
-    println!("[failed]\n");
-    println!("Error: {}\n", info);
+    serial_println!("[failed]\n");
+    serial_println!("Error: {}\n", info);
+    let passed = TESTS_PASSED.load(Ordering::SeqCst);
+    let not_run = TESTS_TOTAL.load(Ordering::SeqCst).saturating_sub(passed + 1);
+    serial_println!("{} passed, 1 failed, {} not run", passed, not_run);
     exit_qemu(QemuExitCode::Failed);
     hlt_loop();
 }
@@ -164,15 +204,16 @@ where
     T: Fn(),
 {
     fn run(&self) {
-        println!("{}...\t", core::any::type_name::<T>());
+        serial_print!("{}...\t", core::any::type_name::<T>());
         self();
-        println!("[ok]");
+        serial_println!("[ok]");
+        TESTS_PASSED.fetch_add(1, Ordering::SeqCst);
     }
 }
 
 /// Startar om systemet
 pub fn reboot() -> ! {
-    println!("Startar om...");
+    println!("Restarting...");
     
     // Använd 8042 PS/2-kontrollern för att utlösa en systemomstart
     use x86_64::instructions::port::Port;
@@ -185,6 +226,36 @@ pub fn reboot() -> ! {
     hlt_loop();
 }
 
+// Power-off ports of emulators that don't need a full ACPI table walk:
+// QEMU (current and pre-2.0 / Bochs) and VirtualBox
+const POWER_OFF_PORTS: [u16; 3] = [0x604, 0xB004, 0x4004];
+const POWER_OFF_COMMAND: u16 = 0x2000;
+
+/// Stänger av datorn
+pub fn shutdown() -> ! {
+    use x86_64::instructions::port::Port;
+    
+    println!("Shutting down...");
+    x86_64::instructions::interrupts::disable();
+    
+    for &port in POWER_OFF_PORTS.iter() {
+        unsafe { Port::<u16>::new(port).write(POWER_OFF_COMMAND) };
+    }
+    
+    // Ingen port fungerade, be användaren stänga av själv
+    vga_buffer::clear_screen();
+    let message = "It is now safe to turn off your computer.";
+    {
+        let mut writer = vga_buffer::WRITER.lock();
+        let x = (vga_buffer::BUFFER_WIDTH - message.len()) / 2;
+        let y = vga_buffer::BUFFER_HEIGHT / 2;
+        for (i, c) in message.chars().enumerate() {
+            writer.write_char_at(x + i, y, c, vga_buffer::Color::Yellow, vga_buffer::Color::Black);
+        }
+    }
+    hlt_loop();
+}
+
 pub fn run_self_tests() {
     println!("Running self tests...");
     // Add self tests here
@@ -209,12 +280,33 @@ fn panic(info: &PanicInfo) -> ! {
     loop {}
 }
 
+#[cfg(test)]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    test_panic_handler(info)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::vec::Vec;
 
     #[test_case]
     fn trivial_assertion() {
         assert_eq!(1, 1);
     }
+
+    #[test_case]
+    fn vec_macro_is_sized_up_front() {
+        let list: Vec<u8> = crate::vec![1, 2, 3,];
+        assert_eq!(list, [1, 2, 3]);
+        assert_eq!(list.capacity(), 3);
+
+        let repeated = crate::vec![7u16; 5];
+        assert_eq!(repeated, [7; 5]);
+        assert_eq!(repeated.capacity(), 5);
+
+        let empty: Vec<u8> = crate::vec![];
+        assert!(empty.is_empty());
+    }
 } 
\ No newline at end of file
@@ -1,12 +1,16 @@
 // src/logger.rs
 // Simple logging system for ScreammOS
 
-use core::fmt::{self, Write};
 use lazy_static::lazy_static;
 use spin::Mutex;
+use core::sync::atomic::{AtomicU64, Ordering};
 use crate::simple_fs::{FILESYSTEM, SimpleString};
 use crate::print;
 
+const LOG_FILE: &str = "system.log";
+const LOG_FILE_LIMIT: usize = 4096;  // system.log keeps only its newest lines
+const FLUSH_LINES: usize = 16;       // Lines queued before system.log is written
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum LogLevel {
     Debug,
@@ -16,6 +20,26 @@ pub enum LogLevel {
     Critical,
 }
 
+impl LogLevel {
+    /// Name used by the `log level` command
+    pub fn name(self) -> &'static str {
+        match self {
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warning => "warn",
+            LogLevel::Error => "error",
+            LogLevel::Critical => "crit",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<LogLevel> {
+        [LogLevel::Debug, LogLevel::Info, LogLevel::Warning, LogLevel::Error, LogLevel::Critical]
+            .iter()
+            .copied()
+            .find(|level| level.name() == name)
+    }
+}
+
 pub struct Logger {
     level: LogLevel,
     buffer: [Option<SimpleString>; 50],  // Circular buffer for last 50 log entries
@@ -23,17 +47,22 @@ pub struct Logger {
     buffer_full: bool,
     log_to_console: bool,
     log_to_file: bool,
+    pending: SimpleString,  // Lines not yet written to system.log
+    pending_lines: usize,
 }
 
 impl Logger {
     pub const fn new() -> Self {
+        const NO_ENTRY: Option<SimpleString> = None;
         Self {
             level: LogLevel::Info,
-            buffer: [None; 50],
+            buffer: [NO_ENTRY; 50],
             buffer_index: 0,
             buffer_full: false,
             log_to_console: true,
             log_to_file: true,
+            pending: SimpleString::new(),
+            pending_lines: 0,
         }
     }
 
@@ -49,10 +78,21 @@ impl Logger {
         self.log_to_file = enabled;
     }
 
+    pub fn level(&self) -> LogLevel {
+        self.level
+    }
+
+    pub fn logs_to_console(&self) -> bool {
+        self.log_to_console
+    }
+
+    pub fn logs_to_file(&self) -> bool {
+        self.log_to_file
+    }
+
     pub fn log(&mut self, level: LogLevel, message: &str) {
         if level as u8 >= self.level as u8 {
-            // Format timestamp (simple counter for now)
-            let timestamp = unsafe { SYSTEM_TIMER };
+            let timestamp = timestamp_us();
             
             // Format log message
             let mut entry = SimpleString::new();
@@ -64,34 +104,10 @@ impl Logger {
                 LogLevel::Critical => entry.push_str("[CRIT]"),
             }
             
-            entry.push_str(" [");
-            // Add timestamp as a simple number
-            let mut tmp = timestamp;
-            if tmp == 0 {
-                entry.push('0');
-            } else {
-                let mut digits = [0u8; 20];
-                let mut i = 0;
-                while tmp > 0 {
-                    digits[i] = (tmp % 10) as u8 + b'0';
-                    tmp /= 10;
-                    i += 1;
-                }
-                while i > 0 {
-                    i -= 1;
-                    entry.push(digits[i] as char);
-                }
-            }
-            entry.push_str("] ");
+            push_timestamp(timestamp, &mut entry);
+            entry.push(' ');
             entry.push_str(message);
             
-            // Store in circular buffer
-            self.buffer[self.buffer_index] = Some(entry);
-            self.buffer_index = (self.buffer_index + 1) % self.buffer.len();
-            if self.buffer_index == 0 {
-                self.buffer_full = true;
-            }
-            
             // Output to console if enabled
             if self.log_to_console {
                 match level {
@@ -104,88 +120,153 @@ impl Logger {
                 print!("{}\x1B[0m\n", entry.as_str());
             }
             
+            // Headless runs only see the serial port
+            if self.log_to_console || self.log_to_file {
+                crate::serial_println!("{}", entry.as_str());
+            }
+            
             // Save to log file if enabled
             if self.log_to_file {
-                let _ = self.append_to_log_file(entry.as_str());
+                self.append_to_log_file(entry.as_str());
+            }
+            
+            // Store in circular buffer, last since it takes the entry
+            self.buffer[self.buffer_index] = Some(entry);
+            self.buffer_index = (self.buffer_index + 1) % self.buffer.len();
+            if self.buffer_index == 0 {
+                self.buffer_full = true;
             }
         }
     }
     
-    fn append_to_log_file(&self, message: &str) -> Result<(), &'static str> {
-        // For now, we'll simply create a new file each time
-        // In a real implementation, we would append to an existing file
-        let mut fs = FILESYSTEM.lock();
-        
-        // Try to read existing log
-        let current_content = match fs.read_file("system.log") {
-            Ok(content) => {
-                let mut s = SimpleString::new();
-                s.push_str(content);
-                s
-            },
-            Err(_) => SimpleString::new(),
-        };
+    // Queue a line for system.log, written out in batches by flush()
+    fn append_to_log_file(&mut self, message: &str) {
+        self.pending.push_str(message);
+        self.pending.push('\n');
+        self.pending_lines += 1;
         
-        // Append new message
-        let mut new_content = SimpleString::new();
-        if current_content.len() > 0 {
-            new_content.push_str(current_content.as_str());
-            new_content.push_str("\n");
+        if self.pending_lines >= FLUSH_LINES {
+            self.flush();
         }
-        new_content.push_str(message);
+    }
+    
+    /// Write queued lines to system.log in the root, keeping only the last
+    /// LOG_FILE_LIMIT bytes of the file
+    pub fn flush(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        
+        // The file system logs its own errors, so it may already be
+        // locked by this CPU. Keep the lines queued and try again later.
+        let mut fs = match FILESYSTEM.try_lock() {
+            Some(fs) => fs,
+            None => return,
+        };
         
-        // Truncate if too long (keep last 4KB)
-        let max_log_size = 4096;
-        if new_content.len() > max_log_size {
-            // Find a newline near the cutoff point
-            let mut cutoff = new_content.len() - max_log_size;
-            while cutoff < new_content.len() {
-                if new_content.as_str().as_bytes()[cutoff] == b'\n' {
-                    cutoff += 1;
-                    break;
+        let pending = self.pending.as_str();
+        fs.in_root(|fs| {
+            let existing = fs.read_file(LOG_FILE).map(|content| content.len()).ok();
+            let appended = match existing {
+                Some(len) if len + pending.len() <= LOG_FILE_LIMIT => {
+                    fs.append_file(LOG_FILE, pending)
                 }
-                cutoff += 1;
-            }
+                _ => false,
+            };
             
-            // Create a new string with just the tail
-            let mut truncated = SimpleString::new();
-            truncated.push_str(&new_content.as_str()[cutoff..]);
-            new_content = truncated;
-        }
+            if !appended {
+                let mut log = SimpleString::new();
+                if let Ok(content) = fs.read_file(LOG_FILE) {
+                    log.push_str(content);
+                }
+                log.push_str(pending);
+                fs.write_file(LOG_FILE, log_tail(log.as_str(), LOG_FILE_LIMIT));
+            }
+        });
         
-        // Write back to file
-        match fs.create_file("system.log", new_content.as_str()) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(e),
-        }
+        self.pending.clear();
+        self.pending_lines = 0;
     }
     
-    pub fn dump_log(&self) {
+    /// Buffered log entries, oldest first
+    pub fn entries(&self) -> impl Iterator<Item = &str> + '_ {
         let count = if self.buffer_full { self.buffer.len() } else { self.buffer_index };
         let start = if self.buffer_full { self.buffer_index } else { 0 };
         
+        (0..count).filter_map(move |i| {
+            self.buffer[(start + i) % self.buffer.len()].as_ref().map(|entry| entry.as_str())
+        })
+    }
+    
+    pub fn dump_log(&self) {
+        let count = self.entries().count();
+        
         print!("\n--- System Log (Last {} entries) ---\n", count);
-        for i in 0..count {
-            let index = (start + i) % self.buffer.len();
-            if let Some(entry) = self.buffer[index] {
-                print!("{}\n", entry.as_str());
-            }
+        for entry in self.entries() {
+            print!("{}\n", entry);
         }
         print!("--- End of Log ---\n\n");
     }
 }
 
+// The last `limit` bytes of `log`, starting at a line boundary
+fn log_tail(log: &str, limit: usize) -> &str {
+    if log.len() <= limit {
+        return log;
+    }
+    let start = log.len() - limit;
+    match log.as_bytes()[start..].iter().position(|&b| b == b'\n') {
+        Some(offset) => &log[start + offset + 1..],
+        None => "",
+    }
+}
+
 // Global logger instance
 lazy_static! {
     pub static ref LOGGER: Mutex<Logger> = Mutex::new(Logger::new());
 }
 
-// System timer for timestamps (incremented by timer interrupt)
-static mut SYSTEM_TIMER: u64 = 0;
+/// Current value of the system timer in ticks since boot, the same
+/// clock `uptime` reads
+pub fn now() -> u64 {
+    crate::interrupts::ticks()
+}
+
+// TSC cycles per microsecond, 0 until calibrated or without a TSC
+static CYCLES_PER_US: AtomicU64 = AtomicU64::new(0);
+// TSC value at calibration, so timestamps start near zero
+static TSC_AT_BOOT: AtomicU64 = AtomicU64::new(0);
+// Newest timestamp handed out, keeps them from going backwards
+static LAST_TIMESTAMP_US: AtomicU64 = AtomicU64::new(0);
+
+/// Raw TSC cycle count, or None if the CPU has no time stamp counter
+pub fn timestamp_cycles() -> Option<u64> {
+    if crate::cpu::has_tsc() {
+        Some(unsafe { core::arch::x86_64::_rdtsc() })
+    } else {
+        None
+    }
+}
+
+/// Microseconds since boot. Uses the calibrated TSC when there is one,
+/// otherwise the PIT tick counter. Never smaller than a value returned
+/// before.
+pub fn timestamp_us() -> u64 {
+    let cycles_per_us = CYCLES_PER_US.load(Ordering::Relaxed);
+    // Only set after calibration found a TSC, so no need to ask cpuid again
+    let now = if cycles_per_us != 0 {
+        let cycles = unsafe { core::arch::x86_64::_rdtsc() };
+        cycles.saturating_sub(TSC_AT_BOOT.load(Ordering::Relaxed)) / cycles_per_us
+    } else {
+        crate::interrupts::uptime_us()
+    };
+    LAST_TIMESTAMP_US.fetch_max(now, Ordering::Relaxed).max(now)
+}
 
-// Function to increment the system timer (called from timer interrupt handler)
-pub fn increment_timer() {
-    unsafe { SYSTEM_TIMER += 1; }
+// `[seconds.micros]`, like the kernel log on other systems
+fn push_timestamp(us: u64, entry: &mut SimpleString) {
+    use core::fmt::Write;
+    let _ = write!(entry, "[{}.{:06}]", us / 1_000_000, us % 1_000_000);
 }
 
 // Convenience macros for logging
@@ -251,31 +332,59 @@ macro_rules! log_crit {
 
 // Initialize the logger
 pub fn init() {
+    if let Some((cycles_per_us, start)) = crate::interrupts::calibrate_tsc() {
+        TSC_AT_BOOT.store(start, Ordering::Relaxed);
+        CYCLES_PER_US.store(cycles_per_us, Ordering::Relaxed);
+    }
     log_info!("Logger initialized");
 }
 
-pub fn log_to_file(level: LogLevel, message: &str) {
-    let mut fs = FILESYSTEM.lock();
-    let timestamp = get_timestamp();
-    let level_str = match level {
-        LogLevel::Info => "INFO",
-        LogLevel::Warning => "WARN",
-        LogLevel::Error => "ERROR",
-        LogLevel::Critical => "CRIT",
-    };
-    
-    let log_entry = format!("[{}] {}: {}\n", timestamp, level_str, message);
-    
-    let current_content = match fs.read_file("system.log") {
-        Some(content) => content,
-        None => "",
-    };
-    
-    let new_content = format!("{}{}", current_content, log_entry);
-    fs.write_file("system.log", &new_content);
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quiet_logger() -> Logger {
+        let mut logger = Logger::new();
+        logger.set_log_to_console(false);
+        logger.set_log_to_file(false);
+        logger
+    }
+
+    #[test_case]
+    fn debug_entries_are_kept_at_debug_level() {
+        let mut logger = quiet_logger();
+        logger.set_level(LogLevel::Debug);
+        logger.log(LogLevel::Debug, "probe");
 
-fn get_timestamp() -> &'static str {
-    // TODO: Implementera riktig tidsstämpel när vi har RTC
-    "00:00:00"
-} 
\ No newline at end of file
+        let entry = logger.entries().last().expect("no log entry");
+        assert!(entry.starts_with("[DEBUG]"));
+        assert!(entry.ends_with("probe"));
+    }
+
+    #[test_case]
+    fn timestamps_are_seconds_and_micros() {
+        let mut entry = SimpleString::new();
+        push_timestamp(12_000_345, &mut entry);
+        assert_eq!(entry.as_str(), "[12.000345]");
+    }
+
+    #[test_case]
+    fn timestamps_never_go_backwards() {
+        let first = timestamp_us();
+        let second = timestamp_us();
+        assert!(second >= first);
+    }
+
+    #[test_case]
+    fn log_tail_drops_whole_lines() {
+        assert_eq!(log_tail("one\ntwo\n", 16), "one\ntwo\n");
+        assert_eq!(log_tail("one\ntwo\nthree\n", 8), "three\n");
+    }
+
+    #[test_case]
+    fn debug_entries_are_dropped_at_info_level() {
+        let mut logger = quiet_logger();
+        logger.log(LogLevel::Debug, "probe");
+        assert_eq!(logger.entries().count(), 0);
+    }
+}
@@ -8,22 +8,20 @@
 
 // Use core for no_std functions
 use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicBool, Ordering};
 
 // This is imported from the bootloader crate
 use bootloader::{entry_point, BootInfo};
 use x86_64::VirtAddr;
+use x86_64::instructions::port::Port;
 use screamos::println;
 use screamos::print;
 
-// Import memory management
-mod memory;
-
 // Import necessary components
 use screamos::vga_buffer::{change_theme, ThemeStyle};
-use screamos::ui::window_manager::WindowManager;
-use screamos::ui::file_manager::FILE_MANAGER;
-use screamos::ui::splash_screen::SPLASH_SCREEN;
-use crate::memory::BootInfoFrameAllocator;
+use screamos::ui::splash_screen::{self, SPLASH_SCREEN};
+use screamos::{log_info, log_warn};
+use screamos::memory::{self, BootInfoFrameAllocator};
 
 // Define OS entry point for bootloader
 entry_point!(kernel_main);
@@ -36,56 +34,63 @@ fn kernel_main(boot_info: &'static BootInfo) -> ! {
     // Change to DOS classic theme
     change_theme(ThemeStyle::DOSClassic);
     
-    // Show splash screen
+    // Show splash screen, the timer interrupt animates it from here on
     if let Some(mut splash) = SPLASH_SCREEN.try_lock() {
         splash.show();
     }
     
-    // Classic boot sequence
-    println!("\nScreammOS Boot Sequence");
-    println!("=====================\n");
+    // Classic boot sequence, the splash screen redraws over anything printed
+    // so problems go to the log
+    log_info!("ScreammOS boot sequence started");
     
     // Step 1: Memory check
-    println!("Step 1: Performing memory check...");
+    memory::set_memory_map(&boot_info.memory_map);
     let physical_memory_offset = VirtAddr::new(boot_info.physical_memory_offset);
     let mut mapper = unsafe { memory::init(physical_memory_offset) };
     
     let mut frame_allocator = unsafe {
         BootInfoFrameAllocator::init(&boot_info.memory_map)
     };
+    set_boot_progress(20);
     
     // Step 2: Initialize memory management
-    println!("Step 2: Initializing memory management...");
     match memory::init_heap(&mut mapper, &mut frame_allocator) {
         Ok(_) => {
-            println!("  Memory management initialized successfully");
-            println!("  Heap memory: {} KiB", memory::HEAP_SIZE / 1024);
+            log_info!("Heap memory: {} KiB", memory::HEAP_SIZE / 1024);
+            frame_allocator.enable_bitmap();
+            memory::enable_heap_growth(mapper, frame_allocator);
         },
         Err(e) => {
-            println!("  WARNING: Heap initialization encountered an issue: {:?}", e);
-            println!("  The system will continue with limited memory functionality");
+            log_warn!("Heap initialization encountered an issue: {:?}", e);
+            log_warn!("The system will continue with limited memory functionality");
         }
     }
+    set_boot_progress(40);
     
     // Step 3: Initialize keyboard
-    println!("Step 3: Initializing keyboard...");
     screamos::keyboard::init();
-    println!("  Keyboard initialized");
+    set_boot_progress(60);
     
     // Step 4: Initialize filesystem
-    println!("Step 4: Initializing filesystem...");
-    if let Some(mut file_manager) = FILE_MANAGER.try_lock() {
-        println!("  File system ready");
-    } else {
-        println!("  Warning: Could not initialize file system");
+    screamos::simple_fs::init();
+    {
+        let mut cli = screamos::ui::UI_STATE.lock();
+        cli.load_history();
+        cli.load_aliases();
     }
+    set_boot_progress(80);
     
     // Step 5: Run system diagnostics
-    println!("Step 5: Running system diagnostics...");
     run_self_tests();
+    set_boot_progress(100);
     
-    // Hide splash screen after initialization
-    if let Some(mut splash) = SPLASH_SCREEN.try_lock() {
+    // Let the animation run for a moment even on a fast boot, unless
+    // a key is pressed
+    {
+        let mut splash = SPLASH_SCREEN.lock();
+        if splash.wait_or_skip(splash_screen::MIN_FRAMES) {
+            log_info!("Splash screen skipped");
+        }
         splash.hide();
     }
     
@@ -97,8 +102,21 @@ fn kernel_main(boot_info: &'static BootInfo) -> ! {
     println!("║  Type 'help' for a list of available commands              ║");
     println!("╚════════════════════════════════════════════════════════════╝\n");
     
-    // Main loop - wait for interrupts
-    screamos::hlt_loop();
+    screamos::ui::UI_STATE.lock().run_autoexec();
+    print_prompt();
+    
+    // Main loop - handle queued keys, then sleep until the next interrupt.
+    // A key that arrives just before hlt waits at most one timer tick.
+    loop {
+        screamos::keyboard::process_keypress();
+        screamos::keyboard::process_key_repeat();
+        screamos::ui::screensaver::process_idle();
+        x86_64::instructions::hlt();
+    }
+}
+
+fn set_boot_progress(percent: u8) {
+    SPLASH_SCREEN.lock().set_progress(percent);
 }
 
 fn print_prompt() {
@@ -107,22 +125,100 @@ fn print_prompt() {
 
 // Definiera testrunner för cargo test
 #[cfg(test)]
-fn test_runner(tests: &[&dyn Fn()]) {
+fn test_runner(tests: &[&dyn screamos::Testable]) {
     screamos::test_runner(tests);
 }
 
+// Number of log entries shown on the panic screen
+const PANIC_LOG_ENTRIES: usize = 6;
+
+// Set by the first panic so a panic inside the panic screen doesn't loop
+static PANICKING: AtomicBool = AtomicBool::new(false);
+
+// Print to the screen and, without locking, to COM1 so headless runs see it
+macro_rules! panic_println {
+    () => (panic_println!(""));
+    ($($arg:tt)*) => {{
+        println!($($arg)*);
+        screamos::serial::panic_print(format_args!("{}\n", format_args!($($arg)*)));
+    }};
+}
+
+// Screen and COM1 at once, for output that goes through fmt::Write
+struct PanicOutput;
+
+impl core::fmt::Write for PanicOutput {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        print!("{}", s);
+        screamos::serial::panic_print(format_args!("{}", s));
+        Ok(())
+    }
+}
+
 /// This function is called on panic
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
+    x86_64::instructions::interrupts::disable();
+    
+    if PANICKING.swap(true, Ordering::SeqCst) {
+        // The panic screen itself panicked, the original message is already out
+        screamos::hlt_loop();
+    }
+    
+    // Out over serial before anything else, drawing the screen may fail
+    screamos::serial::panic_print(format_args!("KERNEL PANIC: {}\n", info));
+    
+    // The panicking code may have held any of these, and nobody else is
+    // going to run to release them
+    unsafe {
+        screamos::vga_buffer::WRITER.force_unlock();
+        screamos::logger::LOGGER.force_unlock();
+        screamos::error_handler::ERROR_HANDLER.force_unlock();
+    }
+    
     // Change screen to red on panic
     screamos::vga_buffer::set_global_color(screamos::vga_buffer::Color::Red, screamos::vga_buffer::Color::Black);
+    screamos::vga_buffer::clear_screen();
     
-    println!();
+    // Context first, so the panic message itself stays on screen
+    panic_println!("Recent log entries:");
+    {
+        let logger = screamos::logger::LOGGER.lock();
+        let skip = logger.entries().count().saturating_sub(PANIC_LOG_ENTRIES);
+        for entry in logger.entries().skip(skip) {
+            panic_println!("  {}", entry);
+        }
+    }
+    panic_println!();
+    let _ = screamos::error_handler::ERROR_HANDLER.lock().dump_error_history(&mut PanicOutput);
+    
+    // Serial already has the message
     println!("KERNEL PANIC!");
     println!("{}", info);
+    panic_println!();
+    panic_println!("Press any key to reboot");
+    
+    wait_for_key_press();
+    screamos::reboot();
+}
+
+// Poll the keyboard controller, interrupts are off while panicking
+fn wait_for_key_press() {
+    let mut status = Port::<u8>::new(0x64);
+    
+    // Drop whatever was typed before the panic
+    while unsafe { status.read() } & 1 != 0 {
+        screamos::keyboard::read_scancode();
+    }
     
     loop {
-        x86_64::instructions::hlt();
+        if unsafe { status.read() } & 1 != 0 {
+            // Ignore key releases so a key held during the panic doesn't count
+            if screamos::keyboard::read_scancode() & 0x80 == 0 {
+                return;
+            }
+        }
+        core::hint::spin_loop();
     }
 }
 
@@ -137,7 +233,7 @@ pub fn run_self_tests() {
     use log_info;
     use ui::text_editor::TextEditor;
     use vga_buffer::{WRITER, Color};
-    use error_handler::{report_error, ErrorDomain, ErrorSeverity};
+    use error_handler::{report_error, ErrorCode, ErrorDomain, ErrorSeverity};
     
     log_info!("Starting comprehensive system self-tests");
     
@@ -169,7 +265,7 @@ pub fn run_self_tests() {
                 
                 // Report the error
                 let _ = report_error(
-                    0x1001, 
+                    ErrorCode::SelfTestFailed,
                     ErrorDomain::System, 
                     ErrorSeverity::Warning,
                     &format!("Test failed: {} - {}", name, msg)
@@ -266,12 +362,12 @@ pub fn run_self_tests() {
             
             // Read the file content
             match fs.read_file(test_filename) {
-                Some(content) => {
+                Ok(content) => {
                     if content != test_content {
                         return Err("File content doesn't match expected content");
                     }
                 },
-                None => return Err("Failed to read test file"),
+                Err(_) => return Err("Failed to read test file"),
             }
         }
         
@@ -377,7 +473,7 @@ pub fn run_self_tests() {
         
         // Create a test error
         let _ = report_error(
-            0x9999, 
+            ErrorCode::Test,
             ErrorDomain::System, 
             ErrorSeverity::Warning,
             "This is a test error for the error handling system"
@@ -1,21 +1,99 @@
 use x86_64::{
     structures::paging::{
-        mapper::MapToError, FrameAllocator, Mapper, Page, PageTableFlags, Size4KiB,
+        mapper::MapToError, FrameAllocator, FrameDeallocator, Mapper, Page, PageTableFlags, Size4KiB,
         OffsetPageTable, PhysFrame, PageTable,
     },
     PhysAddr, VirtAddr,
 };
 use linked_list_allocator::LockedHeap;
+use core::alloc::{GlobalAlloc, Layout};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use spin::Mutex;
 use bootloader::bootinfo::{MemoryMap, MemoryRegionType};
 use crate::println;
+use alloc::vec;
+use alloc::vec::Vec;
 
 // Define the kernel heap size
 pub const HEAP_START: usize = 0x_4444_4444_0000;
 pub const HEAP_SIZE: usize = 500 * 1024; // 500 KiB (increased for filesystem)
+// The heap grows in steps of at least this much, up to MAX_HEAP_SIZE
+const HEAP_GROW_STEP: usize = 64 * 1024;
+pub const MAX_HEAP_SIZE: usize = 16 * 1024 * 1024;
+
+// Bytes handed to the allocator. Pages past the initial heap are only
+// reserved by grow_heap and get mapped when first touched.
+static HEAP_RESERVED: AtomicUsize = AtomicUsize::new(HEAP_SIZE);
+// Bytes of the heap backed by frames, advanced under the HEAP_GROWTH lock
+static HEAP_MAPPED: AtomicUsize = AtomicUsize::new(HEAP_SIZE);
+
+// The page mapper and frame allocator, handed over after boot so the heap
+// can be grown when it runs out
+static HEAP_GROWTH: Mutex<Option<HeapGrowth>> = Mutex::new(None);
+
+struct HeapGrowth {
+    mapper: OffsetPageTable<'static>,
+    frame_allocator: BootInfoFrameAllocator,
+}
+
+// The bootloader's memory map. It lives in the boot info page, which is
+// mapped for the whole run, so keeping the reference is sound.
+static MEMORY_MAP: Mutex<Option<&'static MemoryMap>> = Mutex::new(None);
 
 // Create a global heap allocator
 #[global_allocator]
-static ALLOCATOR: LockedHeap = LockedHeap::empty();
+static ALLOCATOR: TrackingAllocator = TrackingAllocator::new();
+
+/// Global allocator that counts the heap bytes in use and leaves the
+/// actual allocation to the linked list heap
+pub struct TrackingAllocator {
+    heap: LockedHeap,
+    used: AtomicUsize,
+}
+
+impl TrackingAllocator {
+    pub const fn new() -> Self {
+        TrackingAllocator {
+            heap: LockedHeap::empty(),
+            used: AtomicUsize::new(0),
+        }
+    }
+}
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut ptr = self.heap.alloc(layout);
+        if ptr.is_null() {
+            // Out of heap: map more pages and retry once
+            let needed = layout.size() + layout.align();
+            if grow_heap(needed.max(HEAP_GROW_STEP)).is_ok() {
+                ptr = self.heap.alloc(layout);
+            }
+        }
+        if !ptr.is_null() {
+            self.used.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.heap.dealloc(ptr, layout);
+        self.used.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.heap.realloc(ptr, layout, new_size);
+        // On failure the old block is left untouched
+        if !new_ptr.is_null() {
+            if new_size > layout.size() {
+                self.used.fetch_add(new_size - layout.size(), Ordering::Relaxed);
+            } else {
+                self.used.fetch_sub(layout.size() - new_size, Ordering::Relaxed);
+            }
+        }
+        new_ptr
+    }
+}
 
 /// Initialize a new OffsetPageTable.
 ///
@@ -49,12 +127,15 @@ unsafe fn active_level_4_table(physical_memory_offset: VirtAddr)
 }
 
 /// A FrameAllocator that returns usable frames from the bootloader's memory map.
+///
+/// Until the heap exists, frames are handed out in memory map order. After
+/// `enable_bitmap` every frame has a bit (set = in use or not usable), so
+/// frames can be given back with `deallocate_frame` and reused.
 pub struct BootInfoFrameAllocator {
     memory_map: &'static MemoryMap,
-    next: usize,
-    // Håll reda på de senaste ramarna som har tilldelats för att undvika dubbla tilldelningar
-    allocated_frames: [u64; 64], // Vi håller bara de senaste 64 ramarna för enkelhetens skull
-    allocated_count: usize,
+    next: usize,              // Frames handed out before the bitmap existed
+    bitmap: Option<Vec<u64>>,
+    search_from: usize,       // Word to resume searching from
 }
 
 impl BootInfoFrameAllocator {
@@ -67,8 +148,8 @@ impl BootInfoFrameAllocator {
         BootInfoFrameAllocator {
             memory_map,
             next: 0,
-            allocated_frames: [0; 64],
-            allocated_count: 0,
+            bitmap: None,
+            search_from: 0,
         }
     }
     
@@ -85,53 +166,68 @@ impl BootInfoFrameAllocator {
         frame_addresses.map(|addr| PhysFrame::containing_address(PhysAddr::new(addr)))
     }
 
-    // Kolla om en ram redan är allokerad
-    fn is_frame_allocated(&self, frame: PhysFrame) -> bool {
-        let frame_addr = frame.start_address().as_u64();
-        for i in 0..self.allocated_count {
-            if self.allocated_frames[i] == frame_addr {
-                return true;
-            }
-        }
-        false
-    }
+    /// Switch to bitmap tracking. Needs the heap, so call it after
+    /// `init_heap`; the frames used so far stay marked as in use.
+    pub fn enable_bitmap(&mut self) {
+        let frame_count = self.memory_map.iter()
+            .filter(|r| r.region_type == MemoryRegionType::Usable)
+            .map(|r| (r.range.end_addr() / 4096) as usize)
+            .max()
+            .unwrap_or(0);
 
-    // Lägg till en ram till listan över allokerade ramar
-    fn mark_frame_allocated(&mut self, frame: PhysFrame) {
-        let frame_addr = frame.start_address().as_u64();
-        if self.allocated_count < self.allocated_frames.len() {
-            self.allocated_frames[self.allocated_count] = frame_addr;
-            self.allocated_count += 1;
-        } else {
-            // Om listan är full, starta om från början (cirkulär buffer)
-            for i in 0..(self.allocated_frames.len() - 1) {
-                self.allocated_frames[i] = self.allocated_frames[i + 1];
-            }
-            self.allocated_frames[self.allocated_frames.len() - 1] = frame_addr;
+        let mut bitmap = vec![u64::MAX; (frame_count + 63) / 64];
+        for frame in self.usable_frames().skip(self.next) {
+            let index = frame_index(frame);
+            bitmap[index / 64] &= !(1 << (index % 64));
         }
+
+        self.bitmap = Some(bitmap);
+        self.search_from = 0;
     }
 }
 
+fn frame_index(frame: PhysFrame) -> usize {
+    (frame.start_address().as_u64() / 4096) as usize
+}
+
 unsafe impl FrameAllocator<Size4KiB> for BootInfoFrameAllocator {
     fn allocate_frame(&mut self) -> Option<PhysFrame> {
-        let mut frame_iter = self.usable_frames().skip(self.next);
-        
-        // Hitta nästa lediga ram som inte är allokerad
-        let frame = loop {
-            let frame = frame_iter.next()?;
-            
-            // Öka next-räknaren så vi inte hamnar i en oändlig loop
-            self.next += 1;
-            
-            // Kolla om ramen redan är allokerad
-            if !self.is_frame_allocated(frame) {
-                // Markera ramen som allokerad och returnera den
-                self.mark_frame_allocated(frame);
-                break frame;
+        let bitmap = match self.bitmap.as_mut() {
+            Some(bitmap) => bitmap,
+            None => {
+                let frame = self.usable_frames().nth(self.next)?;
+                self.next += 1;
+                return Some(frame);
             }
         };
-        
-        Some(frame)
+
+        // Scan whole words from where the last search ended, wrapping once
+        let words = bitmap.len();
+        for offset in 0..words {
+            let word = (self.search_from + offset) % words;
+            if bitmap[word] != u64::MAX {
+                let bit = bitmap[word].trailing_ones() as usize;
+                bitmap[word] |= 1 << bit;
+                self.search_from = word;
+                let addr = ((word * 64 + bit) * 4096) as u64;
+                return Some(PhysFrame::containing_address(PhysAddr::new(addr)));
+            }
+        }
+        None
+    }
+}
+
+impl FrameDeallocator<Size4KiB> for BootInfoFrameAllocator {
+    unsafe fn deallocate_frame(&mut self, frame: PhysFrame) {
+        // Before the bitmap exists there is nowhere to record it, the
+        // frame is simply not reused
+        if let Some(bitmap) = self.bitmap.as_mut() {
+            let index = frame_index(frame);
+            if let Some(word) = bitmap.get_mut(index / 64) {
+                *word &= !(1 << (index % 64));
+                self.search_from = self.search_from.min(index / 64);
+            }
+        }
     }
 }
 
@@ -175,12 +271,72 @@ pub fn init_heap(
 
     // Initialize the allocator with the heap area
     unsafe {
-        ALLOCATOR.lock().init(HEAP_START as *mut u8, HEAP_SIZE);
+        ALLOCATOR.heap.lock().init(HEAP_START as *mut u8, HEAP_SIZE);
     }
 
     Ok(())
 }
 
+/// Map a frame for the heap page containing `addr`, for page faults on
+/// pages `grow_heap` reserved but didn't map. Anything else, including
+/// wild accesses past the reserved heap, fails and the fault stays fatal.
+pub fn map_heap_page(addr: VirtAddr) -> Result<(), &'static str> {
+    let mut guard = HEAP_GROWTH.try_lock().ok_or("Page mapper is busy")?;
+    let growth = guard.as_mut().ok_or("Heap growth not enabled")?;
+
+    // The initial heap is mapped up front, only grown pages are lazy
+    let reserved_start = (HEAP_START + HEAP_SIZE) as u64;
+    let reserved_end = (HEAP_START + HEAP_RESERVED.load(Ordering::Relaxed)) as u64;
+    if addr.as_u64() < reserved_start || addr.as_u64() >= reserved_end {
+        return Err("Address is outside the reserved heap");
+    }
+
+    let page = Page::<Size4KiB>::containing_address(addr);
+    unsafe { try_map_page(page, &mut growth.mapper, &mut growth.frame_allocator) }
+        .map_err(|_| "Could not map heap page")?;
+    HEAP_MAPPED.fetch_add(4096, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Keep the page mapper and frame allocator so `grow_heap` can map more
+/// pages later. Call once, after `init_heap` succeeded.
+pub fn enable_heap_growth(mapper: OffsetPageTable<'static>, frame_allocator: BootInfoFrameAllocator) {
+    *HEAP_GROWTH.lock() = Some(HeapGrowth { mapper, frame_allocator });
+}
+
+/// Reserve `additional` more bytes (rounded up to whole pages) at the end
+/// of the heap and hand them to the allocator. Only the first page is
+/// mapped here, since the allocator writes its bookkeeping there; the
+/// others are mapped by `map_heap_page` when first touched.
+pub fn grow_heap(additional: usize) -> Result<usize, &'static str> {
+    if crate::error_handler::is_in_safe_mode() {
+        return Err("Heap growth disabled in safe mode");
+    }
+
+    // Called from inside the allocator, so never spin on the lock
+    let mut guard = HEAP_GROWTH.try_lock().ok_or("Heap growth already in progress")?;
+    let growth = guard.as_mut().ok_or("Heap growth not enabled")?;
+
+    let current = HEAP_RESERVED.load(Ordering::Relaxed);
+    let additional = additional.min(MAX_HEAP_SIZE.saturating_sub(current));
+    let pages = (additional + 4095) / 4096;
+    if pages == 0 {
+        return Err("Heap is at its maximum size");
+    }
+
+    let first_page = Page::<Size4KiB>::containing_address(VirtAddr::new((HEAP_START + current) as u64));
+    unsafe { try_map_page(first_page, &mut growth.mapper, &mut growth.frame_allocator) }
+        .map_err(|_| "Out of physical memory")?;
+    HEAP_MAPPED.fetch_add(4096, Ordering::Relaxed);
+
+    let grown = pages * 4096;
+    HEAP_RESERVED.store(current + grown, Ordering::Relaxed);
+    unsafe {
+        ALLOCATOR.heap.lock().extend(grown);
+    }
+    Ok(grown)
+}
+
 // Hjälpfunktion för att försöka mappa en sida
 unsafe fn try_map_page(
     page: Page,
@@ -197,23 +353,67 @@ unsafe fn try_map_page(
 
 // Functions for memory information
 
-// Get total memory size
+// Get total memory size, including any growth since boot
 pub fn get_total_memory() -> usize {
-    HEAP_SIZE
+    HEAP_RESERVED.load(Ordering::Relaxed)
+}
+
+// Get the part of the heap backed by physical frames so far
+pub fn get_mapped_memory() -> usize {
+    HEAP_MAPPED.load(Ordering::Relaxed)
 }
 
-// Get used memory
+// Get used memory, the bytes currently handed out by the allocator
 pub fn get_used_memory() -> usize {
-    // Since LockedHeap doesn't have a stats method in this version,
-    // we'll just return 0 for now
-    0
+    ALLOCATOR.used.load(Ordering::Relaxed)
 }
 
 // Get free memory
 pub fn get_free_memory() -> usize {
-    // Since LockedHeap doesn't have a stats method in this version,
-    // we'll just return the total heap size for now
-    HEAP_SIZE
+    get_total_memory().saturating_sub(get_used_memory())
+}
+
+/// Keep the bootloader's memory map for `lsmem`
+pub fn set_memory_map(memory_map: &'static MemoryMap) {
+    *MEMORY_MAP.lock() = Some(memory_map);
+}
+
+/// The memory map passed in at boot, if `set_memory_map` has run
+pub fn memory_map() -> Option<&'static MemoryMap> {
+    *MEMORY_MAP.lock()
+}
+
+/// Display name of a memory map region type
+pub fn region_type_name(region_type: MemoryRegionType) -> &'static str {
+    match region_type {
+        MemoryRegionType::Usable => "Usable",
+        MemoryRegionType::InUse => "In use",
+        MemoryRegionType::Reserved => "Reserved",
+        MemoryRegionType::AcpiReclaimable => "ACPI reclaim",
+        MemoryRegionType::AcpiNvs => "ACPI NVS",
+        MemoryRegionType::BadMemory => "Bad memory",
+        MemoryRegionType::Kernel => "Kernel",
+        MemoryRegionType::KernelStack => "Kernel stack",
+        MemoryRegionType::PageTable => "Page table",
+        MemoryRegionType::Bootloader => "Bootloader",
+        MemoryRegionType::FrameZero => "Frame zero",
+        MemoryRegionType::Empty => "Empty",
+        MemoryRegionType::BootInfo => "Boot info",
+        MemoryRegionType::Package => "Package",
+        _ => "Unknown",
+    }
+}
+
+/// Bytes in usable regions and in all other regions of `memory_map`
+pub fn region_totals(memory_map: &MemoryMap) -> (u64, u64) {
+    memory_map.iter().fold((0, 0), |(usable, reserved), region| {
+        let size = region.range.end_addr() - region.range.start_addr();
+        if region.region_type == MemoryRegionType::Usable {
+            (usable + size, reserved)
+        } else {
+            (usable, reserved + size)
+        }
+    })
 }
 
 // Struct to collect memory stats
@@ -230,4 +430,86 @@ pub fn get_memory_stats() -> MemoryStats {
         used: get_used_memory(),
         free: get_free_memory(),
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+    use bootloader::bootinfo::{FrameRange, MemoryRegion};
+
+    #[test_case]
+    fn used_memory_follows_allocations() {
+        let before = get_used_memory();
+
+        let mut buffer: Vec<u8> = Vec::with_capacity(100);
+        assert_eq!(get_used_memory(), before + 100);
+
+        // Growing goes through realloc
+        buffer.resize(100, 0);
+        buffer.reserve_exact(300);
+        assert_eq!(get_used_memory(), before + buffer.capacity());
+
+        buffer.truncate(10);
+        buffer.shrink_to_fit();
+        assert_eq!(get_used_memory(), before + 10);
+
+        drop(buffer);
+        assert_eq!(get_used_memory(), before);
+    }
+
+    // A memory map with `frames` usable frames starting at 1 MiB
+    fn test_allocator(frames: u64) -> BootInfoFrameAllocator {
+        let mut map = MemoryMap::new();
+        map.add_region(MemoryRegion {
+            range: FrameRange::new(0x10_0000, 0x10_0000 + frames * 4096),
+            region_type: MemoryRegionType::Usable,
+        });
+        unsafe { BootInfoFrameAllocator::init(Box::leak(Box::new(map))) }
+    }
+
+    #[test_case]
+    fn region_totals_split_usable_from_the_rest() {
+        let mut map = MemoryMap::new();
+        map.add_region(MemoryRegion {
+            range: FrameRange::new(0, 0x1000),
+            region_type: MemoryRegionType::FrameZero,
+        });
+        map.add_region(MemoryRegion {
+            range: FrameRange::new(0x10_0000, 0x10_4000),
+            region_type: MemoryRegionType::Usable,
+        });
+        map.add_region(MemoryRegion {
+            range: FrameRange::new(0x10_4000, 0x10_6000),
+            region_type: MemoryRegionType::Kernel,
+        });
+        assert_eq!(region_totals(&map), (0x4000, 0x3000));
+        assert_eq!(region_type_name(MemoryRegionType::Kernel), "Kernel");
+    }
+
+    #[test_case]
+    fn bitmap_keeps_bootstrap_frames() {
+        let mut allocator = test_allocator(4);
+        let first = allocator.allocate_frame().unwrap();
+        allocator.enable_bitmap();
+
+        let mut rest = Vec::new();
+        while let Some(frame) = allocator.allocate_frame() {
+            assert_ne!(frame, first);
+            rest.push(frame);
+        }
+        assert_eq!(rest.len(), 3);
+    }
+
+    #[test_case]
+    fn freed_frames_are_reused() {
+        let mut allocator = test_allocator(2);
+        allocator.enable_bitmap();
+        let a = allocator.allocate_frame().unwrap();
+        let _b = allocator.allocate_frame().unwrap();
+        assert_eq!(allocator.allocate_frame(), None);
+
+        unsafe { allocator.deallocate_frame(a) };
+        assert_eq!(allocator.allocate_frame(), Some(a));
+    }
+}
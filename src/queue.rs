@@ -1,93 +1,143 @@
 // src/queue.rs
 // Simple queue implementation for ScreammOS
 
+use core::cell::UnsafeCell;
 use core::sync::atomic::{AtomicUsize, Ordering};
 
-/// A lockless queue for simple data types, designed to be used
-/// in interrupt contexts where spinlocks might cause deadlocks.
-/// Uses a ring buffer implementation.
+const MAX_CAPACITY: usize = 100;
+
+/// A lockless single-producer/single-consumer ring buffer for simple data
+/// types, designed to be shared between an interrupt handler and the code
+/// it interrupts without a spinlock.
+///
+/// `push` and `pop` only need `&self`, but at most one context may push and
+/// at most one context may pop at any time. Two concurrent producers (or
+/// consumers) can corrupt the queue.
 pub struct ArrayQueue<T> {
-    buffer: [T; 100],                  // Fixed-size buffer
-    head: AtomicUsize,                // Index for dequeue operations
-    tail: AtomicUsize,                // Index for enqueue operations
-    capacity: usize,                  // Maximum queue capacity (= buffer.len())
+    buffer: UnsafeCell<[T; MAX_CAPACITY]>, // Fixed-size buffer
+    head: AtomicUsize,                     // Items popped so far, only the consumer writes it
+    tail: AtomicUsize,                     // Items pushed so far, only the producer writes it
+    capacity: usize,                       // Maximum number of queued items
 }
 
+// Safety: the producer only writes the slot at `tail` before publishing it,
+// and the consumer only reads the slot at `head` after it was published
+unsafe impl<T: Send> Sync for ArrayQueue<T> {}
+
 impl<T: Copy + Default> ArrayQueue<T> {
     /// Create a new queue with the given capacity
     pub fn new(capacity: usize) -> Self {
-        assert!(capacity <= 100, "Capacity exceeds max buffer size");
-        
+        assert!(capacity > 0 && capacity <= MAX_CAPACITY, "Capacity exceeds max buffer size");
+
         Self {
-            buffer: [T::default(); 100],
+            buffer: UnsafeCell::new([T::default(); MAX_CAPACITY]),
             head: AtomicUsize::new(0),
             tail: AtomicUsize::new(0),
             capacity,
         }
     }
-    
+
     /// Add an item to the queue. Returns Err if the queue is full.
-    pub fn push(&mut self, item: T) -> Result<(), ()> {
-        let current_tail = self.tail.load(Ordering::Relaxed);
-        let next_tail = (current_tail + 1) % self.capacity;
-        
+    /// Must only be called from the single producer.
+    pub fn push(&self, item: T) -> Result<(), ()> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let head = self.head.load(Ordering::Acquire);
+
         // Check if queue is full
-        if next_tail == self.head.load(Ordering::Relaxed) {
+        if tail.wrapping_sub(head) >= self.capacity {
             return Err(());
         }
-        
-        // Add the item
-        self.buffer[current_tail] = item;
-        
-        // Update tail pointer (ensure memory ordering)
-        self.tail.store(next_tail, Ordering::Release);
-        
+
+        // The slot is free: the consumer has moved past it
+        unsafe { (*self.buffer.get())[tail % self.capacity] = item };
+
+        // Publish the item to the consumer
+        self.tail.store(tail.wrapping_add(1), Ordering::Release);
+
         Ok(())
     }
-    
+
     /// Remove and return an item from the queue. Returns None if the queue is empty.
-    pub fn pop(&mut self) -> Option<T> {
-        let current_head = self.head.load(Ordering::Relaxed);
-        
+    /// Must only be called from the single consumer.
+    pub fn pop(&self) -> Option<T> {
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::Acquire);
+
         // Check if queue is empty
-        if current_head == self.tail.load(Ordering::Relaxed) {
+        if head == tail {
             return None;
         }
-        
-        // Get the item
-        let item = self.buffer[current_head];
-        
-        // Update head pointer (ensure memory ordering)
-        self.head.store((current_head + 1) % self.capacity, Ordering::Release);
-        
+
+        // The slot was published by the producer's Release store
+        let item = unsafe { (*self.buffer.get())[head % self.capacity] };
+
+        // Hand the slot back to the producer
+        self.head.store(head.wrapping_add(1), Ordering::Release);
+
         Some(item)
     }
-    
+
     /// Check if the queue is empty
     pub fn is_empty(&self) -> bool {
-        self.head.load(Ordering::Relaxed) == self.tail.load(Ordering::Relaxed)
+        self.len() == 0
+    }
+
+    /// Check if the queue is full
+    pub fn is_full(&self) -> bool {
+        self.len() >= self.capacity
     }
-    
+
     /// Get the number of items in the queue
     pub fn len(&self) -> usize {
-        let head = self.head.load(Ordering::Relaxed);
-        let tail = self.tail.load(Ordering::Relaxed);
-        
-        if tail >= head {
-            tail - head
-        } else {
-            self.capacity - head + tail
-        }
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+        tail.wrapping_sub(head)
     }
-    
+
     /// Get the capacity of the queue
     pub fn capacity(&self) -> usize {
         self.capacity
     }
-    
+
     /// Clear the queue
     pub fn clear(&mut self) {
         self.head.store(0, Ordering::Relaxed);
         self.tail.store(0, Ordering::Relaxed);
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn pop_from_empty_queue() {
+        let queue: ArrayQueue<u8> = ArrayQueue::new(4);
+        assert!(queue.is_empty());
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test_case]
+    fn push_until_full() {
+        let queue = ArrayQueue::new(3);
+        for i in 0..3u8 {
+            assert_eq!(queue.push(i), Ok(()));
+        }
+        assert!(queue.is_full());
+        assert_eq!(queue.push(3), Err(()));
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.pop(), Some(0));
+    }
+
+    #[test_case]
+    fn items_survive_wraparound() {
+        let queue = ArrayQueue::new(4);
+        for round in 0..10u8 {
+            queue.push(round).unwrap();
+            queue.push(round + 100).unwrap();
+            assert_eq!(queue.pop(), Some(round));
+            assert_eq!(queue.pop(), Some(round + 100));
+        }
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,175 @@
+// src/rtc.rs
+// CMOS real-time clock
+
+use core::fmt;
+use x86_64::instructions::port::Port;
+
+// CMOS index and data ports
+const CMOS_ADDRESS: u16 = 0x70;
+const CMOS_DATA: u16 = 0x71;
+
+// RTC registers
+const REG_SECONDS: u8 = 0x00;
+const REG_MINUTES: u8 = 0x02;
+const REG_HOURS: u8 = 0x04;
+const REG_DAY: u8 = 0x07;
+const REG_MONTH: u8 = 0x08;
+const REG_YEAR: u8 = 0x09;
+const REG_STATUS_A: u8 = 0x0A;
+const REG_STATUS_B: u8 = 0x0B;
+
+// Status bits
+const STATUS_A_UPDATE_IN_PROGRESS: u8 = 0x80;
+const STATUS_B_24_HOUR: u8 = 0x02;
+const STATUS_B_BINARY: u8 = 0x04;
+const HOUR_PM: u8 = 0x80;
+
+// Give up waiting for a stable reading after this many attempts
+const MAX_READ_ATTEMPTS: usize = 10;
+
+// Upper bound for the update-in-progress wait, an update takes ~2ms
+const MAX_POLLS: usize = 100_000;
+
+/// A calendar date and wall-clock time as kept by the RTC
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl DateTime {
+    /// Format the date as YYYY-MM-DD
+    pub fn date(&self) -> DateDisplay {
+        DateDisplay(*self)
+    }
+
+    /// Format the time as HH:MM:SS
+    pub fn time(&self) -> TimeDisplay {
+        TimeDisplay(*self)
+    }
+}
+
+pub struct DateDisplay(DateTime);
+
+impl fmt::Display for DateDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.0.year, self.0.month, self.0.day)
+    }
+}
+
+pub struct TimeDisplay(DateTime);
+
+impl fmt::Display for TimeDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:02}:{:02}:{:02}", self.0.hour, self.0.minute, self.0.second)
+    }
+}
+
+fn read_register(register: u8) -> u8 {
+    unsafe {
+        Port::<u8>::new(CMOS_ADDRESS).write(register);
+        Port::<u8>::new(CMOS_DATA).read()
+    }
+}
+
+fn update_in_progress() -> bool {
+    read_register(REG_STATUS_A) & STATUS_A_UPDATE_IN_PROGRESS != 0
+}
+
+// Raw register values in whatever format the RTC is configured for
+fn read_raw() -> [u8; 6] {
+    for _ in 0..MAX_POLLS {
+        if !update_in_progress() {
+            break;
+        }
+    }
+    [
+        read_register(REG_SECONDS),
+        read_register(REG_MINUTES),
+        read_register(REG_HOURS),
+        read_register(REG_DAY),
+        read_register(REG_MONTH),
+        read_register(REG_YEAR),
+    ]
+}
+
+fn bcd_to_binary(value: u8) -> u8 {
+    (value & 0x0F) + (value >> 4) * 10
+}
+
+// Convert raw register values to a DateTime using status register B
+fn decode(raw: [u8; 6], status_b: u8) -> DateTime {
+    let [mut second, mut minute, hour_raw, mut day, mut month, mut year] = raw;
+    let pm = hour_raw & HOUR_PM != 0;
+    let mut hour = hour_raw & !HOUR_PM;
+
+    if status_b & STATUS_B_BINARY == 0 {
+        second = bcd_to_binary(second);
+        minute = bcd_to_binary(minute);
+        hour = bcd_to_binary(hour);
+        day = bcd_to_binary(day);
+        month = bcd_to_binary(month);
+        year = bcd_to_binary(year);
+    }
+
+    // In 12-hour mode midnight is 12 AM and the top bit marks PM
+    if status_b & STATUS_B_24_HOUR == 0 {
+        hour = match (hour, pm) {
+            (12, false) => 0,
+            (12, true) => 12,
+            (h, true) => h + 12,
+            (h, false) => h,
+        };
+    }
+
+    DateTime {
+        // The century register isn't standard, assume the 2000s
+        year: 2000 + year as u16,
+        month,
+        day,
+        hour,
+        minute,
+        second,
+    }
+}
+
+/// Read the current date and time from the CMOS RTC
+pub fn read_datetime() -> DateTime {
+    // Read until two consecutive readings agree so an update that starts
+    // halfway through can't give us a torn value
+    let mut last = read_raw();
+    for _ in 0..MAX_READ_ATTEMPTS {
+        let current = read_raw();
+        if current == last {
+            break;
+        }
+        last = current;
+    }
+
+    decode(last, read_register(REG_STATUS_B))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn decodes_bcd_values() {
+        let raw = [0x45, 0x30, 0x23, 0x31, 0x12, 0x24];
+        let datetime = decode(raw, STATUS_B_24_HOUR);
+        assert_eq!(datetime, DateTime { year: 2024, month: 12, day: 31, hour: 23, minute: 30, second: 45 });
+    }
+
+    #[test_case]
+    fn decodes_binary_twelve_hour_values() {
+        let pm = [0, 5, 1 | HOUR_PM, 1, 1, 25];
+        assert_eq!(decode(pm, STATUS_B_BINARY).hour, 13);
+
+        let midnight = [0, 0, 12, 1, 1, 25];
+        assert_eq!(decode(midnight, STATUS_B_BINARY).hour, 0);
+    }
+}
@@ -0,0 +1,116 @@
+// src/serial.rs
+// 16550 UART driver for the first serial port, used for headless output
+
+use core::fmt;
+use lazy_static::lazy_static;
+use spin::Mutex;
+use core::sync::atomic::{AtomicBool, Ordering};
+use x86_64::instructions::interrupts;
+use x86_64::instructions::port::Port;
+
+const COM1: u16 = 0x3F8;
+
+// Register offsets from the port base
+const DATA: u16 = 0;                 // Divisor low byte while DLAB is set
+const INTERRUPT_ENABLE: u16 = 1;     // Divisor high byte while DLAB is set
+const FIFO_CONTROL: u16 = 2;
+const LINE_CONTROL: u16 = 3;
+const MODEM_CONTROL: u16 = 4;
+const LINE_STATUS: u16 = 5;
+
+// 115200 / 3 = 38400 baud
+const BAUD_DIVISOR: u16 = 3;
+const DLAB: u8 = 0x80;
+const EIGHT_BITS_NO_PARITY_ONE_STOP: u8 = 0x03;
+// Enable and clear both FIFOs, interrupt at 14 bytes
+const FIFO_ENABLE_CLEAR: u8 = 0xC7;
+// DTR, RTS and OUT2
+const MODEM_READY: u8 = 0x0B;
+// Set once the transmit holding register can take another byte
+const TRANSMIT_EMPTY: u8 = 0x20;
+
+// Set by the first init, so the panic path knows whether the UART is set up
+static COM1_READY: AtomicBool = AtomicBool::new(false);
+
+pub struct SerialPort {
+    base: u16,
+}
+
+impl SerialPort {
+    /// A port at I/O address `base`. Call `init` before sending.
+    pub const fn new(base: u16) -> Self {
+        SerialPort { base }
+    }
+
+    /// Set the port up for 38400 baud 8N1 with FIFOs and no interrupts
+    pub fn init(&mut self) {
+        unsafe {
+            self.port(INTERRUPT_ENABLE).write(0x00);
+            self.port(LINE_CONTROL).write(DLAB);
+            self.port(DATA).write(BAUD_DIVISOR as u8);
+            self.port(INTERRUPT_ENABLE).write((BAUD_DIVISOR >> 8) as u8);
+            self.port(LINE_CONTROL).write(EIGHT_BITS_NO_PARITY_ONE_STOP);
+            self.port(FIFO_CONTROL).write(FIFO_ENABLE_CLEAR);
+            self.port(MODEM_CONTROL).write(MODEM_READY);
+        }
+        if self.base == COM1 {
+            COM1_READY.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// Send one byte, waiting until the UART has room for it
+    pub fn send(&mut self, byte: u8) {
+        while !self.transmit_empty() {
+            core::hint::spin_loop();
+        }
+        unsafe { self.port(DATA).write(byte) };
+    }
+
+    fn transmit_empty(&self) -> bool {
+        unsafe { self.port(LINE_STATUS).read() & TRANSMIT_EMPTY != 0 }
+    }
+
+    fn port(&self, offset: u16) -> Port<u8> {
+        Port::new(self.base + offset)
+    }
+}
+
+impl fmt::Write for SerialPort {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.send(byte);
+        }
+        Ok(())
+    }
+}
+
+lazy_static! {
+    pub static ref SERIAL1: Mutex<SerialPort> = {
+        let mut port = SerialPort::new(COM1);
+        port.init();
+        Mutex::new(port)
+    };
+}
+
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments) {
+    use core::fmt::Write;
+    // Same as the VGA writer, an interrupt handler may print too
+    interrupts::without_interrupts(|| {
+        SERIAL1.lock().write_fmt(args).expect("Printing to serial failed");
+    });
+}
+
+/// Write to COM1 without taking the SERIAL1 lock, for the panic handler.
+/// The panicking code may hold the lock, so this talks to the UART
+/// directly. Output can interleave with a half-written line, nothing
+/// else runs after a panic to make that worse.
+pub fn panic_print(args: fmt::Arguments) {
+    use core::fmt::Write;
+    let mut port = SerialPort::new(COM1);
+    // init clears the FIFOs, so only run it if nobody has yet
+    if !COM1_READY.load(Ordering::SeqCst) {
+        port.init();
+    }
+    let _ = port.write_fmt(args);
+}
@@ -9,15 +9,37 @@ use crate::println;
 use crate::{log_error, log_warn, log_info};
 use crate::error_handler::{report_filesystem_error, report_warning};
 use alloc::string::String;
+use alloc::vec;
 use alloc::vec::Vec;
 use crate::vga_buffer::Color;
 use crate::error_handler::{report_error, report_warning, ErrorDomain, ErrorSeverity};
+use crate::ata::{self, AtaDrive, SECTOR_SIZE};
+use crate::logger;
 
 // File system constants
 pub const MAX_FILES: usize = 100;
-const MAX_FILENAME_LENGTH: usize = 32;
-const MAX_FILE_SIZE: usize = 1024;  // 1KB per file
-const MAX_CONTENT_LENGTH: usize = MAX_FILE_SIZE - MAX_FILENAME_LENGTH;
+pub const MAX_FILENAME_LENGTH: usize = 32;
+pub const MAX_FILE_SIZE: usize = 32 * 1024;  // Content lives on the heap
+
+// On-disk layout: sector 0 holds the superblock, followed by one record
+// per used entry. Each record is a header sector followed by just enough
+// sectors to hold the content.
+const DISK_DRIVE: AtaDrive = AtaDrive::Slave;
+const DISK_MAGIC: [u8; 4] = *b"SCFS";
+const DISK_VERSION: u8 = 5;
+const SUPERBLOCK_LBA: u32 = 0;
+
+// Decode a little-endian u64 from an 8-byte slice
+fn read_u64(bytes: &[u8]) -> u64 {
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    u64::from_le_bytes(buf)
+}
+
+// Number of sectors needed to store `len` bytes of content
+fn content_sectors(len: usize) -> usize {
+    (len + SECTOR_SIZE - 1) / SECTOR_SIZE
+}
 
 // File type
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -29,28 +51,59 @@ pub enum FileType {
     Symlink,
 }
 
+impl FileType {
+    /// Regular and File both hold readable content
+    pub fn is_file(self) -> bool {
+        matches!(self, FileType::Regular | FileType::File)
+    }
+
+    fn to_disk(self) -> u8 {
+        match self {
+            FileType::Regular => 0,
+            FileType::Directory => 1,
+            FileType::File => 2,
+            FileType::Symlink => 3,
+        }
+    }
+
+    fn from_disk(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(FileType::Regular),
+            1 => Some(FileType::Directory),
+            2 => Some(FileType::File),
+            3 => Some(FileType::Symlink),
+            _ => None,
+        }
+    }
+}
+
 // File entry
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct FileEntry {
     name: [u8; MAX_FILENAME_LENGTH],
     name_len: usize,
     file_type: FileType,
-    content: [u8; MAX_FILE_SIZE],
-    content_len: usize,
+    content: Vec<u8>,
     is_used: bool,
-    size: usize,
+    parent: usize, // Index of the containing directory, root is its own parent
+    created: u64,  // System timer ticks when the entry was created
+    modified: u64, // System timer ticks when the content last changed
 }
 
+// An unused entry, lets the file table be built without FileEntry being Copy
+const EMPTY_ENTRY: FileEntry = FileEntry::new();
+
 impl FileEntry {
-    pub fn new() -> Self {
+    pub const fn new() -> Self {
         FileEntry {
             name: [0; MAX_FILENAME_LENGTH],
             name_len: 0,
             file_type: FileType::Regular,
-            content: [0; MAX_FILE_SIZE],
-            content_len: 0,
+            content: Vec::new(),
             is_used: false,
-            size: 0,
+            parent: 0,
+            created: 0,
+            modified: 0,
         }
     }
 
@@ -79,29 +132,27 @@ impl FileEntry {
     }
 
     pub fn set_content(&mut self, content: &str) -> bool {
-        if content.len() > MAX_CONTENT_LENGTH {
+        if content.len() > MAX_FILE_SIZE {
             return false;
         }
 
-        self.content_len = 0;
-        for (i, &byte) in content.as_bytes().iter().enumerate() {
-            if i >= MAX_CONTENT_LENGTH {
-                break;
-            }
-            self.content[i] = byte;
-            self.content_len += 1;
-        }
+        self.content.clear();
+        self.content.extend_from_slice(content.as_bytes());
         true
     }
 
     pub fn get_content(&self) -> &str {
         // Convert content buffer to a string
-        match str::from_utf8(&self.content[0..self.content_len]) {
+        match str::from_utf8(&self.content) {
             Ok(s) => s,
             Err(_) => "???",
         }
     }
 
+    pub fn get_bytes(&self) -> &[u8] {
+        &self.content
+    }
+
     pub fn set_type(&mut self, file_type: FileType) {
         self.file_type = file_type;
     }
@@ -111,10 +162,73 @@ impl FileEntry {
     }
 
     pub fn get_size(&self) -> usize {
-        self.content_len
+        self.content.len()
+    }
+
+    pub fn get_parent(&self) -> usize {
+        self.parent
+    }
+
+    pub fn get_created(&self) -> u64 {
+        self.created
+    }
+
+    pub fn get_modified(&self) -> u64 {
+        self.modified
+    }
+
+    // Stamp a newly created entry
+    fn set_created_now(&mut self) {
+        self.created = logger::now();
+        self.modified = self.created;
+    }
+
+    // Header sector layout:
+    // [0] used, [1] type, [2] name_len, [4..8] content length, [8..12] slot,
+    // [12..16] parent, [16..48] name, [48..56] created, [56..64] modified
+    // The slot is stored because `parent` refers to other entries by slot.
+    fn write_header(&self, slot: usize, sector: &mut [u8]) {
+        sector[0] = self.is_used as u8;
+        sector[1] = self.file_type.to_disk();
+        sector[2] = self.name_len as u8;
+        sector[4..8].copy_from_slice(&(self.content.len() as u32).to_le_bytes());
+        sector[8..12].copy_from_slice(&(slot as u32).to_le_bytes());
+        sector[12..16].copy_from_slice(&(self.parent as u32).to_le_bytes());
+        sector[16..16 + MAX_FILENAME_LENGTH].copy_from_slice(&self.name);
+        sector[48..56].copy_from_slice(&self.created.to_le_bytes());
+        sector[56..64].copy_from_slice(&self.modified.to_le_bytes());
+    }
+
+    // Returns the entry without content, its slot and the content length
+    // in bytes
+    fn read_header(sector: &[u8]) -> Result<(Self, usize, usize), &'static str> {
+        let mut entry = FileEntry::new();
+        entry.is_used = sector[0] != 0;
+        entry.file_type = FileType::from_disk(sector[1]).ok_or("Corrupt file record")?;
+        entry.name_len = sector[2] as usize;
+        let content_len = u32::from_le_bytes([sector[4], sector[5], sector[6], sector[7]]) as usize;
+        let slot = u32::from_le_bytes([sector[8], sector[9], sector[10], sector[11]]) as usize;
+        entry.parent = u32::from_le_bytes([sector[12], sector[13], sector[14], sector[15]]) as usize;
+        entry.name.copy_from_slice(&sector[16..16 + MAX_FILENAME_LENGTH]);
+        entry.created = read_u64(&sector[48..56]);
+        entry.modified = read_u64(&sector[56..64]);
+
+        if entry.name_len > MAX_FILENAME_LENGTH
+            || content_len > MAX_FILE_SIZE
+            || entry.parent >= MAX_FILES
+            || slot >= MAX_FILES
+        {
+            return Err("Corrupt file record");
+        }
+        Ok((entry, slot, content_len))
     }
 }
 
+/// Every used entry as (full path, type, content), sorted by path. Two
+/// snapshots compare equal when the visible state of the filesystem is
+/// the same, whichever slots the entries happen to use.
+pub type FsSnapshot = Vec<(SimpleString, FileType, SimpleString)>;
+
 // File system structure
 pub struct SimpleFileSystem {
     files: [FileEntry; MAX_FILES],
@@ -125,7 +239,7 @@ pub struct SimpleFileSystem {
 impl SimpleFileSystem {
     pub fn new() -> Self {
         let mut fs = SimpleFileSystem {
-            files: [FileEntry::new(); MAX_FILES],
+            files: [EMPTY_ENTRY; MAX_FILES],
             current_dir: 0,
             file_count: 0,
         };
@@ -146,10 +260,30 @@ impl SimpleFileSystem {
         fs
     }
 
-    /// Hitta en fil med det givna namnet
+    /// Hitta en fil med det givna namnet i den aktuella katalogen
     pub fn find_file(&self, name: &str) -> Option<usize> {
-        for i in 0..self.file_count {
-            if self.files[i].is_used && self.files[i].get_name() == name {
+        self.find_in_directory(self.current_dir, name)
+    }
+
+    /// Run `f` with the root as the current directory, for files that
+    /// live in one place whichever directory the shell is in
+    pub fn in_root<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let saved = self.current_dir;
+        self.current_dir = 0;
+        let result = f(self);
+        // Go back unless `f` removed the directory
+        if self.files[saved].is_used {
+            self.current_dir = saved;
+        }
+        result
+    }
+
+    // Find an entry by name among the children of `dir`
+    fn find_in_directory(&self, dir: usize, name: &str) -> Option<usize> {
+        // Skip the root entry, it is its own parent
+        for i in 1..MAX_FILES {
+            let file = &self.files[i];
+            if file.is_used && file.parent == dir && file.get_name() == name {
                 return Some(i);
             }
         }
@@ -182,29 +316,31 @@ impl SimpleFileSystem {
             return Err(error_msg);
         }
 
-        if content.len() >= MAX_FILE_SIZE {
+        if content.len() > MAX_FILE_SIZE {
             let error_msg = "File content too large";
             log_error!("{}", error_msg);
             report_filesystem_error(error_msg).ok();
             return Err(error_msg);
         }
 
-        let mut file = FileEntry::new();
-        
-        // Copy filename
-        for (i, &byte) in name.as_bytes().iter().enumerate() {
-            file.name[i] = byte;
-        }
-        
-        // Copy content
-        for (i, &byte) in content.as_bytes().iter().enumerate() {
-            file.content[i] = byte;
+        if self.find_file(name).is_some() {
+            let error_msg = "File already exists";
+            log_error!("{}", error_msg);
+            report_filesystem_error(error_msg).ok();
+            return Err(error_msg);
         }
-        
-        file.size = content.len();
+
+        let index = self.find_free_entry().ok_or("Filesystem is full")?;
+
+        let mut file = FileEntry::new();
+        file.set_name(name);
+        file.set_content(content);
         file.file_type = FileType::Regular;
+        file.parent = self.current_dir;
+        file.set_created_now();
+        file.is_used = true;
         
-        self.files[self.file_count] = file;
+        self.files[index] = file;
         self.file_count += 1;
         
         log_info!("File created: {}", name);
@@ -220,6 +356,8 @@ impl SimpleFileSystem {
         if let Some(index) = self.find_free_entry() {
             self.files[index].set_name(name);
             self.files[index].set_type(FileType::Directory);
+            self.files[index].parent = self.current_dir;
+            self.files[index].set_created_now();
             self.files[index].is_used = true;
             self.file_count += 1;
             true
@@ -229,23 +367,84 @@ impl SimpleFileSystem {
     }
 
     // Read a file
-    pub fn read_file(&self, name: &str) -> Option<&str> {
-        if let Some(index) = self.find_file(name) {
-            if self.files[index].get_type() == FileType::File {
-                Some(self.files[index].get_content())
-            } else {
-                None
+    pub fn read_file(&self, name: &str) -> Result<&str, &'static str> {
+        let index = self.find_file(name).ok_or("File not found")?;
+        if !self.files[index].get_type().is_file() {
+            return Err("Not a file");
+        }
+        Ok(self.files[index].get_content())
+    }
+
+    /// Raw content of a file, None if it doesn't exist or is a directory
+    pub fn read_file_bytes(&self, name: &str) -> Option<&[u8]> {
+        let index = self.find_file(name)?;
+        if !self.files[index].get_type().is_file() {
+            return None;
+        }
+        Some(self.files[index].get_bytes())
+    }
+
+    /// Number of entries in the directory `name`, None if it isn't a directory
+    pub fn count_children(&self, name: &str) -> Option<usize> {
+        let index = self.find_file(name)?;
+        if self.files[index].get_type().is_file() {
+            return None;
+        }
+        Some(self.files.iter().filter(|file| file.is_used && file.parent == index).count())
+    }
+
+    /// Number of used entries and bytes stored in regular files
+    pub fn usage(&self) -> (usize, usize) {
+        let used = self.files.iter().filter(|file| file.is_used);
+        used.fold((0, 0), |(entries, bytes), file| (entries + 1, bytes + file.get_size()))
+    }
+
+    /// Sizes of the entries in the directory `path` ("." for the current
+    /// one). Directories are reported with the total size below them.
+    pub fn disk_usage(&self, path: &str) -> Result<Vec<(String, FileType, usize)>, &'static str> {
+        let dir = match path {
+            "/" => 0,
+            "." => self.current_dir,
+            ".." => self.files[self.current_dir].parent,
+            name => {
+                let index = self.find_file(name).ok_or("Directory not found")?;
+                if self.files[index].get_type() != FileType::Directory {
+                    return Err("Not a directory");
+                }
+                index
             }
-        } else {
-            None
+        };
+
+        Ok(self.children(dir)
+            .map(|index| {
+                let file = &self.files[index];
+                (String::from(file.get_name()), file.get_type(), self.tree_size(index, 0))
+            })
+            .collect())
+    }
+
+    // Indices of the entries directly inside `dir`
+    fn children(&self, dir: usize) -> impl Iterator<Item = usize> + '_ {
+        // Skip the root entry, it is its own parent
+        (1..MAX_FILES).filter(move |&i| self.files[i].is_used && self.files[i].parent == dir)
+    }
+
+    // Size of an entry plus everything below it. The depth limit guards
+    // against parent loops in a corrupt image loaded from disk.
+    fn tree_size(&self, index: usize, depth: usize) -> usize {
+        if depth >= MAX_FILES {
+            return 0;
         }
+        let below: usize = self.children(index).map(|child| self.tree_size(child, depth + 1)).sum();
+        self.files[index].get_size() + below
     }
 
     // List files in current directory
     pub fn list_directory(&self) -> FileList {
         FileList {
             filesystem: self,
-            index: 0,
+            directory: self.current_dir,
+            index: 1,
         }
     }
 
@@ -255,6 +454,15 @@ impl SimpleFileSystem {
             self.current_dir = 0;
             return Ok(());
         }
+
+        if path == ".." {
+            self.current_dir = self.files[self.current_dir].parent;
+            return Ok(());
+        }
+
+        if path == "." {
+            return Ok(());
+        }
         
         let index = self.find_file(path)
             .ok_or("Directory not found")?;
@@ -267,24 +475,52 @@ impl SimpleFileSystem {
         Ok(())
     }
     
-    // Get current directory
-    pub fn get_current_directory(&self) -> &str {
-        self.files[self.current_dir].get_name()
+    // Get the full path of the current directory, e.g. "/system/logs"
+    pub fn get_current_directory(&self) -> String {
+        self.path_of(self.current_dir)
+    }
+
+    // Full path of the entry at `index`
+    fn path_of(&self, index: usize) -> String {
+        if index == 0 {
+            return String::from("/");
+        }
+
+        // Collect names from the entry up to the root. The depth limit
+        // guards against parent loops, like tree_size.
+        let mut names = Vec::new();
+        let mut dir = index;
+        while dir != 0 && names.len() < MAX_FILES {
+            names.push(self.files[dir].get_name());
+            dir = self.files[dir].parent;
+        }
+
+        let mut path = String::new();
+        for name in names.iter().rev() {
+            path.push('/');
+            path.push_str(name);
+        }
+        path
     }
 
     pub fn write_file(&mut self, name: &str, content: &str) -> bool {
         if let Some(index) = self.find_file(name) {
-            if self.files[index].get_type() == FileType::File {
-                self.files[index].set_content(content);
+            if self.files[index].get_type().is_file() && self.files[index].set_content(content) {
+                self.files[index].modified = logger::now();
                 true
             } else {
                 false
             }
         } else {
+            if content.len() > MAX_FILE_SIZE || name.len() >= MAX_FILENAME_LENGTH {
+                return false;
+            }
             if let Some(index) = self.find_free_entry() {
                 self.files[index].set_name(name);
                 self.files[index].set_type(FileType::File);
                 self.files[index].set_content(content);
+                self.files[index].parent = self.current_dir;
+                self.files[index].set_created_now();
                 self.files[index].is_used = true;
                 self.file_count += 1;
                 true
@@ -294,8 +530,150 @@ impl SimpleFileSystem {
         }
     }
     
+    /// Rename an entry in the current directory without copying its content
+    pub fn rename(&mut self, old: &str, new: &str) -> Result<(), &'static str> {
+        let index = self.find_file(old).ok_or("Source file not found")?;
+
+        if new.is_empty() {
+            return Err("New name is empty");
+        }
+        if new.len() >= MAX_FILENAME_LENGTH {
+            return Err("Filename too long");
+        }
+        if old == new {
+            return Ok(());
+        }
+        if self.find_file(new).is_some() {
+            return Err("A file with that name already exists");
+        }
+
+        self.files[index].name = [0; MAX_FILENAME_LENGTH];
+        self.files[index].set_name(new);
+        Ok(())
+    }
+
+    // The directory `name` names from the current one: `.`, `..`, `/` or
+    // a subdirectory
+    fn find_directory(&self, name: &str) -> Option<usize> {
+        match name {
+            "/" => Some(0),
+            "." => Some(self.current_dir),
+            ".." => Some(self.files[self.current_dir].parent),
+            _ => self.find_file(name).filter(|&index| self.files[index].get_type() == FileType::Directory),
+        }
+    }
+
+    /// Whether `name` is a directory, counting `.`, `..` and `/`
+    pub fn is_directory(&self, name: &str) -> bool {
+        self.find_directory(name).is_some()
+    }
+
+    /// Copy a file from the current directory into directory `dir`,
+    /// keeping its name. A file of that name in `dir` is overwritten.
+    pub fn copy_into(&mut self, name: &str, dir: &str) -> Result<(), &'static str> {
+        let source = self.find_file(name).ok_or("Source file not found")?;
+        let target_dir = self.find_directory(dir).ok_or("Directory not found")?;
+        if !self.files[source].get_type().is_file() {
+            return Err("Source is a directory");
+        }
+        if target_dir == self.current_dir {
+            return Err("Source and destination are the same file");
+        }
+
+        let content = String::from(self.files[source].get_content());
+        match self.find_in_directory(target_dir, name) {
+            Some(existing) if !self.files[existing].get_type().is_file() => {
+                Err("A directory with that name already exists")
+            }
+            Some(existing) => {
+                self.files[existing].set_content(&content);
+                self.files[existing].modified = logger::now();
+                Ok(())
+            }
+            None => {
+                let index = self.find_free_entry().ok_or("Filesystem is full")?;
+                let mut file = FileEntry::new();
+                file.set_name(name);
+                file.set_content(&content);
+                file.file_type = self.files[source].get_type();
+                file.parent = target_dir;
+                file.set_created_now();
+                file.is_used = true;
+
+                self.files[index] = file;
+                self.file_count += 1;
+                Ok(())
+            }
+        }
+    }
+
+    /// Move an entry from the current directory into directory `dir`,
+    /// keeping its name. Only the parent link changes, nothing is copied.
+    pub fn move_into(&mut self, name: &str, dir: &str) -> Result<(), &'static str> {
+        let index = self.find_file(name).ok_or("Source file not found")?;
+        let target_dir = self.find_directory(dir).ok_or("Directory not found")?;
+        if target_dir == self.current_dir {
+            return Ok(());
+        }
+
+        // A directory can't go inside itself. Bounded like path_of.
+        let mut ancestor = target_dir;
+        for _ in 0..MAX_FILES {
+            if ancestor == index {
+                return Err("Cannot move a directory into itself");
+            }
+            if ancestor == 0 {
+                break;
+            }
+            ancestor = self.files[ancestor].parent;
+        }
+
+        if self.find_in_directory(target_dir, name).is_some() {
+            return Err("A file with that name already exists");
+        }
+        self.files[index].parent = target_dir;
+        Ok(())
+    }
+
+    /// Update the modification time of an existing entry
+    pub fn touch(&mut self, name: &str) -> bool {
+        match self.find_file(name) {
+            Some(index) => {
+                self.files[index].modified = logger::now();
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Add `content` to the end of an existing file. Returns false if the
+    /// file is missing or the result would not fit in MAX_FILE_SIZE.
+    pub fn append_file(&mut self, name: &str, content: &str) -> bool {
+        let index = match self.find_file(name) {
+            Some(index) => index,
+            None => return false,
+        };
+
+        let file = &mut self.files[index];
+        if !file.get_type().is_file() {
+            return false;
+        }
+
+        if file.content.len() + content.len() > MAX_FILE_SIZE {
+            return false;
+        }
+
+        file.content.extend_from_slice(content.as_bytes());
+        file.modified = logger::now();
+        true
+    }
+    
     pub fn delete_file(&mut self, name: &str) -> bool {
         if let Some(index) = self.find_file(name) {
+            // Don't orphan the contents of a directory
+            if self.files.iter().any(|file| file.is_used && file.parent == index) {
+                return false;
+            }
             self.files[index].is_used = false;
             self.file_count -= 1;
             true
@@ -304,6 +682,59 @@ impl SimpleFileSystem {
         }
     }
 
+    /// Remove an entry of the current directory together with everything
+    /// below it
+    pub fn delete_recursive(&mut self, name: &str) -> Result<(), &'static str> {
+        let index = self.find_file(name).ok_or("File not found")?;
+        let mut doomed = Vec::new();
+        self.collect_tree(index, 0, &mut doomed);
+
+        for &i in &doomed {
+            self.files[i].is_used = false;
+        }
+        self.file_count -= doomed.len();
+        log_info!("Removed {} and {} entries below it", name, doomed.len() - 1);
+        Ok(())
+    }
+
+    /// Number of entries below `name` in the current directory, or None
+    /// if there is no such entry
+    pub fn count_below(&self, name: &str) -> Option<usize> {
+        let index = self.find_file(name)?;
+        let mut tree = Vec::new();
+        self.collect_tree(index, 0, &mut tree);
+        Some(tree.len() - 1)
+    }
+
+    // `index` and all its descendants, each once. The depth limit and the
+    // duplicate check guard against parent loops, like tree_size.
+    fn collect_tree(&self, index: usize, depth: usize, out: &mut Vec<usize>) {
+        if depth >= MAX_FILES || out.contains(&index) {
+            return;
+        }
+        out.push(index);
+        for child in self.children(index) {
+            self.collect_tree(child, depth + 1, out);
+        }
+    }
+
+    /// Capture the state of every used entry, for comparing in tests
+    pub fn snapshot(&self) -> FsSnapshot {
+        let mut entries: FsSnapshot = (1..MAX_FILES)
+            .filter(|&i| self.files[i].is_used)
+            .map(|i| {
+                let file = &self.files[i];
+                let mut path = SimpleString::new();
+                path.push_str(&self.path_of(i));
+                let mut content = SimpleString::new();
+                content.push_str(file.get_content());
+                (path, file.get_type(), content)
+            })
+            .collect();
+        entries.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+        entries
+    }
+
     pub fn get_file_count(&self) -> usize {
         self.file_count
     }
@@ -316,23 +747,214 @@ impl SimpleFileSystem {
     }
 
     pub fn get_file_type(&self, index: usize) -> FileType {
-        if index >= self.file_count {
+        if index >= MAX_FILES || !self.files[index].is_used {
             return FileType::Regular;
         }
         self.files[index].file_type
     }
 
     pub fn get_file_size(&self, index: usize) -> usize {
-        if index >= self.file_count {
+        if index >= MAX_FILES || !self.files[index].is_used {
             return 0;
         }
-        self.files[index].size
+        self.files[index].get_size()
+    }
+
+    pub fn get_file_modified(&self, index: usize) -> u64 {
+        if index >= MAX_FILES {
+            return 0;
+        }
+        self.files[index].modified
+    }
+
+    /// Check the entry table for inconsistencies, reporting the first one found
+    pub fn check_integrity(&self) -> Result<(), &'static str> {
+        if !self.files[0].is_used || self.files[0].get_type() != FileType::Directory {
+            return Err("Root directory is missing");
+        }
+        for index in 1..MAX_FILES {
+            if let Some(problem) = self.entry_problem(index) {
+                return Err(problem);
+            }
+        }
+        if self.used_entries() != self.file_count {
+            return Err("File count does not match the used entries");
+        }
+        Ok(())
+    }
+
+    /// Clear corrupt entries and fix the file count. Returns how many
+    /// entries were cleared.
+    pub fn repair(&mut self) -> usize {
+        if !self.files[0].is_used || self.files[0].get_type() != FileType::Directory {
+            log_warn!("SimpleFS: recreating the root directory");
+            self.files[0] = FileEntry::new();
+            self.files[0].set_name("/");
+            self.files[0].set_type(FileType::Directory);
+            self.files[0].is_used = true;
+        }
+
+        // Clearing a directory orphans its children, so go until nothing changes
+        let mut cleared = 0;
+        loop {
+            let mut changed = false;
+            for index in 1..MAX_FILES {
+                if let Some(problem) = self.entry_problem(index) {
+                    log_warn!("SimpleFS: clearing entry {}: {}", index, problem);
+                    self.files[index] = FileEntry::new();
+                    cleared += 1;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        if !self.files[self.current_dir].is_used {
+            self.current_dir = 0;
+        }
+        self.file_count = self.used_entries();
+        cleared
+    }
+
+    // Used entries, not counting the root directory
+    fn used_entries(&self) -> usize {
+        self.files[1..].iter().filter(|file| file.is_used).count()
+    }
+
+    // What, if anything, is wrong with the entry at `index`
+    fn entry_problem(&self, index: usize) -> Option<&'static str> {
+        let file = &self.files[index];
+        if !file.is_used {
+            return None;
+        }
+        if file.name_len == 0 || file.name_len > MAX_FILENAME_LENGTH {
+            return Some("Name length out of bounds");
+        }
+        if str::from_utf8(&file.name[..file.name_len]).is_err() {
+            return Some("Name is not valid text");
+        }
+        if file.content.len() > MAX_FILE_SIZE {
+            return Some("Content length out of bounds");
+        }
+        if file.parent >= MAX_FILES
+            || !self.files[file.parent].is_used
+            || self.files[file.parent].get_type() != FileType::Directory
+        {
+            return Some("Parent directory is missing");
+        }
+        // The first of two entries with the same name is the one kept
+        let duplicate = (1..index).any(|other| {
+            let other = &self.files[other];
+            other.is_used
+                && other.parent == file.parent
+                && other.name.get(..other.name_len) == Some(&file.name[..file.name_len])
+        });
+        if duplicate {
+            return Some("Another entry has the same name");
+        }
+        None
+    }
+
+    /// Whether a data disk is attached for `flush_to_disk`
+    pub fn has_data_disk(&self) -> bool {
+        ata::identify(DISK_DRIVE)
+    }
+
+    /// Write every used entry to the data disk
+    pub fn flush_to_disk(&self) -> Result<(), &'static str> {
+        if !ata::identify(DISK_DRIVE) {
+            return Err("No data disk attached");
+        }
+
+        let mut lba = SUPERBLOCK_LBA + 1;
+        let mut written: u32 = 0;
+
+        for (slot, entry) in self.files.iter().enumerate().filter(|(_, entry)| entry.is_used) {
+            let sectors = 1 + content_sectors(entry.content.len());
+            let mut record = vec![0u8; sectors * SECTOR_SIZE];
+            entry.write_header(slot, &mut record[..SECTOR_SIZE]);
+            record[SECTOR_SIZE..SECTOR_SIZE + entry.content.len()].copy_from_slice(&entry.content);
+
+            ata::write_sectors(DISK_DRIVE, lba, &record)?;
+            lba += sectors as u32;
+            written += 1;
+        }
+
+        // Write the superblock last, once every record is on disk
+        let mut superblock = [0u8; SECTOR_SIZE];
+        superblock[0..4].copy_from_slice(&DISK_MAGIC);
+        superblock[4] = DISK_VERSION;
+        superblock[8..12].copy_from_slice(&written.to_le_bytes());
+        ata::write_sectors(DISK_DRIVE, SUPERBLOCK_LBA, &superblock)?;
+
+        log_info!("SimpleFS: flushed {} entries to disk", written);
+        Ok(())
+    }
+
+    /// Replace the in-memory entries with the ones stored on the data disk
+    pub fn load_from_disk(&mut self) -> Result<(), &'static str> {
+        if !ata::identify(DISK_DRIVE) {
+            return Err("No data disk attached");
+        }
+
+        let mut superblock = [0u8; SECTOR_SIZE];
+        ata::read_sectors(DISK_DRIVE, SUPERBLOCK_LBA, &mut superblock)?;
+
+        if superblock[0..4] != DISK_MAGIC {
+            return Err("Data disk is not formatted");
+        }
+        if superblock[4] != DISK_VERSION {
+            return Err("Unsupported disk format version");
+        }
+
+        let count = u32::from_le_bytes([superblock[8], superblock[9], superblock[10], superblock[11]]) as usize;
+        if count > MAX_FILES {
+            return Err("Corrupt superblock");
+        }
+
+        // Read everything before touching the live entries
+        let mut entries = Vec::with_capacity(count);
+        let mut header = [0u8; SECTOR_SIZE];
+        let mut lba = SUPERBLOCK_LBA + 1;
+        for _ in 0..count {
+            ata::read_sectors(DISK_DRIVE, lba, &mut header)?;
+            let (mut entry, slot, content_len) = FileEntry::read_header(&header)?;
+            lba += 1;
+
+            let sectors = content_sectors(content_len);
+            if sectors > 0 {
+                let mut content = vec![0u8; sectors * SECTOR_SIZE];
+                ata::read_sectors(DISK_DRIVE, lba, &mut content)?;
+                content.truncate(content_len);
+                entry.content = content;
+                lba += sectors as u32;
+            }
+
+            // Back into the slot it was saved from, so parent links hold
+            if entries.iter().any(|(used, _)| *used == slot) {
+                return Err("Corrupt file record");
+            }
+            entries.push((slot, entry));
+        }
+
+        self.files = [EMPTY_ENTRY; MAX_FILES];
+        for (slot, entry) in entries {
+            self.files[slot] = entry;
+        }
+        self.file_count = self.used_entries();
+        self.current_dir = 0;
+
+        log_info!("SimpleFS: loaded {} entries from disk", count);
+        Ok(())
     }
 }
 
 // File listing iterator
 pub struct FileList<'a> {
     filesystem: &'a SimpleFileSystem,
+    directory: usize,
     index: usize,
 }
 
@@ -344,12 +966,9 @@ impl<'a> Iterator for FileList<'a> {
             let current = self.index;
             self.index += 1;
             
-            if self.filesystem.files[current].is_used {
-                return Some((
-                    self.filesystem.files[current].get_type(),
-                    self.filesystem.files[current].get_name(),
-                    self.filesystem.files[current].get_size(),
-                ));
+            let file = &self.filesystem.files[current];
+            if file.is_used && file.parent == self.directory {
+                return Some((file.get_type(), file.get_name(), file.get_size()));
             }
         }
         
@@ -357,64 +976,60 @@ impl<'a> Iterator for FileList<'a> {
     }
 }
 
-// A simple string implementation to handle text without depending on alloc
-#[derive(Debug)]
+// A simple string type with the small API the rest of the kernel uses,
+// backed by a heap buffer so long lines and file contents aren't truncated
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SimpleString {
-    buffer: [u8; 256],
-    len: usize,
+    buffer: String,
 }
 
 impl SimpleString {
-    pub fn new() -> Self {
+    pub const fn new() -> Self {
         SimpleString {
-            buffer: [0; 256],
-            len: 0,
+            buffer: String::new(),
         }
     }
 
-    pub fn push(&mut self, c: char) {
-        if self.len < 255 {
-            self.buffer[self.len] = c as u8;
-            self.len += 1;
+    /// An empty string with room for `capacity` bytes
+    pub fn with_capacity(capacity: usize) -> Self {
+        SimpleString {
+            buffer: String::with_capacity(capacity),
         }
     }
 
+    /// The decimal representation of `num`
+    pub fn from_usize(num: usize) -> Self {
+        let mut s = SimpleString::new();
+        crate::string_ext::usize_to_str(num, &mut s);
+        s
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.buffer.push(c);
+    }
+
     pub fn push_str(&mut self, s: &str) {
-        for c in s.chars() {
-            self.push(c);
-        }
+        self.buffer.push_str(s);
     }
 
     pub fn clear(&mut self) {
-        self.len = 0;
-        for byte in self.buffer.iter_mut() {
-            *byte = 0;
-        }
+        self.buffer.clear();
     }
 
     pub fn as_str(&self) -> &str {
-        // Konvertera bytes[0..len] till en str slice
-        // Detta är säkert eftersom vi bara lägger till giltiga UTF-8 tecken i push() och push_str()
-        unsafe {
-            core::str::from_utf8_unchecked(&self.buffer[0..self.len])
-        }
+        self.buffer.as_str()
     }
 
     pub fn len(&self) -> usize {
-        self.len
+        self.buffer.len()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.len == 0
+        self.buffer.is_empty()
     }
 
     pub fn pop(&mut self) -> Option<char> {
-        if self.len > 0 {
-            self.len -= 1;
-            Some(self.buffer[self.len] as char)
-        } else {
-            None
-        }
+        self.buffer.pop()
     }
 }
 
@@ -433,8 +1048,7 @@ impl Default for SimpleString {
 
 impl fmt::Display for SimpleString {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let s = unsafe { core::str::from_utf8_unchecked(&self.buffer[..self.len]) };
-        write!(f, "{}", s)
+        f.write_str(&self.buffer)
     }
 }
 
@@ -445,10 +1059,366 @@ lazy_static! {
 
 // Initialization of the file system
 pub fn init() {
+    // Without a data disk we keep running on the in-memory defaults
+    if let Err(e) = FILESYSTEM.lock().load_from_disk() {
+        log_warn!("SimpleFS: {}, using in-memory file system", e);
+        report_warning(ErrorDomain::Filesystem, e).ok();
+    }
+
     println!("SimpleFS: file system initialized");
 }
 
 // Re-export necessary type names with simpler names
 pub use SimpleString as String;
 
-pub type FsString = String; 
\ No newline at end of file
+pub type FsString = String; 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn new_file_system_is_consistent() {
+        let fs = SimpleFileSystem::new();
+        assert_eq!(fs.check_integrity(), Ok(()));
+    }
+
+    #[test_case]
+    fn snapshot_lists_paths_types_and_content() {
+        let mut fs = SimpleFileSystem::new();
+        fs.change_directory("home").unwrap();
+        fs.create_file("notes.txt", "hi").unwrap();
+
+        let snapshot = fs.snapshot();
+        let notes = snapshot.iter().find(|(path, _, _)| path.as_str() == "/home/notes.txt");
+        let (_, file_type, content) = notes.expect("notes.txt missing from snapshot");
+        assert_eq!(*file_type, FileType::Regular);
+        assert_eq!(content.as_str(), "hi");
+        assert!(snapshot.iter().any(|(path, file_type, _)| path.as_str() == "/tmp" && *file_type == FileType::Directory));
+    }
+
+    #[test_case]
+    fn create_then_delete_restores_state() {
+        let mut fs = SimpleFileSystem::new();
+        let before = fs.snapshot();
+
+        fs.create_file("scratch.txt", "data").unwrap();
+        assert!(fs.create_directory("scratch"));
+        assert_ne!(fs.snapshot(), before);
+
+        assert!(fs.delete_file("scratch.txt"));
+        assert!(fs.delete_file("scratch"));
+        assert_eq!(fs.snapshot(), before);
+    }
+
+    #[test_case]
+    fn rename_round_trip_restores_state() {
+        let mut fs = SimpleFileSystem::new();
+        let before = fs.snapshot();
+
+        fs.rename("readme.txt", "a-much-longer-name.txt").unwrap();
+        assert_ne!(fs.snapshot(), before);
+        fs.rename("a-much-longer-name.txt", "readme.txt").unwrap();
+        assert_eq!(fs.snapshot(), before);
+    }
+
+    #[test_case]
+    fn failed_operations_leave_state_alone() {
+        let mut fs = SimpleFileSystem::new();
+        fs.change_directory("home").unwrap();
+        fs.create_file("keep.txt", "x").unwrap();
+        fs.change_directory("/").unwrap();
+        let before = fs.snapshot();
+
+        // A non-empty directory and a clashing rename are both refused
+        assert!(!fs.delete_file("home"));
+        assert!(fs.rename("system", "tmp").is_err());
+        assert_eq!(fs.snapshot(), before);
+    }
+
+    #[test_case]
+    fn repair_fixes_file_count() {
+        let mut fs = SimpleFileSystem::new();
+        fs.file_count += 3;
+        assert!(fs.check_integrity().is_err());
+
+        assert_eq!(fs.repair(), 0);
+        assert_eq!(fs.check_integrity(), Ok(()));
+    }
+
+    #[test_case]
+    fn repair_clears_duplicate_and_corrupt_entries() {
+        let mut fs = SimpleFileSystem::new();
+        fs.create_file("a.txt", "first").unwrap();
+        fs.create_file("b.txt", "second").unwrap();
+        let b = fs.find_file("b.txt").unwrap();
+        fs.files[b].set_name("a.txt");
+        assert_eq!(fs.check_integrity(), Err("Another entry has the same name"));
+
+        let readme = fs.find_file("readme.txt").unwrap();
+        fs.files[readme].name_len = MAX_FILENAME_LENGTH + 1;
+
+        assert_eq!(fs.repair(), 2);
+        assert_eq!(fs.check_integrity(), Ok(()));
+        assert_eq!(fs.read_file("a.txt"), Ok("first"));
+    }
+
+    #[test_case]
+    fn repair_clears_children_of_cleared_directories() {
+        let mut fs = SimpleFileSystem::new();
+        assert!(fs.create_directory("docs"));
+        fs.change_directory("docs").unwrap();
+        fs.create_file("notes.txt", "hello").unwrap();
+        let docs = fs.current_dir;
+        fs.files[docs].name_len = 0;
+
+        assert_eq!(fs.repair(), 2);
+        assert_eq!(fs.current_dir, 0);
+        assert_eq!(fs.check_integrity(), Ok(()));
+    }
+
+    #[test_case]
+    fn in_root_reaches_root_files_and_comes_back() {
+        let mut fs = SimpleFileSystem::new();
+        fs.change_directory("home").unwrap();
+        let home = fs.current_dir;
+
+        assert!(fs.in_root(|fs| fs.write_file(".history", "ls\n")));
+        assert_eq!(fs.current_dir, home);
+        assert!(fs.find_file(".history").is_none());
+        assert_eq!(fs.in_root(|fs| fs.read_file(".history").map(String::from)), Ok(String::from("ls\n")));
+    }
+
+    #[test_case]
+    fn subdirectory_scopes_files() {
+        let mut fs = SimpleFileSystem::new();
+        assert!(fs.create_directory("docs"));
+        fs.change_directory("docs").unwrap();
+        fs.create_file("notes.txt", "hello").unwrap();
+
+        assert!(fs.find_file("notes.txt").is_some());
+        assert!(fs.find_file("readme.txt").is_none());
+        assert_eq!(fs.list_directory().count(), 1);
+
+        fs.change_directory("..").unwrap();
+        assert!(fs.find_file("notes.txt").is_none());
+        assert!(fs.find_file("docs").is_some());
+    }
+
+    #[test_case]
+    fn disk_usage_sums_subdirectories() {
+        let mut fs = SimpleFileSystem::new();
+        fs.change_directory("home").unwrap();
+        fs.create_file("a.txt", "12345").unwrap();
+        assert!(fs.create_directory("nested"));
+        fs.change_directory("nested").unwrap();
+        fs.create_file("b.txt", "123").unwrap();
+        fs.change_directory("/").unwrap();
+
+        let usage = fs.disk_usage(".").unwrap();
+        let home = usage.iter().find(|(name, _, _)| name == "home").unwrap();
+        assert_eq!(home.2, 8);
+        assert_eq!(fs.disk_usage("readme.txt"), Err("Not a directory"));
+    }
+
+    #[test_case]
+    fn simple_string_holds_long_text() {
+        let mut s = SimpleString::with_capacity(16);
+        for i in 0..1000 {
+            s.push((b'a' + (i % 26) as u8) as char);
+        }
+        assert_eq!(s.len(), 1000);
+        assert!(s.as_str().chars().enumerate().all(|(i, c)| c == (b'a' + (i % 26) as u8) as char));
+
+        let copy = s.clone();
+        s.clear();
+        assert_eq!(copy.len(), 1000);
+    }
+
+    #[test_case]
+    fn parent_of_root_is_root() {
+        let mut fs = SimpleFileSystem::new();
+        fs.change_directory("..").unwrap();
+        assert_eq!(fs.get_current_directory(), "/");
+    }
+
+    #[test_case]
+    fn current_directory_is_full_path() {
+        let mut fs = SimpleFileSystem::new();
+        fs.change_directory("system").unwrap();
+        assert_eq!(fs.get_current_directory(), "/system");
+
+        assert!(fs.create_directory("logs"));
+        fs.change_directory("logs").unwrap();
+        assert_eq!(fs.get_current_directory(), "/system/logs");
+
+        fs.change_directory("..").unwrap();
+        fs.change_directory("..").unwrap();
+        assert_eq!(fs.get_current_directory(), "/");
+    }
+
+    #[test_case]
+    fn read_file_returns_created_content() {
+        let mut fs = SimpleFileSystem::new();
+        fs.create_file("hello.txt", "Hello, world!").unwrap();
+        assert_eq!(fs.read_file("hello.txt"), Ok("Hello, world!"));
+    }
+
+    #[test_case]
+    fn read_file_bytes_skips_directories() {
+        let mut fs = SimpleFileSystem::new();
+        fs.create_file("hello.txt", "Hi").unwrap();
+        fs.create_directory("docs");
+        assert_eq!(fs.read_file_bytes("hello.txt"), Some(&b"Hi"[..]));
+        assert_eq!(fs.read_file_bytes("docs"), None);
+        assert_eq!(fs.read_file_bytes("missing.txt"), None);
+    }
+
+    #[test_case]
+    fn write_file_updates_created_file() {
+        let mut fs = SimpleFileSystem::new();
+        fs.create_file("hello.txt", "old").unwrap();
+        assert!(fs.write_file("hello.txt", "new"));
+        assert_eq!(fs.read_file("hello.txt"), Ok("new"));
+    }
+
+    #[test_case]
+    fn write_file_stores_more_than_one_kilobyte() {
+        let mut fs = SimpleFileSystem::new();
+        let content = "abcdefgh".repeat(512);
+        assert!(fs.write_file("large.txt", &content));
+        assert_eq!(fs.read_file("large.txt").map(|c| c.len()), Ok(4096));
+        assert_eq!(fs.read_file("large.txt"), Ok(content.as_str()));
+    }
+
+    #[test_case]
+    fn touch_updates_modified_only() {
+        let mut fs = SimpleFileSystem::new();
+        fs.create_file("stamp.txt", "").unwrap();
+        let index = fs.find_file("stamp.txt").unwrap();
+        let created = fs.files[index].get_created();
+
+        fs.files[index].modified = u64::MAX;
+        assert!(fs.touch("stamp.txt"));
+        assert_eq!(fs.files[index].get_created(), created);
+        assert!(fs.files[index].get_modified() <= logger::now());
+        assert!(!fs.touch("missing.txt"));
+    }
+
+    #[test_case]
+    fn rename_keeps_content() {
+        let mut fs = SimpleFileSystem::new();
+        fs.create_file("old.txt", "data").unwrap();
+        fs.rename("old.txt", "new.txt").unwrap();
+        assert!(fs.find_file("old.txt").is_none());
+        assert_eq!(fs.read_file("new.txt"), Ok("data"));
+    }
+
+    #[test_case]
+    fn rename_rejects_collisions_and_missing_source() {
+        let mut fs = SimpleFileSystem::new();
+        fs.create_file("a.txt", "a").unwrap();
+        fs.create_file("b.txt", "b").unwrap();
+        assert!(fs.rename("a.txt", "b.txt").is_err());
+        assert!(fs.rename("missing.txt", "c.txt").is_err());
+        assert!(fs.rename("a.txt", "a-name-that-is-far-too-long-for-the-fs.txt").is_err());
+    }
+
+    #[test_case]
+    fn copy_into_keeps_the_name_and_the_source() {
+        let mut fs = SimpleFileSystem::new();
+        fs.create_directory("docs");
+        fs.create_file("note.txt", "hello").unwrap();
+        fs.copy_into("note.txt", "docs").unwrap();
+        assert!(fs.copy_into("note.txt", ".").is_err());
+
+        assert_eq!(fs.read_file("note.txt"), Ok("hello"));
+        fs.change_directory("docs").unwrap();
+        assert_eq!(fs.read_file("note.txt"), Ok("hello"));
+    }
+
+    #[test_case]
+    fn move_into_relinks_without_copying() {
+        let mut fs = SimpleFileSystem::new();
+        fs.create_directory("docs");
+        fs.create_file("note.txt", "hello").unwrap();
+        let index = fs.find_file("note.txt").unwrap();
+        fs.move_into("note.txt", "docs").unwrap();
+        assert!(fs.find_file("note.txt").is_none());
+        assert!(fs.move_into("docs", "docs").is_err());
+
+        fs.change_directory("docs").unwrap();
+        assert_eq!(fs.find_file("note.txt"), Some(index));
+        fs.move_into("note.txt", "..").unwrap();
+        assert!(fs.find_file("note.txt").is_none());
+    }
+
+    #[test_case]
+    fn delete_recursive_removes_a_whole_tree() {
+        let mut fs = SimpleFileSystem::new();
+        let before = fs.get_file_count();
+        fs.create_directory("tree");
+        fs.change_directory("tree").unwrap();
+        fs.create_file("leaf.txt", "leaf").unwrap();
+        fs.create_directory("branch");
+        fs.change_directory("branch").unwrap();
+        fs.create_file("deep.txt", "deep").unwrap();
+
+        fs.change_directory("/").unwrap();
+        assert!(!fs.delete_file("tree"));
+        assert_eq!(fs.count_below("tree"), Some(3));
+        fs.delete_recursive("tree").unwrap();
+
+        assert!(fs.find_file("tree").is_none());
+        assert_eq!(fs.get_file_count(), before);
+        assert_eq!(fs.used_entries(), fs.get_file_count());
+        assert!(fs.delete_recursive("tree").is_err());
+    }
+
+    #[test_case]
+    fn headers_keep_slot_and_parent() {
+        let mut fs = SimpleFileSystem::new();
+        fs.create_directory("sub");
+        fs.change_directory("sub").unwrap();
+        fs.create_file("inner.txt", "x").unwrap();
+        let index = fs.find_file("inner.txt").unwrap();
+
+        let mut sector = [0u8; SECTOR_SIZE];
+        fs.files[index].write_header(index, &mut sector);
+        let (entry, slot, len) = FileEntry::read_header(&sector).unwrap();
+        assert_eq!(slot, index);
+        assert_eq!(entry.parent, fs.files[index].parent);
+        assert_eq!(len, 1);
+    }
+
+    #[test_case]
+    fn entries_past_file_count_report_their_type() {
+        let mut fs = SimpleFileSystem::new();
+        fs.create_file("gap1.txt", "").unwrap();
+        fs.create_file("gap2.txt", "").unwrap();
+        fs.create_directory("late");
+        assert!(fs.delete_file("gap1.txt") && fs.delete_file("gap2.txt"));
+        let index = fs.find_file("late").unwrap();
+        assert!(index >= fs.get_file_count());
+        assert_eq!(fs.get_file_type(index), FileType::Directory);
+        assert_eq!(fs.get_file_type(MAX_FILES), FileType::Regular);
+    }
+
+    #[test_case]
+    fn append_file_concatenates_content() {
+        let mut fs = SimpleFileSystem::new();
+        fs.create_file("log.txt", "one\n").unwrap();
+        assert!(fs.append_file("log.txt", "two\n"));
+        assert_eq!(fs.read_file("log.txt"), Ok("one\ntwo\n"));
+    }
+
+    #[test_case]
+    fn append_file_rejects_overflow() {
+        let mut fs = SimpleFileSystem::new();
+        fs.create_file("big.txt", "").unwrap();
+        let chunk = "0123456789abcdef0123456789abcdef";
+        while fs.append_file("big.txt", chunk) {}
+        assert!(fs.read_file("big.txt").unwrap().len() <= MAX_FILE_SIZE);
+        assert!(!fs.append_file("missing.txt", "x"));
+    }
+}
@@ -0,0 +1,84 @@
+// src/sound.rs
+// PC speaker tones through PIT channel 2
+
+use x86_64::instructions::interrupts;
+use x86_64::instructions::port::Port;
+use crate::interrupts::{PIT_BASE_FREQUENCY, PIT_CHANNEL_2, PIT_COMMAND, SPEAKER_PORT};
+
+const PIT_FREQUENCY: u32 = PIT_BASE_FREQUENCY as u32;
+
+// Channel 2, low byte then high byte, mode 3 (square wave)
+const CHANNEL_2_SQUARE_WAVE: u8 = 0xB6;
+// Bit 0 gates channel 2, bit 1 connects its output to the speaker
+const SPEAKER_BITS: u8 = 0x03;
+
+/// Play a tone on the PC speaker and wait until it is done. The wait
+/// sleeps on timer ticks, so the length is rounded up to whole ticks
+/// (~55ms). With interrupts disabled no ticks arrive, and the call
+/// returns without a sound instead of hanging.
+pub fn beep(frequency_hz: u32, duration_ms: u32) {
+    if frequency_hz == 0 || duration_ms == 0 || !interrupts::are_enabled() {
+        return;
+    }
+
+    speaker_on(divisor(frequency_hz));
+
+    let start = crate::interrupts::ticks();
+    let length = crate::interrupts::ticks_for_ms(u64::from(duration_ms));
+    while crate::interrupts::ticks() - start < length {
+        x86_64::instructions::hlt();
+    }
+
+    speaker_off();
+}
+
+/// Silence the speaker
+pub fn speaker_off() {
+    interrupts::without_interrupts(|| {
+        let mut port = Port::<u8>::new(SPEAKER_PORT);
+        unsafe {
+            let state = port.read();
+            port.write(state & !SPEAKER_BITS);
+        }
+    });
+}
+
+// Program channel 2 and open the gate. Interrupts are held off so
+// nothing gets between the two divisor bytes.
+fn speaker_on(divisor: u16) {
+    interrupts::without_interrupts(|| {
+        let mut command = Port::<u8>::new(PIT_COMMAND);
+        let mut channel = Port::<u8>::new(PIT_CHANNEL_2);
+        let mut speaker = Port::<u8>::new(SPEAKER_PORT);
+        unsafe {
+            command.write(CHANNEL_2_SQUARE_WAVE);
+            channel.write(divisor as u8);
+            channel.write((divisor >> 8) as u8);
+
+            let state = speaker.read();
+            speaker.write(state | SPEAKER_BITS);
+        }
+    });
+}
+
+// PIT divisor for a frequency, clamped to what the 16-bit counter can hold
+fn divisor(frequency_hz: u32) -> u16 {
+    (PIT_FREQUENCY / frequency_hz.max(1)).clamp(1, u32::from(u16::MAX)) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn divisor_matches_frequency() {
+        assert_eq!(divisor(1000), 1193);
+        assert_eq!(divisor(440), 2711);
+    }
+
+    #[test_case]
+    fn divisor_is_clamped() {
+        assert_eq!(divisor(1), u16::MAX);
+        assert_eq!(divisor(PIT_FREQUENCY * 2), 1);
+    }
+}
@@ -69,4 +69,120 @@ impl StringExt for SimpleString {
     fn to_lowercase(&self) -> SimpleString {
         self.as_str().to_lowercase()
     }
-} 
\ No newline at end of file
+}
+
+/// Append `num` in decimal to `buf` without going through `core::fmt`
+pub fn u64_to_str(mut num: u64, buf: &mut SimpleString) {
+    let mut digits = [0u8; 20];
+    let mut i = 0;
+    loop {
+        digits[i] = (num % 10) as u8;
+        num /= 10;
+        i += 1;
+        if num == 0 {
+            break;
+        }
+    }
+
+    for j in (0..i).rev() {
+        buf.push((digits[j] + b'0') as char);
+    }
+}
+
+/// Append `num` in decimal to `buf`
+pub fn usize_to_str(num: usize, buf: &mut SimpleString) {
+    u64_to_str(num as u64, buf);
+}
+
+/// Whether `name` matches a shell pattern, where `*` stands for any run
+/// of characters and `?` for exactly one
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (pattern.as_bytes(), name.as_bytes());
+    let (mut p, mut n) = (0, 0);
+    // The last `*` seen and how much of the name it has swallowed, to
+    // backtrack to when the rest of the pattern stops matching
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == b'?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Whether `text` has any `*` or `?` for `glob_match`
+pub fn has_wildcards(text: &str) -> bool {
+    text.contains(|c| c == '*' || c == '?')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn formats_zero() {
+        assert_eq!(SimpleString::from_usize(0).as_str(), "0");
+    }
+
+    #[test_case]
+    fn formats_large_values_without_leading_garbage() {
+        assert_eq!(SimpleString::from_usize(40960).as_str(), "40960");
+        assert_eq!(SimpleString::from_usize(usize::MAX).as_str(), "18446744073709551615");
+
+        let mut buf = SimpleString::new();
+        buf.push_str("t=");
+        u64_to_str(1_000_000, &mut buf);
+        assert_eq!(buf.as_str(), "t=1000000");
+    }
+
+    #[test_case]
+    fn star_matches_any_run() {
+        assert!(glob_match("*", "notes.txt"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*.tmp", "a.tmp"));
+        assert!(glob_match("*.tmp", ".tmp"));
+        assert!(!glob_match("*.tmp", "a.tmp.bak"));
+    }
+
+    #[test_case]
+    fn star_backtracks_between_literals() {
+        assert!(glob_match("a*b", "ab"));
+        assert!(glob_match("a*b", "axxbxb"));
+        assert!(!glob_match("a*b", "axxbx"));
+        assert!(glob_match("a**b", "acb"));
+    }
+
+    #[test_case]
+    fn question_mark_matches_exactly_one() {
+        assert!(glob_match("?", "x"));
+        assert!(!glob_match("?", ""));
+        assert!(!glob_match("?", "xy"));
+        assert!(glob_match("log?.txt", "log1.txt"));
+        assert!(!glob_match("log?.txt", "log.txt"));
+    }
+
+    #[test_case]
+    fn literals_must_match_whole_name() {
+        assert!(glob_match("readme", "readme"));
+        assert!(!glob_match("readme", "readme.txt"));
+        assert!(!glob_match("", "x"));
+        assert!(has_wildcards("*.txt"));
+        assert!(!has_wildcards("notes.txt"));
+    }
+}
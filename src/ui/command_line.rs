@@ -6,10 +6,14 @@ use crate::ui::text_editor::TEXT_EDITOR;
 use crate::vga_buffer::{WRITER, ColorCode, Color};
 use crate::keyboard::{KeyEvent, KeyCode, KeyState};
 use crate::ui::text_editor::TextEditor;
-use crate::ui::file_manager::FileManager;
-use crate::ui::retro_commands::handle_retro_command;
+use crate::ui::file_manager::{FileManager, FILE_MANAGER};
+use crate::ui::retro_commands::{aliases_of, resolve_alias, RetroTheme};
+use crate::ui::pager::Pager;
+use crate::ui::{Component, Rect};
+use pc_keyboard::DecodedKey;
 use alloc::vec::Vec;
-use crate::string_ext::{StringExt, StringSliceExt};
+use alloc::string::String;
+use crate::string_ext::{glob_match, has_wildcards, StringExt, StringSliceExt};
 use core::fmt::Write;
 use crate::error_handler::{ERROR_HANDLER, ErrorSeverity};
 
@@ -17,6 +21,26 @@ use crate::error_handler::{ERROR_HANDLER, ErrorSeverity};
 const MAX_COMMAND_HISTORY: usize = 10;
 const MAX_HISTORY: usize = 50;
 const MAX_TAB_COMPLETIONS: usize = 10;
+const HISTORY_FILE: &str = ".history";
+const MAX_ALIASES: usize = 32;
+const ALIAS_FILE: &str = ".aliases";
+const MAX_ENV_VARS: usize = 32;
+const MAX_ENV_VALUE: usize = 256;
+// Batch files may run other batch files, but not endlessly
+const MAX_BATCH_DEPTH: usize = 4;
+const AUTOEXEC_FILE: &str = "autoexec.bat";
+// Bytes per `hexdump` row
+const HEXDUMP_WIDTH: usize = 16;
+// Width of the label column in the `sysinfo` report
+const SYSINFO_LABEL_WIDTH: usize = 13;
+
+// Exit status of the last command, exposed to scripts as `$?`
+const EXIT_SUCCESS: u8 = 0;
+const EXIT_FAILURE: u8 = 1;
+const EXIT_NOT_FOUND: u8 = 127;
+
+// Returned by handlers that fail without an error message (e.g. `false`)
+const SILENT_FAILURE: &str = "";
 
 // Command structure for more organized command handling
 struct Command {
@@ -26,6 +50,52 @@ struct Command {
     handler: fn(&mut CommandLine, &[&str]) -> Result<(), &'static str>,
 }
 
+/// What a key fed to a `LineReader` did
+#[derive(Debug, Clone, PartialEq)]
+pub enum LineInput {
+    /// Still reading
+    Pending,
+    /// Enter finished the line
+    Done(SimpleString),
+    /// Esc gave up on it
+    Cancelled,
+}
+
+/// A line of input collected one key at a time. Code that can't block
+/// feeds it from its own key handling, `CommandLine::read_line` feeds it
+/// from the keyboard queue.
+pub struct LineReader {
+    text: SimpleString,
+    max_len: usize,
+}
+
+impl LineReader {
+    pub fn new(max_len: usize) -> Self {
+        Self { text: SimpleString::new(), max_len }
+    }
+    
+    /// The text typed so far
+    pub fn text(&self) -> &str {
+        self.text.as_str()
+    }
+    
+    pub fn feed(&mut self, key: char) -> LineInput {
+        match key {
+            '\n' => return LineInput::Done(core::mem::take(&mut self.text)),
+            '\u{001B}' => {
+                self.text.clear();
+                return LineInput::Cancelled;
+            },
+            '\u{0008}' => {
+                self.text.pop();
+            },
+            c if !c.is_control() && self.text.len() + c.len_utf8() <= self.max_len => self.text.push(c),
+            _ => {},
+        }
+        LineInput::Pending
+    }
+}
+
 /// Command line interface for ScreammOS
 pub struct CommandLine {
     input: SimpleString,
@@ -36,64 +106,133 @@ pub struct CommandLine {
     tab_completions: [SimpleString; MAX_TAB_COMPLETIONS],
     tab_completion_index: usize,
     tab_completion_count: usize,
+    tab_token_start: usize,
+    tab_token_end: usize,
     cursor_position: usize,
     text_editor: Option<TextEditor>,
     file_manager: Option<FileManager>,
+    last_exit_status: u8,
+    // Captured output of the current pipeline stage, None prints to screen
+    output: Option<String>,
+    // Output of the previous pipeline stage
+    stdin: Option<String>,
+    // User-defined aliases, name and replacement for the first word
+    aliases: Vec<(SimpleString, SimpleString)>,
+    // Environment variables for `$NAME` expansion
+    env: Vec<(SimpleString, SimpleString)>,
+    // Number of batch files currently running
+    batch_depth: usize,
 }
 
 impl CommandLine {
-    pub fn new() -> Self {
+    pub const fn new() -> Self {
+        const EMPTY: SimpleString = SimpleString::new();
         CommandLine {
             input: SimpleString::new(),
-            history: [SimpleString::new(); MAX_HISTORY],
+            history: [EMPTY; MAX_HISTORY],
             history_index: 0,
             history_count: 0,
             last_tab_command: SimpleString::new(),
-            tab_completions: [SimpleString::new(); MAX_TAB_COMPLETIONS],
+            tab_completions: [EMPTY; MAX_TAB_COMPLETIONS],
             tab_completion_index: 0,
             tab_completion_count: 0,
+            tab_token_start: 0,
+            tab_token_end: 0,
             cursor_position: 0,
             text_editor: None,
             file_manager: None,
+            last_exit_status: EXIT_SUCCESS,
+            output: None,
+            stdin: None,
+            aliases: Vec::new(),
+            env: Vec::new(),
+            batch_depth: 0,
         }
     }
     
+    /// Feed a character typed at the prompt into the command line
     pub fn handle_input(&mut self, ch: char) {
+        // Any key other than Tab ends the current completion cycle
+        if ch != '\t' {
+            self.reset_tab_completion();
+        }
+        
         match ch {
-            // ... existing code ...
+            '\n' => {
+                self.process_command();
+                self.print("> ");
+            },
             
-            // Up arrow for command history
-            '\u{1b}' => {
-                // Check if this is an escape sequence for arrow keys
-                if self.input.len() >= 2 && 
-                   self.input.as_str().chars().nth(self.input.len() - 2) == Some('[') {
-                    match self.input.as_str().chars().last() {
-                        Some('A') => { // Up arrow
-                            self.navigate_history_up();
-                        },
-                        Some('B') => { // Down arrow
-                            self.navigate_history_down();
-                        },
-                        _ => {}
-                    }
+            '\u{0008}' => {
+                if self.input.pop().is_some() {
+                    self.cursor_position = self.input.len();
+                    self.print("\u{0008} \u{0008}");
                 }
             },
             
             // Tab for command completion
             '\t' => {
-                self.command_completion();
+                let shown = self.input.len();
+                if self.command_completion() {
+                    // The candidate list pushed the prompt off its line
+                    let input = self.input.clone();
+                    self.print("> ");
+                    self.print(input.as_str());
+                } else {
+                    self.redraw_input(shown);
+                }
             },
             
-            _ => {
-                // ... existing code ...
-            }
+            c if !c.is_control() => {
+                self.input.push(c);
+                self.cursor_position = self.input.len();
+                let mut encoded = [0; 4];
+                self.print(c.encode_utf8(&mut encoded));
+            },
+            
+            _ => {}
+        }
+    }
+    
+    /// Up arrow: replace the input with the previous history entry
+    pub fn recall_previous(&mut self) {
+        self.reset_tab_completion();
+        let shown = self.input.len();
+        self.navigate_history_up();
+        self.redraw_input(shown);
+    }
+    
+    /// Down arrow: replace the input with the next history entry
+    pub fn recall_next(&mut self) {
+        self.reset_tab_completion();
+        let shown = self.input.len();
+        self.navigate_history_down();
+        self.redraw_input(shown);
+    }
+    
+    // Erase the `shown` characters after the prompt and print the input
+    fn redraw_input(&mut self, shown: usize) {
+        for _ in 0..shown {
+            self.print("\u{0008} \u{0008}");
         }
+        let input = self.input.clone();
+        self.print(input.as_str());
+    }
+    
+    /// Number of entries still held in the history ring
+    fn history_len(&self) -> usize {
+        self.history_count.min(MAX_HISTORY)
+    }
+    
+    /// The entry `back` steps before the newest one (1 = newest)
+    fn history_entry(&self, back: usize) -> &SimpleString {
+        &self.history[(self.history_count - back) % MAX_HISTORY]
     }
     
     pub fn navigate_history_up(&mut self) {
-        if self.history_index < self.history_count {
+        if self.history_index < self.history_len() {
             self.history_index += 1;
-            self.input = self.history[self.history_count - self.history_index];
+            self.input = self.history_entry(self.history_index).clone();
             self.cursor_position = self.input.len();
         }
     }
@@ -104,91 +243,447 @@ impl CommandLine {
             if self.history_index == 0 {
                 self.input = SimpleString::new();
             } else {
-                self.input = self.history[self.history_count - self.history_index];
+                self.input = self.history_entry(self.history_index).clone();
             }
             self.cursor_position = self.input.len();
         }
     }
     
-    pub fn command_completion(&mut self) {
-        if self.input.len() == 0 {
-            return;
+    /// Write the history, oldest first, to the history file in the root
+    pub fn save_history(&self) -> bool {
+        let mut text = alloc::string::String::new();
+        for back in (1..=self.history_len()).rev() {
+            text.push_str(self.history_entry(back).as_str());
+            text.push('\n');
         }
-
-        if self.last_tab_command.len() == 0 {
-            self.last_tab_command = self.input;
-            self.clear_tab_completions();
+        
+        FILESYSTEM.lock().in_root(|fs| fs.write_file(HISTORY_FILE, &text))
+    }
+    
+    /// Replace the history with the contents of the history file
+    pub fn load_history(&mut self) {
+        let text = match FILESYSTEM.lock().in_root(|fs| fs.read_file(HISTORY_FILE).map(String::from)) {
+            Ok(content) => content,
+            Err(_) => return,
+        };
+        
+        self.history_count = 0;
+        self.history_index = 0;
+        for line in text.lines() {
+            self.add_to_history(line);
+        }
+    }
+    
+    /// Define or replace an alias. Names are matched ignoring case.
+    fn set_alias(&mut self, name: &str, value: &str) -> Result<(), &'static str> {
+        if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == '"' || c == '|' || c == '>') {
+            return Err("Invalid alias name");
+        }
+        if value.trim().is_empty() {
+            return Err("Alias needs a command");
+        }
+        
+        let mut replacement = SimpleString::new();
+        replacement.push_str(value.trim());
+        match self.find_alias(name) {
+            Some(index) => self.aliases[index].1 = replacement,
+            None if self.aliases.len() >= MAX_ALIASES => return Err("Alias table is full"),
+            None => {
+                let mut key = SimpleString::new();
+                key.push_str(name);
+                self.aliases.push((key, replacement));
+            }
+        }
+        Ok(())
+    }
+    
+    fn find_alias(&self, name: &str) -> Option<usize> {
+        self.aliases.iter().position(|(alias, _)| alias.as_str().eq_ignore_ascii_case(name))
+    }
+    
+    /// Replace the first word of `line` for as long as it names an alias.
+    /// Each alias is used at most once, so `alias ls "ls -a"` and loops
+    /// like a -> b -> a stop instead of recursing forever.
+    fn expand_aliases(&self, line: &str) -> String {
+        let mut line = String::from(line);
+        let mut used = Vec::new();
+        loop {
+            let trimmed = line.trim_start();
+            let end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+            let index = match self.find_alias(&trimmed[..end]) {
+                Some(index) if !used.contains(&index) => index,
+                _ => return line,
+            };
+            used.push(index);
             
-            // Hitta alla kommandon som börjar med input
-            let input_str = self.input.as_str();
-            let fs = FILESYSTEM.lock();
-            for i in 0..fs.get_file_count() {
-                let filename = fs.get_filename(i);
-                if filename.starts_with(input_str) {
-                    self.add_tab_completion(filename);
-                }
+            let mut expanded = String::from(self.aliases[index].1.as_str());
+            expanded.push_str(&trimmed[end..]);
+            line = expanded;
+        }
+    }
+    
+    /// Set an environment variable. `$?` is read-only.
+    fn set_var(&mut self, name: &str, value: &str) -> Result<(), &'static str> {
+        if !is_var_name(name) {
+            return Err("Invalid variable name");
+        }
+        if value.len() > MAX_ENV_VALUE {
+            return Err("Value too long");
+        }
+        
+        let mut stored = SimpleString::new();
+        stored.push_str(value);
+        match self.env.iter().position(|(var, _)| var.as_str() == name) {
+            Some(index) => self.env[index].1 = stored,
+            None if self.env.len() >= MAX_ENV_VARS => return Err("Environment is full"),
+            None => {
+                let mut key = SimpleString::new();
+                key.push_str(name);
+                self.env.push((key, stored));
             }
         }
+        Ok(())
+    }
+    
+    fn get_var(&self, name: &str) -> Option<&str> {
+        self.env.iter()
+            .find(|(var, _)| var.as_str() == name)
+            .map(|(_, value)| value.as_str())
+    }
+    
+    /// Replace `$NAME` with the variable's value and `$?` with the exit
+    /// status of the previous command. Unknown variables expand to nothing.
+    fn expand_variables(&self, text: &str) -> String {
+        let mut expanded = String::new();
+        let mut rest = text;
+        while let Some(dollar) = rest.find('$') {
+            expanded.push_str(&rest[..dollar]);
+            let after = &rest[dollar + 1..];
+            
+            if after.starts_with('?') {
+                expanded.push_str(&format!("{}", self.last_exit_status));
+                rest = &after[1..];
+                continue;
+            }
+            
+            let len = after.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(after.len());
+            if len == 0 {
+                // A lone `$` is kept as is
+                expanded.push('$');
+            } else {
+                expanded.push_str(self.get_var(&after[..len]).unwrap_or(""));
+            }
+            rest = &after[len..];
+        }
+        expanded.push_str(rest);
+        expanded
+    }
+    
+    /// Write the aliases to the alias file in the root, one `name value`
+    /// per line
+    pub fn save_aliases(&self) -> Result<(), &'static str> {
+        let mut text = String::new();
+        for (name, value) in self.aliases.iter() {
+            text.push_str(name.as_str());
+            text.push(' ');
+            text.push_str(value.as_str());
+            text.push('\n');
+        }
+        if FILESYSTEM.lock().in_root(|fs| fs.write_file(ALIAS_FILE, &text)) {
+            Ok(())
+        } else {
+            Err("Failed to write file")
+        }
+    }
+    
+    /// Replace the aliases with the contents of the alias file
+    pub fn load_aliases(&mut self) {
+        let text = match FILESYSTEM.lock().in_root(|fs| fs.read_file(ALIAS_FILE).map(String::from)) {
+            Ok(content) => content,
+            Err(_) => return,
+        };
+        
+        self.aliases.clear();
+        for line in text.lines() {
+            if let Some((name, value)) = line.split_once(' ') {
+                self.set_alias(name, value).ok();
+            }
+        }
+    }
+    
+    /// Complete the token under the cursor: command names for the first
+    /// token, filenames in the current directory for the rest. Returns true
+    /// when a list of candidates was printed below the prompt.
+    pub fn command_completion(&mut self) -> bool {
+        if self.last_tab_command.len() == 0 {
+            return self.start_tab_completion();
+        }
 
-        if self.tab_completion_count > 0 {
-            self.input = self.tab_completions[self.tab_completion_index];
-            self.cursor_position = self.input.len();
+        // Repeated Tab cycles through the candidates
+        if self.tab_completion_count > 1 {
+            let candidate = self.tab_completions[self.tab_completion_index].clone();
+            self.replace_tab_token(candidate.as_str());
             self.tab_completion_index = (self.tab_completion_index + 1) % self.tab_completion_count;
         }
+        false
+    }
+    
+    fn start_tab_completion(&mut self) -> bool {
+        self.clear_tab_completions();
+        self.last_tab_command = self.input.clone();
+        
+        let mut typed = SimpleString::new();
+        typed.push_str(&self.input.as_str()[..self.cursor_position.min(self.input.len())]);
+        let line = typed.as_str();
+        self.tab_token_end = line.len();
+        self.tab_token_start = line.rfind(' ').map_or(0, |i| i + 1);
+        let token = &line[self.tab_token_start..];
+        
+        if line[..self.tab_token_start].trim().is_empty() {
+            for command in COMMANDS.iter() {
+                if command.name.starts_with(token) {
+                    self.add_tab_completion(command.name);
+                }
+            }
+        } else {
+            let fs = FILESYSTEM.lock();
+            for (_, name, _) in fs.list_directory() {
+                if name.starts_with(token) {
+                    self.add_tab_completion(name);
+                }
+            }
+        }
+        
+        match self.tab_completion_count {
+            0 => false,
+            1 => {
+                let only = self.tab_completions[0].clone();
+                self.replace_tab_token(only.as_str());
+                false
+            }
+            _ => {
+                // Show the candidates and fill in what they all share
+                let mut listing = SimpleString::new();
+                for i in 0..self.tab_completion_count {
+                    listing.push_str(self.tab_completions[i].as_str());
+                    listing.push_str("  ");
+                }
+                self.println("");
+                self.println(listing.as_str());
+                
+                let first = self.tab_completions[0].clone();
+                let mut shared = first.len();
+                for i in 1..self.tab_completion_count {
+                    shared = shared.min(common_prefix_len(first.as_str(), self.tab_completions[i].as_str()));
+                }
+                self.replace_tab_token(&first.as_str()[..shared]);
+                true
+            }
+        }
+    }
+    
+    /// Replace the token being completed, keeping anything after the cursor
+    fn replace_tab_token(&mut self, completion: &str) {
+        let original = self.last_tab_command.as_str();
+        
+        let mut line = SimpleString::new();
+        line.push_str(&original[..self.tab_token_start]);
+        line.push_str(completion);
+        let new_cursor = line.len();
+        line.push_str(&original[self.tab_token_end..]);
+        
+        self.input = line;
+        self.cursor_position = new_cursor;
+    }
+    
+    fn reset_tab_completion(&mut self) {
+        self.last_tab_command.clear();
+        self.clear_tab_completions();
     }
     
     pub fn process_command(&mut self) {
         self.println("");
         
-        let command = self.input.as_str().trim();
+        let line = self.input.clone();
+        let command = line.as_str().trim();
         
         // Add command to history
         self.add_to_history(command);
+        self.history_index = 0;
+        if !self.save_history() {
+            log_error!("Could not save command history");
+        }
+        
+        if command.is_empty() {
+            self.input.clear();
+            return;
+        }
+        
+        // Log the command
+        log_info!("Command executed: {}", command);
+        
+        self.run_line(command);
+        self.input.clear();
+    }
+    
+    /// Expand aliases in `command`, then run it as a pipeline with an
+    /// optional `> file` redirect. Output the caller is capturing, like a
+    /// batch file inside a pipe, keeps collecting the last stage's output.
+    fn run_line(&mut self, command: &str) {
+        let expanded = self.expand_aliases(command);
+        let command = expanded.as_str();
+        
+        // Split off `> file` and break the rest into pipeline stages
+        let (pipeline, redirect) = match command.split_once('>') {
+            Some((pipeline, target)) => (pipeline, Some(target.trim())),
+            None => (command, None),
+        };
+        
+        if redirect == Some("") || redirect.map_or(false, |target| target.contains('|')) {
+            self.last_exit_status = EXIT_FAILURE;
+            self.eprintln("Error: Usage: <command> [| <command>...] > <file>");
+            return;
+        }
+        
+        let stages: Vec<&str> = pipeline.split('|').map(str::trim).collect();
+        let last = stages.len() - 1;
+        let mut sink = self.output.take();
+        let outer_stdin = self.stdin.take();
+        
+        for (i, stage) in stages.iter().enumerate() {
+            self.stdin = self.output.take();
+            if i < last || redirect.is_some() {
+                self.output = Some(String::new());
+            } else {
+                self.output = sink.take();
+            }
+            
+            self.execute(stage);
+        }
+        
+        self.stdin = outer_stdin;
+        if let Some(target) = redirect {
+            let text = self.output.take().unwrap_or_default();
+            self.output = sink;
+            if let Err(msg) = write_or_create(target, &text) {
+                self.last_exit_status = EXIT_FAILURE;
+                self.eprintln(&format!("Error: {}", msg));
+            }
+        }
+    }
+    
+    /// Run `autoexec.bat` if there is one
+    pub fn run_autoexec(&mut self) {
+        if FILESYSTEM.lock().find_file(AUTOEXEC_FILE).is_none() {
+            return;
+        }
+        
+        log_info!("Running {}", AUTOEXEC_FILE);
+        if let Err(msg) = self.run_batch(AUTOEXEC_FILE) {
+            if msg != SILENT_FAILURE {
+                self.eprintln(&format!("Error: {}", msg));
+            }
+        }
+    }
+    
+    /// Run each line of a batch file as a command. Blank lines and `REM`
+    /// comments are skipped, a leading `@` hides that line and
+    /// `echo off`/`echo on` hide or show the ones after it. The file stops
+    /// at the first failing command unless the line starts with `-`.
+    fn run_batch(&mut self, filename: &str) -> Result<(), &'static str> {
+        if self.batch_depth >= MAX_BATCH_DEPTH {
+            return Err("Batch files nested too deeply");
+        }
+        
+        // Copy the script so the filesystem isn't locked while it runs
+        let script = String::from(FILESYSTEM.lock().read_file(filename)?);
+        
+        self.batch_depth += 1;
+        let result = self.run_script(&script);
+        self.batch_depth -= 1;
+        result
+    }
+    
+    fn run_script(&mut self, script: &str) -> Result<(), &'static str> {
+        let mut echo = true;
+        
+        for (number, line) in script.lines().enumerate() {
+            let mut line = line.trim();
+            let quiet = line.starts_with('@');
+            if quiet {
+                line = line[1..].trim_start();
+            }
+            if line.is_empty() || is_batch_comment(line) {
+                continue;
+            }
+            
+            if let Some(state) = echo_switch(line) {
+                echo = state;
+                continue;
+            }
+            
+            let ignore_failure = line.starts_with('-');
+            if ignore_failure {
+                line = line[1..].trim_start();
+            }
+            
+            if echo && !quiet {
+                self.println(&format!("> {}", line));
+            }
+            
+            self.run_line(line);
+            if self.last_exit_status != EXIT_SUCCESS && !ignore_failure {
+                self.eprintln(&format!("Batch file stopped at line {}", number + 1));
+                return Err(SILENT_FAILURE);
+            }
+        }
         
-        // Parse the command and arguments
+        Ok(())
+    }
+    
+    /// Run a single command, leaving its status in `last_exit_status`
+    fn execute(&mut self, command: &str) {
         let parts: Vec<&str> = command.split_whitespace().collect();
         
         if parts.is_empty() {
-            self.input.clear();
+            self.last_exit_status = EXIT_FAILURE;
+            self.eprintln("Error: Empty command in pipeline");
             return;
         }
         
         let cmd = parts[0];
         let args = &parts[1..];
         
-        // Log the command
-        log_info!("Command executed: {}", command);
-        
-        // Find and execute the command
-        let mut found = false;
-        
-        for command in COMMANDS.iter() {
-            if command.name == cmd {
-                found = true;
-                
-                match (command.handler)(self, args) {
-                    Ok(_) => {},
-                    Err(msg) => {
-                        self.println(&format!("Error: {}", msg));
+        match find_command(cmd) {
+            Some(command) => match (command.handler)(self, args) {
+                Ok(_) => {
+                    self.last_exit_status = EXIT_SUCCESS;
+                },
+                Err(msg) => {
+                    self.last_exit_status = EXIT_FAILURE;
+                    if msg != SILENT_FAILURE {
+                        self.eprintln(&format!("Error: {}", msg));
                         report_warning(ErrorDomain::UserInterface, &format!("Command error: {}", msg)).ok();
                     }
                 }
-                
-                break;
+            },
+            None => {
+                self.last_exit_status = EXIT_NOT_FOUND;
+                self.eprintln(&format!("Unknown command: {}", cmd));
+                self.eprintln("Type 'help' for a list of commands.");
+                report_warning(ErrorDomain::UserInterface, &format!("Unknown command: {}", cmd)).ok();
             }
         }
-        
-        if !found {
-            self.println(&format!("Unknown command: {}", cmd));
-            self.println("Type 'help' for a list of commands.");
-            report_warning(ErrorDomain::UserInterface, &format!("Unknown command: {}", cmd)).ok();
-        }
-        
-        self.input.clear();
     }
     
     // Command handlers
     fn cmd_help(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        let (text, result) = self.capture_output(|cl| cl.write_help(args));
+        self.page_output(&text);
+        result
+    }
+    
+    fn write_help(&mut self, args: &[&str]) -> Result<(), &'static str> {
         if args.is_empty() {
             self.println("Available commands:");
             self.println("------------------");
@@ -198,42 +693,80 @@ impl CommandLine {
             }
             
             self.println("");
+            self.println("DOS names such as DIR, TYPE, COPY and DEL work too, in any case.");
             self.println("Type 'help <command>' for more information about a specific command.");
         } else {
             let cmd_name = args[0];
-            let mut found = false;
-            
-            for cmd in COMMANDS.iter() {
-                if cmd.name == cmd_name {
-                    found = true;
-                    self.println(&format!("Command: {}", cmd.name));
-                    self.println(&format!("Description: {}", cmd.description));
-                    self.println(&format!("Usage: {}", cmd.usage));
-                    break;
+            let cmd = match find_command(cmd_name) {
+                Some(cmd) => cmd,
+                None => {
+                    self.println(&format!("No help available for '{}'", cmd_name));
+                    return Err("Unknown command");
                 }
-            }
+            };
             
-            if !found {
-                self.println(&format!("No help available for '{}'", cmd_name));
-                return Err("Unknown command");
+            self.println(&format!("Command: {}", cmd.name));
+            self.println(&format!("Description: {}", cmd.description));
+            self.println(&format!("Usage: {}", cmd.usage));
+            let aliases: Vec<&str> = aliases_of(cmd.name).collect();
+            if !aliases.is_empty() {
+                self.println(&format!("DOS names: {}", aliases.join(", ")));
             }
         }
         
         Ok(())
     }
     
+    fn cmd_boxdemo(&mut self, _args: &[&str]) -> Result<(), &'static str> {
+        use crate::ui::{draw_box, draw_text_centered, BorderStyle, Rect};
+        use crate::vga_buffer::{BUFFER_WIDTH, BUFFER_HEIGHT};
+        
+        const BOX_WIDTH: usize = 14;
+        const BOX_HEIGHT: usize = 6;
+        const TOP: usize = 8;
+        
+        let saved = WRITER.lock().save_region(0, 0, BUFFER_WIDTH, BUFFER_HEIGHT);
+        
+        let count = BorderStyle::ALL.len();
+        let left = (BUFFER_WIDTH - count * (BOX_WIDTH + 1) + 1) / 2;
+        for (i, &style) in BorderStyle::ALL.iter().enumerate() {
+            let rect = Rect { x: left + i * (BOX_WIDTH + 1), y: TOP, width: BOX_WIDTH, height: BOX_HEIGHT };
+            draw_box(rect, style, None);
+            draw_text_centered(&mut *WRITER.lock(), rect, BOX_HEIGHT / 2, style.name(), Color::White, Color::Black);
+        }
+        let footer = Rect { x: 0, y: TOP + BOX_HEIGHT + 1, width: BUFFER_WIDTH, height: 1 };
+        draw_text_centered(&mut *WRITER.lock(), footer, 0, "Press any key", Color::LightGray, Color::Black);
+        
+        while crate::keyboard::read_key().is_none() {
+            x86_64::instructions::hlt();
+        }
+        
+        WRITER.lock().restore_region(&saved);
+        Ok(())
+    }
+    
     fn cmd_clear(&mut self, _args: &[&str]) -> Result<(), &'static str> {
         self.clear();
+        vga_buffer::clear_screen();
         Ok(())
     }
     
     fn cmd_ls(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        let (text, result) = self.capture_output(|cl| cl.write_listing(args));
+        self.page_output(&text);
+        result
+    }
+    
+    fn write_listing(&mut self, args: &[&str]) -> Result<(), &'static str> {
         let mut fs = FILESYSTEM.lock();
         
         let show_all = args.contains(&"-a");
         let show_long = args.contains(&"-l");
+        // Anything that isn't a flag limits the listing to matching names
+        let patterns: Vec<&str> = args.iter().copied().filter(|arg| !arg.starts_with('-')).collect();
         
         let mut count = 0;
+        let mut matched = 0;
         
         // Print header for long format
         if show_long {
@@ -250,11 +783,16 @@ impl CommandLine {
             if !show_all && filename.starts_with(".") {
                 continue;
             }
+            if !patterns.is_empty() && !patterns.iter().any(|pattern| glob_match(pattern, filename)) {
+                continue;
+            }
+            matched += 1;
             
             if show_long {
                 let file_size = fs.get_file_size(i);
+                let modified = fs.get_file_modified(i);
                 let type_indicator = if file_type == FileType::Directory { "DIR" } else { "FILE" };
-                self.println(&format!("{:4}  {:4}           {}", type_indicator, file_size, filename));
+                self.println(&format!("{:4}  {:4}  {:8}  {}", type_indicator, file_size, modified, filename));
             } else {
                 let type_indicator = if file_type == FileType::Directory { "/" } else { "" };
                 self.print(&format!("{}{} ", filename, type_indicator));
@@ -270,8 +808,13 @@ impl CommandLine {
             self.println("");
         }
         
-        // Print total count
-        self.println(&format!("Total: {} files", fs.get_file_count()));
+        if !patterns.is_empty() && matched == 0 {
+            return Err("No matches");
+        }
+        
+        // Print total count, only the matches when filtering
+        let total = if patterns.is_empty() { fs.get_file_count() } else { matched };
+        self.println(&format!("Total: {} files", total));
         
         Ok(())
     }
@@ -281,33 +824,88 @@ impl CommandLine {
             return Err("No filename specified");
         }
         
-        let filename = args[0];
+        // Copy the content so the filesystem isn't locked while paging
+        let mut content = String::new();
+        {
+            let fs = FILESYSTEM.lock();
+            for filename in expand_globs(&fs, args, true)? {
+                content.push_str(fs.read_file(&filename)?);
+            }
+        }
         
-        let fs = FILESYSTEM.lock();
+        // Wrap prose at word boundaries instead of mid-word
+        WRITER.lock().set_word_wrap(true);
+        self.page_output(&content);
+        WRITER.lock().set_word_wrap(false);
+        Ok(())
+    }
+    
+    fn cmd_lsmem(&mut self, _args: &[&str]) -> Result<(), &'static str> {
+        let memory_map = crate::memory::memory_map().ok_or("No memory map available")?;
         
-        if let Some(content) = fs.read_file(filename) {
-            self.println(content);
-            Ok(())
-        } else {
-            Err("File not found")
+        let mut text = String::new();
+        text.push_str("Type         Start          End                Size\n");
+        for region in memory_map.iter() {
+            let start = region.range.start_addr();
+            let end = region.range.end_addr();
+            text.push_str(&format!("{:<12} {:#012x}   {:#012x}   {:>8} KiB\n",
+                crate::memory::region_type_name(region.region_type), start, end, (end - start) / 1024));
         }
+        let (usable, reserved) = crate::memory::region_totals(memory_map);
+        text.push_str(&format!("\nUsable:   {:>8} KiB\n", usable / 1024));
+        text.push_str(&format!("Reserved: {:>8} KiB\n", reserved / 1024));
+        
+        self.page_output(&text);
+        Ok(())
     }
     
-    fn cmd_edit(&mut self, args: &[&str]) -> Result<(), &'static str> {
-        if args.is_empty() {
-            return Err("No filename specified");
+    fn cmd_hexdump(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        const USAGE: &str = "Usage: hexdump [-n count] <file>";
+        let (limit, filename) = match args {
+            [filename] => (usize::MAX, *filename),
+            ["-n", count, filename] => (count.parse().map_err(|_| "Invalid byte count")?, *filename),
+            _ => return Err(USAGE),
+        };
+        
+        let mut text = String::new();
+        {
+            let fs = FILESYSTEM.lock();
+            let bytes = fs.read_file_bytes(filename).ok_or("File not found")?;
+            let bytes = &bytes[..bytes.len().min(limit)];
+            for (row, chunk) in bytes.chunks(HEXDUMP_WIDTH).enumerate() {
+                text.push_str(&hexdump_row(row * HEXDUMP_WIDTH, chunk));
+                text.push('\n');
+            }
         }
         
-        let filename = args[0];
+        self.page_output(&text);
+        Ok(())
+    }
+    
+    fn cmd_edit(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        let read_only = args.contains(&"-r");
+        let filename = args.iter().find(|arg| **arg != "-r");
         
         let mut editor = TEXT_EDITOR.try_lock().ok_or("Text editor is busy")?;
         
-        editor.open_file(filename);
-        editor.set_active(true);
+        // Without a name the buffer asks for one on the first save
+        match filename {
+            Some(filename) => { editor.open_file(filename); },
+            None => editor.new_file(),
+        }
+        editor.set_read_only(read_only);
+        editor.show();
         
         Ok(())
     }
     
+    fn cmd_run(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        match args {
+            [filename] => self.run_batch(filename),
+            _ => Err("Usage: run <file.bat>"),
+        }
+    }
+    
     fn cmd_mkdir(&mut self, args: &[&str]) -> Result<(), &'static str> {
         if args.is_empty() {
             return Err("No directory name specified");
@@ -332,75 +930,260 @@ impl CommandLine {
         
         let mut fs = FILESYSTEM.lock();
         
-        // Check if the file already exists
-        if fs.find_file(filename).is_some() {
-            // File exists, just update timestamp (not implemented in SimpleFs yet)
+        // An existing file only gets a new modification time, its
+        // content is left alone
+        if fs.touch(filename) {
             return Ok(());
         }
         
         // Create empty file
-        match fs.create_file(filename, "") {
-            Ok(_) => Ok(()),
-            Err(e) => Err(e),
-        }
+        fs.create_file(filename, "").map(|_| ())
     }
     
     fn cmd_rm(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        let (recursive, args) = match args {
+            ["-r", rest @ ..] => (true, rest),
+            _ => (false, args),
+        };
         if args.is_empty() {
             return Err("No filename specified");
         }
         
-        let filename = args[0];
+        // Without -r a pattern only removes files, empty directories stay
+        let filenames = expand_globs(&FILESYSTEM.lock(), args, !recursive)?;
         
-        let mut fs = FILESYSTEM.lock();
+        // A pattern can reach further than intended, ask before removing several
+        let confirmed = filenames.len() > 1 && args.iter().any(|arg| has_wildcards(arg));
+        if confirmed && !self.confirm(&format!("Remove {} files? (y/n) ", filenames.len())) {
+            return Err(SILENT_FAILURE);
+        }
         
-        match fs.delete_file(filename) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(e),
+        for filename in &filenames {
+            if recursive {
+                // One yes covers the directories the pattern matched
+                self.remove_tree(filename, !confirmed)?;
+                continue;
+            }
+            let mut fs = FILESYSTEM.lock();
+            if fs.find_file(filename).is_none() {
+                return Err("File not found");
+            }
+            if !fs.delete_file(filename) {
+                return Err("Directory is not empty, use rm -r");
+            }
         }
+        Ok(())
     }
     
-    fn cmd_echo(&mut self, args: &[&str]) -> Result<(), &'static str> {
-        let text = args.join(" ");
-        self.println(&text);
-        Ok(())
+    fn cmd_rmdir(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        let name = *args.first().ok_or("Usage: rmdir <dirname>")?;
+        if !FILESYSTEM.lock().is_directory(name) {
+            return Err("Not a directory");
+        }
+        self.remove_tree(name, true)
     }
     
-    fn cmd_write(&mut self, args: &[&str]) -> Result<(), &'static str> {
-        if args.len() < 2 {
-            return Err("Usage: write <filename> <text>");
+    // Remove an entry and everything below it. With `ask`, confirm first
+    // if that is more than the entry itself.
+    fn remove_tree(&mut self, name: &str, ask: bool) -> Result<(), &'static str> {
+        let below = FILESYSTEM.lock().count_below(name).ok_or("File not found")?;
+        if ask && below > 0 && !self.confirm(&format!("{} has {} entries in it, remove them all? (y/n) ", name, below)) {
+            return Err(SILENT_FAILURE);
         }
-        
-        let filename = args[0];
-        let content = args[1..].join(" ");
+        FILESYSTEM.lock().delete_recursive(name)
+    }
+    
+    /// Ask a yes/no question, anything but `y` counts as no
+    fn confirm(&mut self, question: &str) -> bool {
+        self.read_line(question)
+            .map_or(false, |answer| answer.as_str().trim().eq_ignore_ascii_case("y"))
+    }
+    
+    fn cmd_sync(&mut self, _args: &[&str]) -> Result<(), &'static str> {
+        crate::logger::LOGGER.lock().flush();
+        FILESYSTEM.lock().flush_to_disk()?;
+        self.println("File system written to disk");
+        Ok(())
+    }
+    
+    fn cmd_fsck(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        let repair = match args.first().copied() {
+            None => false,
+            Some("-r") => true,
+            Some(_) => return Err("Usage: fsck [-r]"),
+        };
         
         let mut fs = FILESYSTEM.lock();
+        match fs.check_integrity() {
+            Ok(()) => self.println("File system is clean"),
+            Err(problem) if repair => {
+                let cleared = fs.repair();
+                self.println(&format!("Found: {}", problem));
+                self.println(&format!("Repaired, {} entries cleared", cleared));
+            }
+            Err(problem) => {
+                self.println(&format!("Found: {}", problem));
+                self.println("Run 'fsck -r' to repair");
+                return Err(SILENT_FAILURE);
+            }
+        }
+        Ok(())
+    }
+    
+    fn cmd_cp(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        if args.len() < 2 {
+            return Err("Usage: cp <source> <destination>");
+        }
         
-        // If file doesn't exist, create it
-        if fs.find_file(filename).is_none() {
-            match fs.create_file(filename, &content) {
-                Ok(_) => Ok(()),
-                Err(e) => Err(e),
+        let destination = args[1];
+        let content = {
+            let mut fs = FILESYSTEM.lock();
+            let sources = expand_globs(&fs, &args[..1], true)?;
+            if fs.is_directory(destination) {
+                for source in &sources {
+                    fs.copy_into(source, destination)?;
+                }
+                return Ok(());
+            }
+            
+            // Anything else is the name of a single new file
+            if sources.len() > 1 {
+                return Err("Source pattern matches more than one file");
             }
+            if sources[0] == destination {
+                return Err("Source and destination are the same file");
+            }
+            String::from(fs.read_file(&sources[0])?)
+        };
+        write_or_create(destination, &content)
+    }
+    
+    fn cmd_mv(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        if args.len() < 2 {
+            return Err("Usage: mv <source> <destination>");
+        }
+        
+        let mut fs = FILESYSTEM.lock();
+        if fs.is_directory(args[1]) {
+            fs.move_into(args[0], args[1])
         } else {
-            // File exists, update its content
-            if fs.write_file(filename, &content) {
-                Ok(())
-            } else {
-                Err("Failed to write file")
+            fs.rename(args[0], args[1])
+        }
+    }
+    
+    fn cmd_echo(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        let text = self.expand_variables(&args.join(" "));
+        self.println(&text);
+        Ok(())
+    }
+    
+    fn cmd_grep(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        let pattern = *args.first().ok_or("Usage: grep <pattern> [file]")?;
+        
+        // Read the named file, or the previous pipeline stage
+        let text = match args.get(1) {
+            Some(filename) => {
+                let fs = FILESYSTEM.lock();
+                String::from(fs.read_file(filename)?)
+            }
+            None => self.stdin.take().ok_or("No input: pipe text into grep or name a file")?,
+        };
+        
+        let mut matched = false;
+        for line in text.lines().filter(|line| line.contains(pattern)) {
+            self.println(line);
+            matched = true;
+        }
+        
+        // Like grep, no match is a failure without a message
+        if matched { Ok(()) } else { Err(SILENT_FAILURE) }
+    }
+    
+    fn cmd_wc(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        let (mut lines, mut words, mut bytes) = (false, false, false);
+        let mut filename = None;
+        for arg in args {
+            match arg.strip_prefix('-') {
+                Some(flags) if !flags.is_empty() => {
+                    for flag in flags.chars() {
+                        match flag {
+                            'l' => lines = true,
+                            'w' => words = true,
+                            'c' => bytes = true,
+                            _ => return Err("Usage: wc [-l] [-w] [-c] [file]"),
+                        }
+                    }
+                }
+                _ if filename.is_none() => filename = Some(*arg),
+                _ => return Err("Usage: wc [-l] [-w] [-c] [file]"),
+            }
+        }
+        // No flags prints all three counts
+        if !(lines || words || bytes) {
+            lines = true;
+            words = true;
+            bytes = true;
+        }
+        
+        // Count the named file, or the previous pipeline stage
+        let text = match filename {
+            Some(filename) => String::from(FILESYSTEM.lock().read_file(filename)?),
+            None => self.stdin.take().ok_or("No input: pipe text into wc or name a file")?,
+        };
+        
+        let counts = count_text(&text);
+        let mut report = String::new();
+        for (selected, count) in [(lines, counts.0), (words, counts.1), (bytes, counts.2)] {
+            if selected {
+                report.push_str(&format!("{:>7} ", count));
             }
         }
+        report.push_str(filename.unwrap_or(""));
+        self.println(report.trim_end());
+        Ok(())
+    }
+    
+    fn cmd_write(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        if args.len() < 2 {
+            return Err("Usage: write <filename> <text>");
+        }
+        
+        let filename = args[0];
+        let content = args[1..].join(" ");
+        
+        write_or_create(filename, &content)
+    }
+    
+    fn cmd_append(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        if args.len() < 2 {
+            return Err("Usage: append <filename> <text>");
+        }
+        
+        let filename = args[0];
+        let mut content = args[1..].join(" ");
+        content.push('\n');
+        
+        let mut fs = FILESYSTEM.lock();
+        
+        // Like >>, create the file if it doesn't exist yet
+        if fs.find_file(filename).is_none() {
+            fs.create_file(filename, &content).map(|_| ())
+        } else if fs.append_file(filename, &content) {
+            Ok(())
+        } else {
+            Err("Failed to append: file is full")
+        }
     }
     
     fn cmd_theme(&mut self, args: &[&str]) -> Result<(), &'static str> {
         if args.is_empty() {
             // Show current theme
             let current_theme = vga_buffer::get_current_theme();
-            self.println(&format!("Current theme: {}", vga_buffer::get_theme_name(current_theme)));
+            self.println(&format!("Current theme: {}", vga_buffer::get_theme_name(current_theme as u8)));
             
             // List available themes
             self.println("Available themes:");
-            for i in 0..4 {
+            for i in 0..vga_buffer::THEME_COUNT {
                 self.println(&format!("  {} - {}", i, vga_buffer::get_theme_name(i)));
             }
             
@@ -409,14 +1192,13 @@ impl CommandLine {
         
         // Try to parse theme number
         if let Ok(theme_num) = args[0].parse::<u8>() {
-            if theme_num > 3 {
-                return Err("Invalid theme number");
-            }
+            let theme = vga_buffer::Theme::from_index(theme_num).ok_or("Invalid theme number")?;
             
             let theme_name = vga_buffer::get_theme_name(theme_num);
             self.println(&format!("Setting theme to: {}", theme_name));
             
-            vga_buffer::set_theme(theme_num);
+            vga_buffer::set_theme(theme);
+            crate::ui::window_manager::WINDOW_MANAGER.lock().follow_theme(theme);
             
             Ok(())
         } else {
@@ -424,7 +1206,50 @@ impl CommandLine {
         }
     }
     
-    fn cmd_test(&mut self, _args: &[&str]) -> Result<(), &'static str> {
+    fn cmd_history(&mut self, _args: &[&str]) -> Result<(), &'static str> {
+        // Number entries by their position since boot, like bash
+        for back in (1..=self.history_len()).rev() {
+            let number = self.history_count - back + 1;
+            let line = format!("{:4}  {}", number, self.history_entry(back).as_str());
+            self.println(&line);
+        }
+        Ok(())
+    }
+    
+    fn cmd_alias(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        match args {
+            [] => {
+                let lines: Vec<SimpleString> = self.aliases.iter()
+                    .map(|(name, value)| format!("{} = {}", name.as_str(), value.as_str()))
+                    .collect();
+                for line in lines.iter() {
+                    self.println(line.as_str());
+                }
+                Ok(())
+            },
+            [name] => {
+                let index = self.find_alias(name).ok_or("No such alias")?;
+                let line = format!("{} = {}", self.aliases[index].0.as_str(), self.aliases[index].1.as_str());
+                self.println(&line);
+                Ok(())
+            },
+            [name, value @ ..] => {
+                // The command was split on spaces, put it back together
+                let value = value.join(" ");
+                self.set_alias(name, value.trim_matches('"'))?;
+                self.save_aliases()
+            },
+        }
+    }
+    
+    fn cmd_unalias(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        let name = args.first().ok_or("Usage: unalias <name>")?;
+        let index = self.find_alias(name).ok_or("No such alias")?;
+        self.aliases.remove(index);
+        self.save_aliases()
+    }
+    
+    fn cmd_selftest(&mut self, _args: &[&str]) -> Result<(), &'static str> {
         self.println("Running system self-tests...");
         
         crate::run_self_tests();
@@ -432,6 +1257,36 @@ impl CommandLine {
         Ok(())
     }
     
+    fn cmd_true(&mut self, _args: &[&str]) -> Result<(), &'static str> {
+        Ok(())
+    }
+    
+    fn cmd_false(&mut self, _args: &[&str]) -> Result<(), &'static str> {
+        Err(SILENT_FAILURE)
+    }
+    
+    fn cmd_test(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        let fs = FILESYSTEM.lock();
+        
+        if evaluate_test(&fs, args)? {
+            Ok(())
+        } else {
+            Err(SILENT_FAILURE)
+        }
+    }
+    
+    fn cmd_bracket(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        match args.split_last() {
+            Some((&"]", expr)) => self.cmd_test(expr),
+            _ => Err("Missing closing ']'"),
+        }
+    }
+    
+    /// Exit status of the previous command (0 = success)
+    pub fn last_exit_status(&self) -> u8 {
+        self.last_exit_status
+    }
+    
     fn cmd_errors(&mut self, _args: &[&str]) -> Result<(), &'static str> {
         let handler = ERROR_HANDLER.lock();
         
@@ -443,36 +1298,457 @@ impl CommandLine {
         self.println(&format!("Fatal:     {}", handler.get_error_count(ErrorSeverity::Fatal)));
         self.println(&format!("Total:     {}", handler.get_total_error_count()));
         
-        // Dump error history
-        handler.dump_error_history();
+        // Error history, with each code spelled out
+        self.println("");
+        self.println("Error history:");
+        self.println("--------------");
+        let mut count = 0;
+        for error in handler.history() {
+            self.println(&format!("{}", error));
+            count += 1;
+        }
+        if count == 0 {
+            self.println("No errors recorded.");
+        }
         
         Ok(())
     }
     
-    fn cmd_restart(&mut self, _args: &[&str]) -> Result<(), &'static str> {
-        self.println("Restarting system...");
-        self.println("This is a simulated restart (not implemented)");
+    fn cmd_safemode(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        match args.first().copied() {
+            None => {
+                let active = ERROR_HANDLER.lock().is_in_safe_mode();
+                self.println(if active { "Safe mode is on" } else { "Safe mode is off" });
+            }
+            Some("off") => {
+                let mut handler = ERROR_HANDLER.lock();
+                if !handler.is_in_safe_mode() {
+                    return Err("Safe mode is not active");
+                }
+                handler.exit_safe_mode();
+                drop(handler);
+                self.println("Safe mode off, error counters reset");
+            }
+            Some(_) => return Err("Usage: safemode [off]"),
+        }
+        Ok(())
+    }
+    
+    fn cmd_set(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        use crate::ui::screensaver::SCREENSAVER;
+        use x86_64::instructions::interrupts::without_interrupts;
+        
+        match args {
+            ["screensaver"] => {
+                let seconds = without_interrupts(|| SCREENSAVER.lock().timeout_seconds());
+                self.println(&format!("screensaver = {} seconds", seconds));
+                Ok(())
+            },
+            ["screensaver", seconds] => {
+                let seconds = seconds.parse::<u64>().map_err(|_| "Invalid number of seconds")?;
+                without_interrupts(|| SCREENSAVER.lock().set_timeout_seconds(seconds));
+                if seconds == 0 {
+                    self.println("Screensaver disabled");
+                } else {
+                    self.println(&format!("Screensaver starts after {} seconds", seconds));
+                }
+                Ok(())
+            },
+            [] => self.cmd_env(args),
+            [name] => {
+                let value = self.get_var(name).ok_or("No such variable")?;
+                let line = format!("{}={}", name, value);
+                self.println(&line);
+                Ok(())
+            },
+            [name, value @ ..] => self.set_var(name, &value.join(" ")),
+        }
+    }
+    
+    fn cmd_read(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        let (name, prompt) = match args {
+            [name] => (*name, String::new()),
+            [name, prompt @ ..] => (*name, prompt.join(" ") + " "),
+            [] => return Err("Usage: read <name> [prompt]"),
+        };
+        if !is_var_name(name) {
+            return Err("Invalid variable name");
+        }
+        
+        // Esc leaves the variable alone and fails, so batch files can stop
+        let line = self.read_line(&prompt).ok_or(SILENT_FAILURE)?;
+        self.set_var(name, line.as_str())
+    }
+    
+    fn cmd_unset(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        let name = args.first().ok_or("Usage: unset <name>")?;
+        let index = self.env.iter().position(|(var, _)| var.as_str() == *name).ok_or("No such variable")?;
+        self.env.remove(index);
+        Ok(())
+    }
+    
+    fn cmd_env(&mut self, _args: &[&str]) -> Result<(), &'static str> {
+        let lines: Vec<SimpleString> = self.env.iter()
+            .map(|(name, value)| format!("{}={}", name.as_str(), value.as_str()))
+            .collect();
+        for line in lines.iter() {
+            self.println(line.as_str());
+        }
+        Ok(())
+    }
+    
+    fn cmd_cd(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        let path = args.first().copied().unwrap_or("/");
+        FILESYSTEM.lock().change_directory(path)
+    }
+    
+    fn cmd_pwd(&mut self, _args: &[&str]) -> Result<(), &'static str> {
+        let path = FILESYSTEM.lock().get_current_directory();
+        self.println(&path);
+        Ok(())
+    }
+    
+    fn cmd_df(&mut self, _args: &[&str]) -> Result<(), &'static str> {
+        use crate::simple_fs::{MAX_FILES, MAX_FILE_SIZE};
         
-        // In a real OS, we'd do something like this:
-        // unsafe { x86_64::instructions::port::outb(0x64, 0xFE); }
+        let (entries, bytes) = FILESYSTEM.lock().usage();
+        let max_bytes = MAX_FILES * MAX_FILE_SIZE;
         
-        self.clear();
-        self.println("System restarted");
+        self.println("           Used       Max  Use%");
+        self.println(&format!("Entries {:>7} {:>9} {:>4}%",
+            SimpleString::from_usize(entries).as_str(),
+            SimpleString::from_usize(MAX_FILES).as_str(),
+            entries * 100 / MAX_FILES));
+        self.println(&format!("Bytes   {:>7} {:>9} {:>4}%",
+            SimpleString::from_usize(bytes).as_str(),
+            SimpleString::from_usize(max_bytes).as_str(),
+            bytes * 100 / max_bytes));
+        Ok(())
+    }
+    
+    fn cmd_du(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        let path = args.first().copied().unwrap_or(".");
+        let entries = FILESYSTEM.lock().disk_usage(path)?;
+        
+        let mut total = 0;
+        for (name, file_type, size) in entries.iter() {
+            let suffix = if *file_type == FileType::Directory { "/" } else { "" };
+            self.println(&format!("{:>8}  {}{}", SimpleString::from_usize(*size).as_str(), name, suffix));
+            total += size;
+        }
+        self.println(&format!("{:>8}  total", SimpleString::from_usize(total).as_str()));
+        Ok(())
+    }
+    
+    fn cmd_log(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        use crate::logger::{LogLevel, LOGGER};
+        
+        fn on_off(arg: Option<&&str>) -> Result<bool, &'static str> {
+            match arg.copied() {
+                Some("on") => Ok(true),
+                Some("off") => Ok(false),
+                _ => Err("Expected 'on' or 'off'"),
+            }
+        }
+        
+        match args.first().copied() {
+            None => {
+                let (level, console, file) = {
+                    let logger = LOGGER.lock();
+                    (logger.level(), logger.logs_to_console(), logger.logs_to_file())
+                };
+                let state = |enabled: bool| if enabled { "on" } else { "off" };
+                self.println(&format!("Level:   {}", level.name()));
+                self.println(&format!("Console: {}", state(console)));
+                self.println(&format!("File:    {}", state(file)));
+            }
+            Some("level") => {
+                let name = args.get(1).ok_or("Usage: log level <debug|info|warn|error|crit>")?;
+                let level = LogLevel::from_name(name).ok_or("Unknown level, use debug, info, warn, error or crit")?;
+                LOGGER.lock().set_level(level);
+            }
+            Some("console") => LOGGER.lock().set_log_to_console(on_off(args.get(1))?),
+            Some("file") => {
+                let enabled = on_off(args.get(1))?;
+                if enabled && crate::error_handler::is_in_safe_mode() {
+                    return Err("File logging is disabled in safe mode");
+                }
+                LOGGER.lock().set_log_to_file(enabled);
+            }
+            Some("dump") => LOGGER.lock().dump_log(),
+            Some(_) => return Err("Unknown subcommand, use level, console, file or dump"),
+        }
+        Ok(())
+    }
+    
+    fn cmd_keymap(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        use crate::keymap::Layout;
+        
+        match args.first() {
+            Some(name) => {
+                let layout = Layout::from_name(name).ok_or("Unknown layout, type 'keymap' for a list")?;
+                crate::keyboard::set_layout(layout);
+                self.println(&format!("Keyboard layout: {}", layout.description()));
+            }
+            None => {
+                let current = crate::keyboard::current_layout();
+                self.println(&format!("Current layout: {}", current.description()));
+                self.println("Available layouts:");
+                for layout in Layout::ALL.iter() {
+                    self.println(&format!("  {:8} {}", layout.name(), layout.description()));
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    fn cmd_files(&mut self, _args: &[&str]) -> Result<(), &'static str> {
+        let mut file_manager = FILE_MANAGER.try_lock().ok_or("File manager is busy")?;
+        file_manager.show();
+        Ok(())
+    }
+    
+    fn cmd_sysinfo(&mut self, _args: &[&str]) -> Result<(), &'static str> {
+        let vendor = crate::cpu::vendor();
+        let brand = crate::cpu::brand();
+        let stats = crate::memory::get_memory_stats();
+        let seconds = crate::interrupts::uptime_seconds();
+        let theme = vga_buffer::get_theme_name(vga_buffer::get_current_theme() as u8);
+        let files = FILESYSTEM.lock().get_file_count();
+        let (warnings, errors) = {
+            let handler = ERROR_HANDLER.lock();
+            (handler.get_error_count(ErrorSeverity::Warning), handler.get_total_error_count())
+        };
+        
+        self.println("ScreammOS System Information");
+        self.println("---------------------------");
+        self.println(&sysinfo_row("Version", format_args!("0.2.0")));
+        self.println(&sysinfo_row("CPU vendor", format_args!("{}", vendor)));
+        if let Some(brand) = brand {
+            self.println(&sysinfo_row("CPU", format_args!("{}", brand)));
+        }
+        self.println(&sysinfo_row("Heap total", format_args!("{:>6} KiB", stats.total / 1024)));
+        self.println(&sysinfo_row("Heap used", format_args!("{:>6} KiB", stats.used / 1024)));
+        self.println(&sysinfo_row("Heap free", format_args!("{:>6} KiB", stats.free / 1024)));
+        self.println(&sysinfo_row("Uptime", format_args!("{:02}:{:02}:{:02}", seconds / 3600, (seconds / 60) % 60, seconds % 60)));
+        self.println(&sysinfo_row("Color theme", format_args!("{}", theme)));
+        self.println(&sysinfo_row("Files", format_args!("{}", files)));
+        self.println(&sysinfo_row("Errors", format_args!("{} ({} warnings)", errors, warnings)));
+        Ok(())
+    }
+    
+    fn cmd_beep(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        const DEFAULT_HZ: u32 = 880;
+        const DEFAULT_MS: u32 = 200;
+        // Keep the shell from being locked up by a typo
+        const MAX_MS: u32 = 5000;
         
-        // Re-initialize components
-        crate::init();
+        let frequency = match args.first() {
+            Some(arg) => arg.parse::<u32>().map_err(|_| "Invalid frequency")?,
+            None => DEFAULT_HZ,
+        };
+        let duration = match args.get(1) {
+            Some(arg) => arg.parse::<u32>().map_err(|_| "Invalid duration")?,
+            None => DEFAULT_MS,
+        };
+        if !(20..=20_000).contains(&frequency) {
+            return Err("Frequency must be between 20 and 20000 Hz");
+        }
+        
+        crate::sound::beep(frequency, duration.min(MAX_MS));
+        Ok(())
+    }
+    
+    fn cmd_mem(&mut self, _args: &[&str]) -> Result<(), &'static str> {
+        let stats = crate::memory::get_memory_stats();
+        self.println("Heap     KiB");
+        self.println(&format!("Used  {:>6}", stats.used / 1024));
+        self.println(&format!("Free  {:>6}", stats.free / 1024));
+        self.println(&format!("Total {:>6}", stats.total / 1024));
+        Ok(())
+    }
+    
+    fn cmd_color(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        let name = match args.first() {
+            Some(name) => name,
+            None => {
+                self.println("Available color schemes:");
+                for theme in RetroTheme::ALL.iter() {
+                    self.println(&format!("  {:10} - {}", theme.name(), theme.description()));
+                }
+                return Ok(());
+            }
+        };
         
+        let theme = RetroTheme::from_name(name).ok_or("Unknown color scheme, run 'color' for a list")?;
+        theme.apply();
+        self.println(&format!("Color scheme changed to {}", theme.name()));
+        Ok(())
+    }
+    
+    fn cmd_date(&mut self, _args: &[&str]) -> Result<(), &'static str> {
+        self.println(&format!("Current date: {}", crate::rtc::read_datetime().date()));
+        Ok(())
+    }
+    
+    fn cmd_time(&mut self, _args: &[&str]) -> Result<(), &'static str> {
+        let seconds = crate::interrupts::uptime_seconds();
+        self.println(&format!("Current time: {}", crate::rtc::read_datetime().time()));
+        self.println(&format!("Uptime: {:02}:{:02}:{:02}", seconds / 3600, (seconds / 60) % 60, seconds % 60));
         Ok(())
     }
+    
+    fn cmd_about(&mut self, _args: &[&str]) -> Result<(), &'static str> {
+        self.println("ScreammOS");
+        self.println("--------");
+        self.println("An experimental DOS-inspired operating system");
+        self.println("developed in Rust for x86_64 architecture.");
+        Ok(())
+    }
+    
+    fn cmd_shutdown(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        self.save_before_power_off(args)?;
+        self.println("Shutting down ScreammOS...");
+        crate::shutdown();
+    }
+    
+    fn cmd_reboot(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        self.save_before_power_off(args)?;
+        self.println("Restarting system...");
+        crate::reboot();
+    }
+    
+    // Write history, the log and the file system out before the power
+    // goes. If the disk write fails the system stays up, unless `-f` says
+    // to go ahead anyway.
+    fn save_before_power_off(&mut self, args: &[&str]) -> Result<(), &'static str> {
+        let force = match args {
+            [] => false,
+            ["-f"] => true,
+            _ => return Err("Usage: shutdown|reboot [-f]"),
+        };
+        
+        if !self.save_history() {
+            log_error!("Could not save command history");
+        }
+        crate::logger::LOGGER.lock().flush();
+        
+        let mut fs = FILESYSTEM.lock();
+        if !fs.has_data_disk() {
+            return Ok(());
+        }
+        match fs.flush_to_disk() {
+            Ok(()) => Ok(()),
+            Err(_) if force => Ok(()),
+            Err(e) => {
+                drop(fs);
+                self.eprintln(&format!("Could not write the file system to disk: {}", e));
+                self.eprintln("Use -f to turn off anyway and lose unsaved changes");
+                Err(SILENT_FAILURE)
+            }
+        }
+    }
 
     fn println(&mut self, text: &str) {
-        use crate::println;
-        println!("{}", text);
+        match self.output.as_mut() {
+            Some(buffer) => {
+                buffer.push_str(text);
+                buffer.push('\n');
+            }
+            None => {
+                use crate::println;
+                println!("{}", text);
+            }
+        }
     }
 
     fn print(&mut self, text: &str) {
-        use crate::print;
-        print!("{}", text);
+        match self.output.as_mut() {
+            Some(buffer) => buffer.push_str(text),
+            None => {
+                use crate::print;
+                print!("{}", text);
+            }
+        }
+    }
+
+    /// Run `f` with its output captured and return the captured text
+    fn capture_output<F>(&mut self, f: F) -> (String, Result<(), &'static str>)
+    where
+        F: FnOnce(&mut Self) -> Result<(), &'static str>,
+    {
+        let outer = self.output.replace(String::new());
+        let result = f(self);
+        let text = core::mem::replace(&mut self.output, outer).unwrap_or_default();
+        (text, result)
+    }
+
+    /// Print `text`, stopping after every screenful with a `-- More --`
+    /// prompt. Output going into a pipe or redirect is never paged.
+    fn page_output(&mut self, text: &str) {
+        if self.output.is_some() || text.lines().count() < vga_buffer::BUFFER_HEIGHT {
+            for line in text.lines() {
+                self.println(line);
+            }
+            return;
+        }
+
+        let mut pager = Pager::new(text);
+        pager.render();
+        loop {
+            match crate::keyboard::read_key() {
+                Some(DecodedKey::Unicode(c)) => {
+                    if !pager.handle_key(c) {
+                        break;
+                    }
+                }
+                Some(DecodedKey::RawKey(_)) => {}
+                None => x86_64::instructions::hlt(),
+            }
+        }
+        vga_buffer::clear_screen();
+    }
+
+    /// Show `prompt` and read a line from the keyboard, echoing it as it is
+    /// typed. Blocks until Enter, or Esc which returns None. The prompt
+    /// goes to the screen even inside a pipeline.
+    pub fn read_line(&mut self, prompt: &str) -> Option<SimpleString> {
+        use crate::{print, println};
+        print!("{}", prompt);
+        
+        let mut reader = LineReader::new(MAX_ENV_VALUE);
+        loop {
+            let c = match crate::keyboard::read_key() {
+                Some(DecodedKey::Unicode(c)) => c,
+                Some(DecodedKey::RawKey(pc_keyboard::KeyCode::Return)) => '\n',
+                Some(DecodedKey::RawKey(pc_keyboard::KeyCode::Escape)) => '\u{001B}',
+                Some(DecodedKey::RawKey(_)) => continue,
+                None => {
+                    x86_64::instructions::hlt();
+                    continue;
+                }
+            };
+            
+            let shown = reader.text().len();
+            match reader.feed(c) {
+                LineInput::Pending if reader.text().len() < shown => print!("\u{0008} \u{0008}"),
+                LineInput::Pending => print!("{}", &reader.text()[shown..]),
+                LineInput::Done(text) => {
+                    println!();
+                    return Some(text);
+                },
+                LineInput::Cancelled => {
+                    println!();
+                    return None;
+                },
+            }
+        }
+    }
+    
+    // Errors always go to the screen, even inside a pipeline
+    fn eprintln(&mut self, text: &str) {
+        use crate::println;
+        println!("{}", text);
     }
 
     fn clear(&mut self) {
@@ -499,7 +1775,7 @@ impl CommandLine {
         // Don't add empty commands or duplicates of the last command
         if command.trim().is_empty() || 
            (self.history_count > 0 && 
-            command == self.history[self.history_count - 1].as_str()) {
+            command == self.history_entry(1).as_str()) {
             return;
         }
         
@@ -511,14 +1787,181 @@ impl CommandLine {
     }
 }
 
-// Define all available commands
-static COMMANDS: &[Command] = &[
-    Command {
-        name: "help",
-        description: "Display help information",
-        usage: "help [command]",
-        handler: CommandLine::cmd_help,
-    },
+impl Component for CommandLine {
+    /// Print a fresh prompt with the current input
+    fn render(&self) {
+        crate::print!("\n> {}", self.input.as_str());
+    }
+    
+    fn handle_input(&mut self, key: u8) -> bool {
+        CommandLine::handle_input(self, key as char);
+        true
+    }
+    
+    // The prompt scrolls with the rest of the screen
+    fn get_bounds(&self) -> Rect {
+        Rect { x: 0, y: 0, width: vga_buffer::BUFFER_WIDTH, height: vga_buffer::BUFFER_HEIGHT }
+    }
+    
+    /// Typed characters, Enter, and Up/Down for the history. Ctrl keys are
+    /// left for the global shortcuts.
+    fn handle_key_event(&mut self, key: DecodedKey) -> bool {
+        match key {
+            DecodedKey::Unicode(_) if crate::keyboard::modifiers().ctrl => return false,
+            DecodedKey::Unicode(c) => CommandLine::handle_input(self, c),
+            DecodedKey::RawKey(pc_keyboard::KeyCode::Return) => CommandLine::handle_input(self, '\n'),
+            DecodedKey::RawKey(pc_keyboard::KeyCode::ArrowUp) => self.recall_previous(),
+            DecodedKey::RawKey(pc_keyboard::KeyCode::ArrowDown) => self.recall_next(),
+            DecodedKey::RawKey(_) => return false,
+        }
+        true
+    }
+}
+
+/// Lines, whitespace-separated words and bytes in `text`. A last line
+/// without a newline still counts as a line.
+fn count_text(text: &str) -> (usize, usize, usize) {
+    (text.lines().count(), text.split_whitespace().count(), text.len())
+}
+
+/// One line of the `sysinfo` report, with the values lined up in a column
+fn sysinfo_row(label: &str, value: core::fmt::Arguments) -> String {
+    let mut row = String::new();
+    row.push_str(label);
+    row.push(':');
+    while row.len() < SYSINFO_LABEL_WIDTH {
+        row.push(' ');
+    }
+    row.push_str(&format!("{}", value));
+    row
+}
+
+/// One `hexdump` row: offset, up to 16 bytes in hex and the same bytes
+/// as ASCII, with `.` for anything that isn't printable
+fn hexdump_row(offset: usize, bytes: &[u8]) -> String {
+    let mut row = String::new();
+    row.push_str(&format!("{:08x} ", offset));
+    for i in 0..HEXDUMP_WIDTH {
+        // Extra gap between the two halves of the row
+        if i == HEXDUMP_WIDTH / 2 {
+            row.push(' ');
+        }
+        match bytes.get(i) {
+            Some(byte) => row.push_str(&format!(" {:02x}", byte)),
+            None => row.push_str("   "),
+        }
+    }
+    
+    row.push_str("  |");
+    for &byte in bytes {
+        row.push(if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' });
+    }
+    row.push('|');
+    row
+}
+
+/// `REM` comment lines in a batch file
+fn is_batch_comment(line: &str) -> bool {
+    let word = line.split_whitespace().next().unwrap_or("");
+    word.eq_ignore_ascii_case("rem")
+}
+
+/// `echo off` or `echo on`, as a new echo state
+fn echo_switch(line: &str) -> Option<bool> {
+    let mut words = line.split_whitespace();
+    match (words.next(), words.next(), words.next()) {
+        (Some(echo), Some(state), None) if echo.eq_ignore_ascii_case("echo") => {
+            if state.eq_ignore_ascii_case("off") {
+                Some(false)
+            } else if state.eq_ignore_ascii_case("on") {
+                Some(true)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Variable names are letters, digits and `_`, not starting with a digit
+fn is_var_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+/// Look a command up by name or DOS alias, ignoring case
+fn find_command(name: &str) -> Option<&'static Command> {
+    let name = resolve_alias(name).unwrap_or(name);
+    COMMANDS.iter().find(|command| command.name.eq_ignore_ascii_case(name))
+}
+
+/// Replace arguments with `*` or `?` by the matching names in the current
+/// directory, sorted. Names starting with `.` only match a pattern that
+/// does too, and with `files_only` directories never do. A pattern
+/// without matches is an error.
+fn expand_globs(fs: &SimpleFileSystem, args: &[&str], files_only: bool) -> Result<Vec<String>, &'static str> {
+    let mut names = Vec::new();
+    for &arg in args {
+        if !has_wildcards(arg) {
+            names.push(String::from(arg));
+            continue;
+        }
+        
+        let mut matches: Vec<String> = fs.list_directory()
+            .filter(|(file_type, _, _)| !files_only || *file_type != FileType::Directory)
+            .map(|(_, name, _)| name)
+            .filter(|name| !name.starts_with('.') || arg.starts_with('.'))
+            .filter(|name| glob_match(arg, name))
+            .map(String::from)
+            .collect();
+        if matches.is_empty() {
+            return Err("No matches");
+        }
+        matches.sort();
+        names.append(&mut matches);
+    }
+    Ok(names)
+}
+
+/// Replace the contents of `name`, creating the file if needed
+fn write_or_create(name: &str, content: &str) -> Result<(), &'static str> {
+    let mut fs = FILESYSTEM.lock();
+    if fs.find_file(name).is_none() {
+        fs.create_file(name, content).map(|_| ())
+    } else if fs.write_file(name, content) {
+        Ok(())
+    } else {
+        Err("Failed to write file")
+    }
+}
+
+/// Length in bytes of the prefix shared by `a` and `b`
+fn common_prefix_len(a: &str, b: &str) -> usize {
+    a.char_indices()
+        .zip(b.chars())
+        .find(|((_, x), y)| x != y)
+        .map_or(a.len().min(b.len()), |((i, _), _)| i)
+}
+
+// Define all available commands
+static COMMANDS: &[Command] = &[
+    Command {
+        name: "help",
+        description: "Display help information",
+        usage: "help [command]",
+        handler: CommandLine::cmd_help,
+    },
+    Command {
+        name: "boxdemo",
+        description: "Draw one box in each border style",
+        usage: "boxdemo",
+        handler: CommandLine::cmd_boxdemo,
+    },
     Command {
         name: "clear",
         description: "Clear the screen",
@@ -528,21 +1971,63 @@ static COMMANDS: &[Command] = &[
     Command {
         name: "ls",
         description: "List files and directories",
-        usage: "ls [-a] [-l]",
+        usage: "ls [-a] [-l] [pattern...]",
         handler: CommandLine::cmd_ls,
     },
     Command {
         name: "cat",
         description: "Display file contents",
-        usage: "cat <filename>",
+        usage: "cat <filename...>",
         handler: CommandLine::cmd_cat,
     },
     Command {
         name: "edit",
         description: "Edit a file",
-        usage: "edit <filename>",
+        usage: "edit [-r] [filename]",
         handler: CommandLine::cmd_edit,
     },
+    Command {
+        name: "run",
+        description: "Run the commands in a batch file",
+        usage: "run <file.bat>",
+        handler: CommandLine::cmd_run,
+    },
+    Command {
+        name: "files",
+        description: "Open the file manager",
+        usage: "files",
+        handler: CommandLine::cmd_files,
+    },
+    Command {
+        name: "cd",
+        description: "Change the current directory",
+        usage: "cd [directory|..|/]",
+        handler: CommandLine::cmd_cd,
+    },
+    Command {
+        name: "pwd",
+        description: "Print the current directory",
+        usage: "pwd",
+        handler: CommandLine::cmd_pwd,
+    },
+    Command {
+        name: "df",
+        description: "Show filesystem capacity",
+        usage: "df",
+        handler: CommandLine::cmd_df,
+    },
+    Command {
+        name: "du",
+        description: "Show disk usage of a directory",
+        usage: "du [directory]",
+        handler: CommandLine::cmd_du,
+    },
+    Command {
+        name: "rmdir",
+        description: "Remove a directory and what is in it",
+        usage: "rmdir <dirname>",
+        handler: CommandLine::cmd_rmdir,
+    },
     Command {
         name: "mkdir",
         description: "Create a directory",
@@ -557,22 +2042,76 @@ static COMMANDS: &[Command] = &[
     },
     Command {
         name: "rm",
-        description: "Remove a file",
-        usage: "rm <filename>",
+        description: "Remove files",
+        usage: "rm [-r] <filename...>",
         handler: CommandLine::cmd_rm,
     },
+    Command {
+        name: "cp",
+        description: "Copy a file, or files into a directory",
+        usage: "cp <source> <destination>",
+        handler: CommandLine::cmd_cp,
+    },
+    Command {
+        name: "mv",
+        description: "Rename an entry or move it into a directory",
+        usage: "mv <source> <destination>",
+        handler: CommandLine::cmd_mv,
+    },
+    Command {
+        name: "ren",
+        description: "Rename an entry or move it into a directory",
+        usage: "ren <source> <destination>",
+        handler: CommandLine::cmd_mv,
+    },
+    Command {
+        name: "keymap",
+        description: "Show or change the keyboard layout",
+        usage: "keymap [us|se|dvorak]",
+        handler: CommandLine::cmd_keymap,
+    },
+    Command {
+        name: "sync",
+        description: "Write the file system to disk",
+        usage: "sync",
+        handler: CommandLine::cmd_sync,
+    },
     Command {
         name: "echo",
         description: "Display a message",
         usage: "echo <text>",
         handler: CommandLine::cmd_echo,
     },
+    Command {
+        name: "grep",
+        description: "Print lines containing a pattern",
+        usage: "grep <pattern> [file]",
+        handler: CommandLine::cmd_grep,
+    },
+    Command {
+        name: "wc",
+        description: "Count lines, words and bytes",
+        usage: "wc [-l] [-w] [-c] [file]",
+        handler: CommandLine::cmd_wc,
+    },
+    Command {
+        name: "hexdump",
+        description: "Show a file's bytes in hex and ASCII",
+        usage: "hexdump [-n count] <file>",
+        handler: CommandLine::cmd_hexdump,
+    },
     Command {
         name: "write",
         description: "Write text to a file",
         usage: "write <filename> <text>",
         handler: CommandLine::cmd_write,
     },
+    Command {
+        name: "append",
+        description: "Append a line of text to a file",
+        usage: "append <filename> <text>",
+        handler: CommandLine::cmd_append,
+    },
     Command {
         name: "theme",
         description: "Change the display theme",
@@ -580,11 +2119,53 @@ static COMMANDS: &[Command] = &[
         handler: CommandLine::cmd_theme,
     },
     Command {
-        name: "test",
+        name: "history",
+        description: "Show previously entered commands",
+        usage: "history",
+        handler: CommandLine::cmd_history,
+    },
+    Command {
+        name: "alias",
+        description: "List, show or define command aliases",
+        usage: "alias [name [\"command\"]]",
+        handler: CommandLine::cmd_alias,
+    },
+    Command {
+        name: "unalias",
+        description: "Remove a command alias",
+        usage: "unalias <name>",
+        handler: CommandLine::cmd_unalias,
+    },
+    Command {
+        name: "selftest",
         description: "Run system self-tests",
-        usage: "test",
+        usage: "selftest",
+        handler: CommandLine::cmd_selftest,
+    },
+    Command {
+        name: "true",
+        description: "Do nothing, successfully",
+        usage: "true",
+        handler: CommandLine::cmd_true,
+    },
+    Command {
+        name: "false",
+        description: "Do nothing, unsuccessfully",
+        usage: "false",
+        handler: CommandLine::cmd_false,
+    },
+    Command {
+        name: "test",
+        description: "Evaluate a file or string condition",
+        usage: "test -e|-d|-f <file> | test <a> = <b>",
         handler: CommandLine::cmd_test,
     },
+    Command {
+        name: "[",
+        description: "Evaluate a condition (same as test)",
+        usage: "[ -e|-d|-f <file> ] | [ <a> = <b> ]",
+        handler: CommandLine::cmd_bracket,
+    },
     Command {
         name: "errors",
         description: "Display system error information",
@@ -592,9 +2173,651 @@ static COMMANDS: &[Command] = &[
         handler: CommandLine::cmd_errors,
     },
     Command {
-        name: "restart",
-        description: "Restart the system",
-        usage: "restart",
-        handler: CommandLine::cmd_restart,
+        name: "fsck",
+        description: "Check the file system, -r repairs it",
+        usage: "fsck [-r]",
+        handler: CommandLine::cmd_fsck,
     },
-]; 
\ No newline at end of file
+    Command {
+        name: "safemode",
+        description: "Show safe mode or leave it once errors subside",
+        usage: "safemode [off]",
+        handler: CommandLine::cmd_safemode,
+    },
+    Command {
+        name: "set",
+        description: "Set a variable or a system setting",
+        usage: "set [name [value]] | set screensaver <seconds>",
+        handler: CommandLine::cmd_set,
+    },
+    Command {
+        name: "read",
+        description: "Read a line from the keyboard into a variable",
+        usage: "read <name> [prompt]",
+        handler: CommandLine::cmd_read,
+    },
+    Command {
+        name: "unset",
+        description: "Remove an environment variable",
+        usage: "unset <name>",
+        handler: CommandLine::cmd_unset,
+    },
+    Command {
+        name: "env",
+        description: "List environment variables",
+        usage: "env",
+        handler: CommandLine::cmd_env,
+    },
+    Command {
+        name: "reboot",
+        description: "Save to disk and restart the system",
+        usage: "reboot [-f]",
+        handler: CommandLine::cmd_reboot,
+    },
+    Command {
+        name: "log",
+        description: "Configure or show the system log",
+        usage: "log [level <debug|info|warn|error|crit> | console on|off | file on|off | dump]",
+        handler: CommandLine::cmd_log,
+    },
+    Command {
+        name: "beep",
+        description: "Play a tone on the PC speaker",
+        usage: "beep [hz] [ms]",
+        handler: CommandLine::cmd_beep,
+    },
+    Command {
+        name: "mem",
+        description: "Show heap memory usage",
+        usage: "mem",
+        handler: CommandLine::cmd_mem,
+    },
+    Command {
+        name: "lsmem",
+        description: "List the physical memory regions",
+        usage: "lsmem",
+        handler: CommandLine::cmd_lsmem,
+    },
+    Command {
+        name: "sysinfo",
+        description: "Display system information",
+        usage: "sysinfo",
+        handler: CommandLine::cmd_sysinfo,
+    },
+    Command {
+        name: "color",
+        description: "Switch to a retro color scheme",
+        usage: "color [scheme]",
+        handler: CommandLine::cmd_color,
+    },
+    Command {
+        name: "date",
+        description: "Show the current date",
+        usage: "date",
+        handler: CommandLine::cmd_date,
+    },
+    Command {
+        name: "time",
+        description: "Show the current time and uptime",
+        usage: "time",
+        handler: CommandLine::cmd_time,
+    },
+    Command {
+        name: "about",
+        description: "Show information about ScreammOS",
+        usage: "about",
+        handler: CommandLine::cmd_about,
+    },
+    Command {
+        name: "shutdown",
+        description: "Save to disk and turn off the computer",
+        usage: "shutdown [-f]",
+        handler: CommandLine::cmd_shutdown,
+    },
+    Command {
+        name: "exit",
+        description: "Shut down ScreammOS",
+        usage: "exit [-f]",
+        handler: CommandLine::cmd_shutdown,
+    },
+];
+
+/// Evaluate a `test` expression: `-e`, `-d` or `-f` on a file, or `<a> = <b>`
+fn evaluate_test(fs: &SimpleFileSystem, args: &[&str]) -> Result<bool, &'static str> {
+    match args {
+        ["-e", name] => Ok(fs.find_file(name).is_some()),
+        ["-d", name] => Ok(fs.find_file(name)
+            .map_or(false, |index| fs.get_file_type(index) == FileType::Directory)),
+        ["-f", name] => Ok(fs.find_file(name)
+            .map_or(false, |index| fs.get_file_type(index).is_file())),
+        [a, "=", b] => Ok(a == b),
+        [] => Ok(false),
+        _ => Err("Unsupported test expression"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_fs() -> SimpleFileSystem {
+        let mut fs = SimpleFileSystem::new();
+        fs.write_file("notes.txt", "hello");
+        fs.create_directory("docs");
+        fs
+    }
+
+    #[test_case]
+    fn dos_aliases_find_shell_commands() {
+        assert_eq!(find_command("DIR").map(|cmd| cmd.name), Some("ls"));
+        assert_eq!(find_command("type").map(|cmd| cmd.name), Some("cat"));
+        assert_eq!(find_command("Del").map(|cmd| cmd.name), Some("rm"));
+        assert_eq!(find_command("COPY").map(|cmd| cmd.name), Some("cp"));
+        assert_eq!(find_command("LS").map(|cmd| cmd.name), Some("ls"));
+        assert!(find_command("format").is_none());
+    }
+
+    #[test_case]
+    fn test_exists_operator() {
+        let fs = test_fs();
+        assert_eq!(evaluate_test(&fs, &["-e", "notes.txt"]), Ok(true));
+        assert_eq!(evaluate_test(&fs, &["-e", "docs"]), Ok(true));
+        assert_eq!(evaluate_test(&fs, &["-e", "missing.txt"]), Ok(false));
+    }
+
+    #[test_case]
+    fn test_directory_operator() {
+        let fs = test_fs();
+        assert_eq!(evaluate_test(&fs, &["-d", "docs"]), Ok(true));
+        assert_eq!(evaluate_test(&fs, &["-d", "notes.txt"]), Ok(false));
+    }
+
+    #[test_case]
+    fn test_regular_file_operator() {
+        let fs = test_fs();
+        assert_eq!(evaluate_test(&fs, &["-f", "notes.txt"]), Ok(true));
+        assert_eq!(evaluate_test(&fs, &["-f", "docs"]), Ok(false));
+        assert_eq!(evaluate_test(&fs, &["-f", "missing.txt"]), Ok(false));
+    }
+
+    #[test_case]
+    fn test_string_equality() {
+        let fs = test_fs();
+        assert_eq!(evaluate_test(&fs, &["abc", "=", "abc"]), Ok(true));
+        assert_eq!(evaluate_test(&fs, &["abc", "=", "abd"]), Ok(false));
+        assert!(evaluate_test(&fs, &["-x", "abc"]).is_err());
+    }
+
+    #[test_case]
+    fn true_and_false_exit_codes() {
+        let mut cli = CommandLine::new();
+        cli.input.push_str("true");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_SUCCESS);
+
+        cli.input.push_str("false");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_FAILURE);
+    }
+
+    #[test_case]
+    fn history_walks_back_in_order_after_wrapping() {
+        let mut cli = CommandLine::new();
+        for i in 0..MAX_HISTORY + 5 {
+            cli.add_to_history(&format!("cmd{}", i));
+        }
+
+        cli.navigate_history_up();
+        assert_eq!(cli.input.as_str(), format!("cmd{}", MAX_HISTORY + 4).as_str());
+        cli.navigate_history_up();
+        assert_eq!(cli.input.as_str(), format!("cmd{}", MAX_HISTORY + 3).as_str());
+
+        // The oldest reachable entry is the first one not overwritten
+        for _ in 0..MAX_HISTORY {
+            cli.navigate_history_up();
+        }
+        assert_eq!(cli.input.as_str(), "cmd5");
+
+        cli.navigate_history_down();
+        assert_eq!(cli.input.as_str(), "cmd6");
+    }
+
+    #[test_case]
+    fn history_round_trips_through_file() {
+        let mut cli = CommandLine::new();
+        cli.add_to_history("ls");
+        cli.add_to_history("cat readme.txt");
+        assert!(cli.save_history());
+
+        let mut restored = CommandLine::new();
+        restored.load_history();
+        restored.navigate_history_up();
+        assert_eq!(restored.input.as_str(), "cat readme.txt");
+        restored.navigate_history_up();
+        assert_eq!(restored.input.as_str(), "ls");
+    }
+
+    #[test_case]
+    fn aliases_expand_the_first_word() {
+        let mut cli = CommandLine::new();
+        cli.set_alias("ll", "ls -l").unwrap();
+        assert_eq!(cli.expand_aliases("ll -a"), "ls -l -a");
+        assert_eq!(cli.expand_aliases("LL"), "ls -l");
+        assert_eq!(cli.expand_aliases("echo ll"), "echo ll");
+    }
+
+    #[test_case]
+    fn recursive_aliases_stop() {
+        let mut cli = CommandLine::new();
+        cli.set_alias("ls", "ls -a").unwrap();
+        assert_eq!(cli.expand_aliases("ls docs"), "ls -a docs");
+
+        cli.set_alias("a", "b x").unwrap();
+        cli.set_alias("b", "a y").unwrap();
+        assert_eq!(cli.expand_aliases("a"), "a y x");
+    }
+
+    #[test_case]
+    fn aliases_round_trip_through_file() {
+        let mut cli = CommandLine::new();
+        cli.set_alias("ll", "ls -l").unwrap();
+        assert!(cli.set_alias("bad name", "ls").is_err());
+        cli.save_aliases().unwrap();
+
+        let mut restored = CommandLine::new();
+        restored.load_aliases();
+        assert_eq!(restored.expand_aliases("ll"), "ls -l");
+    }
+
+    fn type_and_tab(cli: &mut CommandLine, text: &str) {
+        cli.input.push_str(text);
+        cli.cursor_position = cli.input.len();
+        cli.command_completion();
+    }
+
+    #[test_case]
+    fn tab_completes_unique_command() {
+        let mut cli = CommandLine::new();
+        type_and_tab(&mut cli, "hist");
+        assert_eq!(cli.input.as_str(), "history");
+    }
+
+    #[test_case]
+    fn tab_cycles_through_ambiguous_commands() {
+        let mut cli = CommandLine::new();
+        type_and_tab(&mut cli, "t");
+        assert_eq!(cli.input.as_str(), "t");
+
+        cli.command_completion();
+        assert_eq!(cli.input.as_str(), "touch");
+        cli.command_completion();
+        assert_eq!(cli.input.as_str(), "theme");
+    }
+
+    #[test_case]
+    fn tab_completes_filenames_after_command() {
+        let _ = FILESYSTEM.lock().create_file("tabnotes.txt", "");
+        let _ = FILESYSTEM.lock().create_file("tabnotebook.txt", "");
+
+        let mut cli = CommandLine::new();
+        type_and_tab(&mut cli, "cat tabno");
+        assert_eq!(cli.input.as_str(), "cat tabnote");
+
+        // Another key ends the cycle and the next Tab starts over
+        cli.handle_input('s');
+        cli.handle_input('\t');
+        assert_eq!(cli.input.as_str(), "cat tabnotes.txt");
+    }
+
+    #[test_case]
+    fn pipe_into_grep_and_redirect() {
+        let _ = write_or_create("pipe_in.txt", "apple pie\nbanana\npineapple\n");
+
+        let mut cli = CommandLine::new();
+        cli.input.push_str("cat pipe_in.txt | grep apple > pipe_out.txt");
+        cli.process_command();
+
+        assert_eq!(cli.last_exit_status(), EXIT_SUCCESS);
+        assert_eq!(FILESYSTEM.lock().read_file("pipe_out.txt"), Ok("apple pie\npineapple\n"));
+    }
+
+    #[test_case]
+    fn write_then_cat_round_trips() {
+        let mut cli = CommandLine::new();
+        cli.input.push_str("write roundtrip.txt hello disk");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_SUCCESS);
+
+        cli.input.push_str("cat roundtrip.txt > roundtrip_copy.txt");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_SUCCESS);
+        assert_eq!(FILESYSTEM.lock().read_file("roundtrip_copy.txt"), Ok("hello disk\n"));
+
+        cli.input.push_str("cat missing.txt");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_FAILURE);
+    }
+
+    #[test_case]
+    fn batch_file_stops_at_first_failure() {
+        let _ = write_or_create("batch_test.bat",
+            "@echo off\nREM not a command\n\necho one\n-false\necho two\nfalse\necho three\n");
+
+        let mut cli = CommandLine::new();
+        cli.input.push_str("run batch_test.bat > batch_out.txt");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_FAILURE);
+        assert_eq!(FILESYSTEM.lock().read_file("batch_out.txt"), Ok("one\ntwo\n"));
+    }
+
+    #[test_case]
+    fn batch_file_echoes_commands() {
+        let _ = write_or_create("batch_echo.bat", "echo loud\n@echo quiet\n");
+
+        let mut cli = CommandLine::new();
+        cli.input.push_str("run batch_echo.bat > batch_echo.txt");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_SUCCESS);
+        assert_eq!(FILESYSTEM.lock().read_file("batch_echo.txt"), Ok("> echo loud\nloud\nquiet\n"));
+    }
+
+    #[test_case]
+    fn recursive_batch_file_stops() {
+        let _ = write_or_create("batch_loop.bat", "run batch_loop.bat\n");
+
+        let mut cli = CommandLine::new();
+        cli.input.push_str("run batch_loop.bat");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_FAILURE);
+        assert_eq!(cli.batch_depth, 0);
+    }
+
+    #[test_case]
+    fn wc_counts_empty_text() {
+        assert_eq!(count_text(""), (0, 0, 0));
+    }
+
+    #[test_case]
+    fn wc_counts_last_line_without_newline() {
+        assert_eq!(count_text("one two\nthree"), (2, 3, 13));
+        assert_eq!(count_text("one two\nthree\n"), (2, 3, 14));
+        assert_eq!(count_text("  spaced \t  out  \n"), (1, 2, 18));
+    }
+
+    #[test_case]
+    fn wc_selects_counts_with_flags() {
+        let _ = write_or_create("wc_in.txt", "a b\nc");
+
+        let mut cli = CommandLine::new();
+        cli.input.push_str("wc -lw wc_in.txt > wc_out.txt");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_SUCCESS);
+        assert_eq!(FILESYSTEM.lock().read_file("wc_out.txt"), Ok("      2       3 wc_in.txt\n"));
+
+        cli.input.push_str("wc missing.txt");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_FAILURE);
+    }
+
+    #[test_case]
+    fn sysinfo_rows_line_up() {
+        assert_eq!(sysinfo_row("Files", format_args!("{}", 3)), "Files:       3");
+        assert_eq!(sysinfo_row("Color theme", format_args!("DOS")), "Color theme: DOS");
+    }
+
+    #[test_case]
+    fn hexdump_row_pads_short_rows() {
+        assert_eq!(
+            hexdump_row(0x10, b"Hi\n"),
+            "00000010  48 69 0a                                          |Hi.|"
+        );
+    }
+
+    #[test_case]
+    fn hexdump_limits_byte_count() {
+        let _ = write_or_create("hex_in.txt", "abcdefghijklmnopqrstuvwxyz");
+
+        let mut cli = CommandLine::new();
+        cli.input.push_str("hexdump -n 18 hex_in.txt > hex_out.txt");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_SUCCESS);
+
+        let fs = FILESYSTEM.lock();
+        let dump = fs.read_file("hex_out.txt").unwrap();
+        assert_eq!(dump.lines().count(), 2);
+        assert!(dump.lines().nth(1).unwrap().ends_with("|qr|"));
+    }
+
+    #[test_case]
+    fn line_reader_edits_and_finishes() {
+        let mut reader = LineReader::new(8);
+        for c in "abx".chars() {
+            assert_eq!(reader.feed(c), LineInput::Pending);
+        }
+        reader.feed('\u{0008}');
+        assert_eq!(reader.text(), "ab");
+
+        let mut expected = SimpleString::new();
+        expected.push_str("ab");
+        assert_eq!(reader.feed('\n'), LineInput::Done(expected));
+        assert_eq!(reader.text(), "");
+    }
+
+    #[test_case]
+    fn line_reader_cancels_and_limits_length() {
+        let mut reader = LineReader::new(3);
+        for c in "abcd\t".chars() {
+            reader.feed(c);
+        }
+        assert_eq!(reader.text(), "abc");
+        assert_eq!(reader.feed('\u{001B}'), LineInput::Cancelled);
+        assert_eq!(reader.text(), "");
+    }
+
+    #[test_case]
+    fn echo_expands_variables() {
+        let mut cli = CommandLine::new();
+        cli.input.push_str("set X hi");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_SUCCESS);
+
+        cli.input.push_str("echo $X, $MISSING$X! > env_out.txt");
+        cli.process_command();
+        assert_eq!(FILESYSTEM.lock().read_file("env_out.txt"), Ok("hi, hi!\n"));
+
+        cli.input.push_str("unset X");
+        cli.process_command();
+        assert_eq!(cli.expand_variables("[$X]"), "[]");
+    }
+
+    #[test_case]
+    fn exit_status_variable_is_read_only() {
+        let mut cli = CommandLine::new();
+        cli.input.push_str("false");
+        cli.process_command();
+        assert_eq!(cli.expand_variables("status $? costs $5"), "status 1 costs ");
+        assert!(cli.set_var("?", "0").is_err());
+        assert!(cli.set_var("1X", "0").is_err());
+    }
+
+    #[test_case]
+    fn log_command_validates_arguments() {
+        use crate::logger::{LogLevel, LOGGER};
+
+        let mut cli = CommandLine::new();
+        let original = LOGGER.lock().level();
+
+        cli.input.push_str("log level error");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_SUCCESS);
+        assert_eq!(LOGGER.lock().level(), LogLevel::Error);
+
+        cli.input.push_str("log level loud");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_FAILURE);
+
+        cli.input.push_str("log rotate");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_FAILURE);
+
+        LOGGER.lock().set_level(original);
+    }
+
+    #[test_case]
+    fn touch_keeps_content_and_bumps_modified() {
+        let _ = write_or_create("touched.txt", "keep me");
+        let before = {
+            let fs = FILESYSTEM.lock();
+            fs.get_file_modified(fs.find_file("touched.txt").unwrap())
+        };
+        // Modification times count timer ticks, wait for the next one
+        while crate::logger::now() <= before {
+            x86_64::instructions::hlt();
+        }
+
+        let mut cli = CommandLine::new();
+        cli.input.push_str("touch touched.txt");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_SUCCESS);
+
+        let fs = FILESYSTEM.lock();
+        assert_eq!(fs.read_file("touched.txt"), Ok("keep me"));
+        assert!(fs.get_file_modified(fs.find_file("touched.txt").unwrap()) > before);
+    }
+
+    #[test_case]
+    fn globs_expand_against_the_current_directory() {
+        let _ = write_or_create("globcat_b.txt", "two\n");
+        let _ = write_or_create("globcat_a.txt", "one\n");
+
+        let mut cli = CommandLine::new();
+        cli.input.push_str("cat globcat_?.txt > globcat.out");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_SUCCESS);
+        assert_eq!(FILESYSTEM.lock().read_file("globcat.out"), Ok("one\ntwo\n"));
+
+        cli.input.push_str("cp globcat_* globcat.copy");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_FAILURE);
+
+        cli.input.push_str("ls nothing*here");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_FAILURE);
+    }
+
+    #[test_case]
+    fn cp_and_mv_into_a_directory_keep_the_name() {
+        FILESYSTEM.lock().create_directory("intodir");
+        let _ = write_or_create("carried.txt", "cargo");
+
+        let mut cli = CommandLine::new();
+        cli.input.push_str("cp carried.txt carried.txt");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_FAILURE);
+
+        cli.input.push_str("cp carried.txt intodir");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_SUCCESS);
+        assert_eq!(FILESYSTEM.lock().read_file("carried.txt"), Ok("cargo"));
+
+        cli.input.push_str("mv carried.txt intodir");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_FAILURE);
+        cli.input.push_str("rm intodir");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_FAILURE);
+
+        cli.input.push_str("cd intodir");
+        cli.process_command();
+        assert_eq!(FILESYSTEM.lock().read_file("carried.txt"), Ok("cargo"));
+        cli.input.push_str("rm carried.txt");
+        cli.process_command();
+        cli.input.push_str("cd ..");
+        cli.process_command();
+
+        cli.input.push_str("mv carried.txt intodir");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_SUCCESS);
+        assert!(FILESYSTEM.lock().find_file("carried.txt").is_none());
+    }
+
+    #[test_case]
+    fn rmdir_and_rm_r_remove_entries_without_content_silently() {
+        FILESYSTEM.lock().create_directory("emptydir");
+        let _ = write_or_create("plain_r.txt", "x");
+
+        let mut cli = CommandLine::new();
+        cli.input.push_str("rmdir plain_r.txt");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_FAILURE);
+
+        cli.input.push_str("rmdir emptydir");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_SUCCESS);
+        assert!(FILESYSTEM.lock().find_file("emptydir").is_none());
+
+        cli.input.push_str("rm -r plain_r.txt");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_SUCCESS);
+        assert!(FILESYSTEM.lock().find_file("plain_r.txt").is_none());
+    }
+
+    #[test_case]
+    fn file_globs_skip_directories() {
+        let _ = write_or_create("globdir_a.txt", "one\n");
+        FILESYSTEM.lock().create_directory("globdir_sub");
+
+        let mut cli = CommandLine::new();
+        cli.input.push_str("cat globdir_* > globdir.out");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_SUCCESS);
+        assert_eq!(FILESYSTEM.lock().read_file("globdir.out"), Ok("one\n"));
+
+        cli.input.push_str("rm globdir_*");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_SUCCESS);
+        assert!(FILESYSTEM.lock().is_directory("globdir_sub"));
+    }
+
+    #[test_case]
+    fn rm_removes_a_single_glob_match_without_asking() {
+        let _ = write_or_create("lonely.tmp", "x");
+
+        let mut cli = CommandLine::new();
+        cli.input.push_str("rm lonely.t?p");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_SUCCESS);
+        assert!(FILESYSTEM.lock().find_file("lonely.tmp").is_none());
+
+        cli.input.push_str("rm lonely.*");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_FAILURE);
+    }
+
+    #[test_case]
+    fn grep_without_match_fails() {
+        let _ = write_or_create("pipe_in2.txt", "one\ntwo\n");
+
+        let mut cli = CommandLine::new();
+        cli.input.push_str("grep three pipe_in2.txt");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_FAILURE);
+    }
+
+    #[test_case]
+    fn cd_and_pwd_track_the_current_directory() {
+        let mut cli = CommandLine::new();
+        cli.input.push_str("cd system");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_SUCCESS);
+        assert_eq!(FILESYSTEM.lock().get_current_directory(), "/system");
+
+        cli.input.push_str("cd ..");
+        cli.process_command();
+        assert_eq!(FILESYSTEM.lock().get_current_directory(), "/");
+
+        cli.input.push_str("cd missing");
+        cli.process_command();
+        assert_eq!(cli.last_exit_status(), EXIT_FAILURE);
+    }
+}
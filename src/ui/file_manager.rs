@@ -1,22 +1,34 @@
 // src/ui/file_manager.rs
 // File manager for ScreammOS
 
-use crate::vga_buffer::{Color, WRITER};
+use crate::vga_buffer::{Color, WRITER, BUFFER_WIDTH, BUFFER_HEIGHT};
+use crate::ui::{draw_text_centered, fill_rect, Component, Rect};
+use crate::ui::menu::{Menu, MenuBar, MenuEvent, MenuItem};
+use pc_keyboard::{DecodedKey, KeyCode};
+use crate::keyboard::modifiers;
 use crate::simple_fs::{FILESYSTEM, FileType, String as FsString, SimpleString};
 use lazy_static::lazy_static;
 use spin::Mutex;
-use crate::{println, print, format};
+use crate::format;
 use crate::{log_info, log_error};
 use crate::error_handler::{report_warning, ErrorDomain};
 use crate::ui::text_editor::TEXT_EDITOR;
+use alloc::vec;
 use alloc::vec::Vec;
-use crate::ui::{Rect, BorderStyle, draw_box};
+use crate::ui::window_manager::WINDOW_MANAGER;
+use core::cmp::Ordering;
 use core::fmt::Write;
 
 // Constants for the file manager UI
-const WINDOW_WIDTH: usize = 60;
+const WINDOW_LEFT: usize = 2;
+const WINDOW_WIDTH: usize = 76;
 const WINDOW_HEIGHT: usize = 20;
 const LIST_HEIGHT: usize = 16;
+const LIST_LEFT: usize = WINDOW_LEFT + 2;
+const LIST_WIDTH: usize = 33;
+// The preview pane fills the rest of the window to the right of the list
+const PREVIEW_LEFT: usize = LIST_LEFT + LIST_WIDTH + 3;
+const PREVIEW_WIDTH: usize = WINDOW_LEFT + WINDOW_WIDTH - 2 - PREVIEW_LEFT;
 
 pub struct FileManager {
     pub visible: bool,
@@ -25,7 +37,30 @@ pub struct FileManager {
     selected_index: usize,
     scroll_offset: usize,
     is_active: bool,
-    rect: Rect,
+    // Name of the entry in preview_lines, so navigating doesn't re-read it
+    preview_name: Option<FsString>,
+    preview_lines: Vec<FsString>,
+    sort_mode: SortMode,
+    sort_ascending: bool,
+    menu: MenuBar,
+}
+
+/// Column the file list is sorted by
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortMode {
+    Name,
+    Size,
+    Type,
+}
+
+impl SortMode {
+    fn next(self) -> SortMode {
+        match self {
+            SortMode::Name => SortMode::Size,
+            SortMode::Size => SortMode::Type,
+            SortMode::Type => SortMode::Name,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -35,6 +70,44 @@ struct FileEntry {
     size: usize,
 }
 
+// Case-insensitive comparison of two names, byte by byte
+fn compare_names(a: &str, b: &str) -> Ordering {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    for i in 0..a.len().min(b.len()) {
+        let order = a[i].to_ascii_lowercase().cmp(&b[i].to_ascii_lowercase());
+        if order != Ordering::Equal {
+            return order;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+// Directories always come before files, the direction only applies within each group
+fn compare_entries(a: &FileEntry, b: &FileEntry, mode: SortMode, ascending: bool) -> Ordering {
+    let is_file = |entry: &FileEntry| entry.file_type == FileType::Regular;
+    let group = is_file(a).cmp(&is_file(b));
+    if group != Ordering::Equal {
+        return group;
+    }
+    
+    let name = compare_names(a.name.as_str(), b.name.as_str());
+    let order = match mode {
+        SortMode::Name => name,
+        SortMode::Size => a.size.cmp(&b.size).then(name),
+        // Only two types and they are already grouped, so this is by extension
+        SortMode::Type => compare_names(extension(a.name.as_str()), extension(b.name.as_str())).then(name),
+    };
+    
+    if ascending { order } else { order.reverse() }
+}
+
+fn extension(name: &str) -> &str {
+    match name.rfind('.') {
+        Some(dot) if dot > 0 => &name[dot + 1..],
+        _ => "",
+    }
+}
+
 impl FileManager {
     pub fn new() -> Self {
         let mut current_dir = SimpleString::new();
@@ -47,7 +120,11 @@ impl FileManager {
             selected_index: 0,
             scroll_offset: 0,
             is_active: false,
-            rect: Rect::new(0, 0, 80, 24),
+            preview_name: None,
+            preview_lines: Vec::new(),
+            sort_mode: SortMode::Name,
+            sort_ascending: true,
+            menu: file_manager_menu(),
         }
     }
     
@@ -55,13 +132,14 @@ impl FileManager {
     pub fn show(&mut self) {
         self.visible = true;
         self.refresh_file_list();
-        self.render();
+        self.redraw();
         log_info!("File manager opened");
     }
     
     // Hide the file manager
     pub fn hide(&mut self) {
         self.visible = false;
+        self.menu.close();
         // Restore screen
         let mut writer = WRITER.lock();
         writer.set_color(Color::LightGray, Color::Black);
@@ -72,6 +150,8 @@ impl FileManager {
     // Update the file list
     fn refresh_file_list(&mut self) {
         self.files.clear();
+        // Contents may have changed, read the preview again
+        self.preview_name = None;
         
         let fs = FILESYSTEM.lock();
         
@@ -99,56 +179,59 @@ impl FileManager {
             });
         }
         
+        // Keep ".." first and sort the rest
+        let (mode, ascending) = (self.sort_mode, self.sort_ascending);
+        self.files[1..].sort_by(|a, b| compare_entries(a, b, mode, ascending));
+        
         // Reset cursor if list has changed
         if self.selected_index >= self.files.len() && !self.files.is_empty() {
             self.selected_index = self.files.len() - 1;
         }
     }
     
+    // Bring the preview up to date and draw the file manager
+    fn redraw(&mut self) {
+        self.update_preview();
+        self.draw();
+    }
+    
     // Draw the file manager UI
-    fn render(&self) {
+    fn draw(&self) {
         let mut writer = WRITER.lock();
         writer.clear_screen();
         
         // Draw title and border
-        writer.set_color(Color::Black, Color::LightGray);
-        
-        // Top border
-        writer.set_position(10, 1);
-        for _ in 0..WINDOW_WIDTH {
-            print!("═");
-        }
+        let (fg, bg) = (Color::Black, Color::LightGray);
+        let column = |x, top, height| Rect { x, y: top, width: 1, height };
+        let border = |y| Rect { x: WINDOW_LEFT, y, width: WINDOW_WIDTH, height: 1 };
         
-        // Title
-        writer.set_position(10 + (WINDOW_WIDTH - 13) / 2, 1);
-        print!(" FILE MANAGER ");
+        fill_rect(&mut *writer, border(1), '═', fg, bg);
+        draw_text_centered(&mut *writer, border(1), 0, " FILE MANAGER ", fg, bg);
         
-        // Left and right borders
-        for y in 2..2+WINDOW_HEIGHT {
-            writer.set_position(10, y);
-            print!("║");
-            writer.set_position(10 + WINDOW_WIDTH - 1, y);
-            print!("║");
-        }
+        // Left and right borders, and the divider in front of the preview
+        fill_rect(&mut *writer, column(WINDOW_LEFT, 2, WINDOW_HEIGHT), '║', fg, bg);
+        fill_rect(&mut *writer, column(WINDOW_LEFT + WINDOW_WIDTH - 1, 2, WINDOW_HEIGHT), '║', fg, bg);
+        fill_rect(&mut *writer, column(PREVIEW_LEFT - 2, 5, LIST_HEIGHT + 2), '│', fg, bg);
         
-        // Bottom border
-        writer.set_position(10, 2 + WINDOW_HEIGHT);
-        for _ in 0..WINDOW_WIDTH {
-            print!("═");
-        }
+        fill_rect(&mut *writer, border(2 + WINDOW_HEIGHT), '═', fg, bg);
         
         // Show current directory
         writer.set_color(Color::White, Color::Blue);
-        writer.set_position(12, 3);
-        print!(" Current directory: {} ", self.current_dir.as_str());
+        writer.set_position(LIST_LEFT, 3);
+        write!(writer, " Current directory: {} ", self.current_dir.as_str()).ok();
         
         // Show file list
         writer.set_color(Color::LightGray, Color::Black);
-        writer.set_position(12, 5);
-        print!(" Name                  Type      Size    ");
+        writer.set_position(LIST_LEFT, 5);
+        let arrow = if self.sort_ascending { '↑' } else { '↓' };
+        let mark = |mode| if self.sort_mode == mode { arrow } else { ' ' };
+        write!(writer, " Name{}            Type{}    Size{}",
+            mark(SortMode::Name), mark(SortMode::Type), mark(SortMode::Size)).ok();
         
-        writer.set_position(12, 6);
-        print!("─────────────────────────────────────────");
+        writer.set_position(LIST_LEFT, 6);
+        for _ in 0..LIST_WIDTH {
+            write!(writer, "─").ok();
+        }
         
         // Show files and directories with scrolling
         let visible_items = LIST_HEIGHT.min(self.files.len());
@@ -167,21 +250,21 @@ impl FileManager {
                 writer.set_color(Color::LightGray, Color::Black);
             }
             
-            writer.set_position(12, 7 + i);
+            writer.set_position(LIST_LEFT, 7 + i);
             
-            // Filename (max 20 characters)
+            // Filename (max 16 characters)
             let mut display_name = FsString::new();
             display_name.push_str(file.name.as_str());
-            if display_name.len() > 20 {
+            if display_name.len() > 16 {
                 display_name.clear();
-                display_name.push_str(&file.name.as_str()[0..17]);
+                display_name.push_str(&file.name.as_str()[0..13]);
                 display_name.push_str("...");
             }
             
             // File type
             let type_str = match file.file_type {
-                FileType::Directory => "<DIR>     ",
-                FileType::Regular => "<FILE>    ",
+                FileType::Directory => "<DIR> ",
+                FileType::Regular => "<FILE>",
             };
             
             // Size
@@ -194,13 +277,77 @@ impl FileManager {
             };
             
             // Write the line
-            print!("{:<20} {:9} {:7}", display_name.as_str(), type_str, size_str);
+            write!(writer, " {:<16} {:6} {:>6} ", display_name.as_str(), type_str, size_str.as_str()).ok();
+        }
+        
+        // Show the preview of the selected entry
+        writer.set_color(Color::LightGray, Color::Black);
+        writer.set_position(PREVIEW_LEFT, 5);
+        write!(writer, "Preview").ok();
+        writer.set_position(PREVIEW_LEFT, 6);
+        for _ in 0..PREVIEW_WIDTH {
+            write!(writer, "─").ok();
+        }
+        for (i, line) in self.preview_lines.iter().enumerate() {
+            writer.set_position(PREVIEW_LEFT, 7 + i);
+            write!(writer, "{}", line.as_str()).ok();
         }
         
         // Show help text
         writer.set_color(Color::Black, Color::LightGray);
-        writer.set_position(12, 7 + LIST_HEIGHT + 1);
-        print!(" ↑/↓:Navigate  ENTER:Open  ESC:Close ");
+        writer.set_position(LIST_LEFT, 7 + LIST_HEIGHT + 1);
+        write!(writer, " ↑/↓:Move ENTER:Open DEL:Delete F2:Rename F7:MkDir F6:Sort ").ok();
+        
+        // Menu bar last, so an open dropdown covers the list
+        self.menu.draw(&mut *writer);
+    }
+    
+    /// Sort by the next column, or reverse the direction of the current one
+    pub fn toggle_sort(&mut self, reverse: bool) {
+        if reverse {
+            self.sort_ascending = !self.sort_ascending;
+        } else {
+            self.sort_mode = self.sort_mode.next();
+            self.sort_ascending = true;
+        }
+        self.refresh_file_list();
+        self.redraw();
+    }
+    
+    // Read the preview of the selected entry, unless it is already cached
+    fn update_preview(&mut self) {
+        let entry = match self.files.get(self.selected_index) {
+            Some(entry) => entry,
+            None => {
+                self.preview_name = None;
+                self.preview_lines.clear();
+                return;
+            }
+        };
+        
+        if self.preview_name.as_ref().map(|name| name.as_str()) == Some(entry.name.as_str()) {
+            return;
+        }
+        
+        self.preview_lines.clear();
+        let fs = FILESYSTEM.lock();
+        
+        if entry.name.as_str() == ".." {
+            self.preview_lines.push(FsString::from("Parent directory"));
+        } else if entry.file_type == FileType::Directory {
+            let count = fs.count_children(entry.name.as_str()).unwrap_or(0);
+            self.preview_lines.push(FsString::from(format!("Directory, {} entries", count).as_str()));
+        } else if let Ok(content) = fs.read_file(entry.name.as_str()) {
+            for line in content.lines().take(LIST_HEIGHT) {
+                let mut preview_line = FsString::new();
+                for c in line.chars().take(PREVIEW_WIDTH) {
+                    preview_line.push(if c == '\t' { ' ' } else { c });
+                }
+                self.preview_lines.push(preview_line);
+            }
+        }
+        
+        self.preview_name = Some(entry.name.clone());
     }
     
     // Navigate up in the file list
@@ -213,7 +360,7 @@ impl FileManager {
                 self.scroll_offset = self.selected_index;
             }
             
-            self.render();
+            self.redraw();
         }
     }
     
@@ -227,7 +374,7 @@ impl FileManager {
                 self.scroll_offset = self.selected_index - LIST_HEIGHT + 1;
             }
             
-            self.render();
+            self.redraw();
         }
     }
     
@@ -251,14 +398,14 @@ impl FileManager {
                             // Update current directory
                             let dir_name = fs.get_current_directory();
                             self.current_dir.clear();
-                            self.current_dir.push_str(dir_name);
+                            self.current_dir.push_str(&dir_name);
                             
                             // Update file list
                             drop(fs);
                             self.refresh_file_list();
                             self.selected_index = 0;
                             self.scroll_offset = 0;
-                            self.render();
+                            self.redraw();
                         },
                         Err(e) => {
                             log_error!("Failed to navigate to parent directory: {}", e);
@@ -275,14 +422,14 @@ impl FileManager {
                             // Update current directory
                             let dir_name = fs.get_current_directory();
                             self.current_dir.clear();
-                            self.current_dir.push_str(dir_name);
+                            self.current_dir.push_str(&dir_name);
                             
                             // Update file list
                             drop(fs);
                             self.refresh_file_list();
                             self.selected_index = 0;
                             self.scroll_offset = 0;
-                            self.render();
+                            self.redraw();
                         },
                         Err(e) => {
                             log_error!("Failed to navigate to directory {}: {}", dir_name, e);
@@ -291,7 +438,7 @@ impl FileManager {
                     }
                 }
             },
-            FileType::Regular => {
+            file_type if file_type.is_file() => {
                 // Open file in text editor
                 let file_name = selected.name.as_str();
                 if let Some(mut text_editor) = TEXT_EDITOR.try_lock() {
@@ -307,93 +454,296 @@ impl FileManager {
                     }
                 }
             },
+            _ => {},
         }
     }
 
-    pub fn set_active(&mut self, active: bool) {
-        self.is_active = active;
+    // Name of the selected entry, unless it is the ".." entry
+    fn selected_name(&self) -> Option<FsString> {
+        self.files.get(self.selected_index)
+            .filter(|entry| entry.name.as_str() != "..")
+            .map(|entry| entry.name.clone())
     }
-
-    pub fn draw(&self, writer: &mut Writer) {
-        draw_box(self.rect, BorderStyle::Double, Some("File Manager"));
+    
+    // Reload the list after a change and redraw
+    fn after_change(&mut self) {
+        self.refresh_file_list();
+        if self.selected_index < self.scroll_offset {
+            self.scroll_offset = self.selected_index;
+        }
+        self.redraw();
+    }
+    
+    fn report_failure(&self, message: &str) {
+        log_error!("{}", message);
+        report_warning(ErrorDomain::Filesystem, message).ok();
+    }
+    
+    /// Delete the selected entry after confirmation
+    pub fn delete_selected(&mut self) {
+        let name = match self.selected_name() {
+            Some(name) => name,
+            None => return,
+        };
         
-        // Draw current directory
-        print!(" Current directory: {} ", self.current_dir.as_str());
+        let question = format!("Delete {}?", name.as_str());
+        if !WINDOW_MANAGER.lock().show_yes_no("Delete", question.as_str()) {
+            return;
+        }
         
-        // Draw file list
-        let mut fs = FILESYSTEM.lock();
-        let file_count = fs.get_file_count();
+        if FILESYSTEM.lock().delete_file(name.as_str()) {
+            log_info!("Deleted {}", name.as_str());
+        } else {
+            self.report_failure(format!("Could not delete {} (directories must be empty)", name.as_str()).as_str());
+        }
+        self.after_change();
+    }
+    
+    /// Rename the selected entry
+    pub fn rename_selected(&mut self) {
+        let old = match self.selected_name() {
+            Some(name) => name,
+            None => return,
+        };
         
-        for i in 0..file_count {
-            let filename = fs.get_filename(i);
-            let file_type = fs.get_file_type(i);
-            let size = fs.get_file_size(i);
-            
-            let type_str = match file_type {
-                FileType::File => "File",
-                FileType::Directory => "Dir",
-                FileType::Symlink => "Link",
-            };
-            
-            let size_str = if size < 1024 {
-                format!("{} B", size)
-            } else if size < 1024 * 1024 {
-                format!("{} KB", size / 1024)
-            } else {
-                format!("{} MB", size / (1024 * 1024))
-            };
-            
-            let display_name = if i == self.selected_index {
-                format!("> {}", filename)
-            } else {
-                format!("  {}", filename)
-            };
-            
-            print!("{:<20} {:9} {:7}", display_name.as_str(), type_str, size_str);
+        let new = match WINDOW_MANAGER.lock().show_input("Rename", old.as_str()) {
+            Some(new) if !new.is_empty() => new,
+            _ => return,
+        };
+        
+        if let Err(e) = FILESYSTEM.lock().rename(old.as_str(), &new) {
+            self.report_failure(format!("Could not rename {}: {}", old.as_str(), e).as_str());
         }
+        self.after_change();
+    }
+    
+    /// Create a directory in the current directory
+    pub fn make_directory(&mut self) {
+        let name = match WINDOW_MANAGER.lock().show_input("Make directory", "Name of the new directory:") {
+            Some(name) if !name.is_empty() => name,
+            _ => return,
+        };
+        
+        if !FILESYSTEM.lock().create_directory(&name) {
+            self.report_failure(format!("Could not create directory {}", name.as_str()).as_str());
+        }
+        self.after_change();
+    }
+    
+    /// Create an empty file in the current directory
+    pub fn create_file(&mut self) {
+        let name = match WINDOW_MANAGER.lock().show_input("New file", "Name of the new file:") {
+            Some(name) if !name.is_empty() => name,
+            _ => return,
+        };
+        
+        if let Err(e) = FILESYSTEM.lock().create_file(&name, "") {
+            self.report_failure(format!("Could not create {}: {}", name.as_str(), e).as_str());
+        }
+        self.after_change();
     }
 
-    pub fn handle_key(&mut self, key: char) {
+    pub fn set_active(&mut self, active: bool) {
+        self.is_active = active;
+    }
+
+    /// Let the menu bar look at a key first. A selected action must be run
+    /// by the caller after releasing FILE_MANAGER, since the actions lock it.
+    pub fn menu_key(&mut self, key: DecodedKey, alt: bool) -> MenuEvent {
+        let event = self.menu.handle_key(key, alt);
+        if !matches!(event, MenuEvent::Ignored) {
+            self.redraw();
+        }
+        event
+    }
+    
+    /// Vim-style navigation: j/k move, l opens and h goes to the parent.
+    /// Returns false for keys the file manager doesn't use.
+    pub fn handle_key(&mut self, key: char) -> bool {
         match key {
-            'j' => {
-                let mut fs = FILESYSTEM.lock();
-                if self.selected_index < fs.get_file_count() - 1 {
-                    self.selected_index += 1;
-                }
-            }
-            'k' => {
-                if self.selected_index > 0 {
-                    self.selected_index -= 1;
-                }
-            }
-            'l' => {
-                let mut fs = FILESYSTEM.lock();
-                if self.selected_index < fs.get_file_count() {
-                    let filename = fs.get_filename(self.selected_index);
-                    if fs.get_file_type(self.selected_index) == FileType::Directory {
-                        self.current_dir.push_str(filename);
-                        self.current_dir.push_str("/");
-                        self.selected_index = 0;
-                    }
-                }
-            }
+            'j' => self.navigate_down(),
+            'k' => self.navigate_up(),
+            'l' => self.open_selected(),
             'h' => {
-                if self.current_dir.len() > 1 {
-                    let mut new_dir = SimpleString::new();
-                    let dir_str = self.current_dir.as_str();
-                    let parent = dir_str.rsplit_once('/').unwrap_or(("", "")).0;
-                    new_dir.push_str(parent);
-                    new_dir.push_str("/");
-                    self.current_dir = new_dir;
-                    self.selected_index = 0;
-                }
+                // ".." is always the first entry
+                self.selected_index = 0;
+                self.scroll_offset = 0;
+                self.open_selected();
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl Component for FileManager {
+    fn render(&self) {
+        self.draw();
+    }
+    
+    /// Enter opens the selection, otherwise the vim-style keys of `handle_key`
+    fn handle_input(&mut self, key: u8) -> bool {
+        if !self.visible {
+            return false;
+        }
+        match key {
+            b'\n' => {
+                self.open_selected();
+                true
             }
-            _ => {}
+            _ => self.handle_key(key as char),
+        }
+    }
+    
+    // The file manager takes over the whole screen
+    fn get_bounds(&self) -> Rect {
+        Rect { x: 0, y: 0, width: BUFFER_WIDTH, height: BUFFER_HEIGHT }
+    }
+    
+    /// Arrows, Enter and the function keys. Ctrl keys are left for the
+    /// global shortcuts.
+    fn handle_key_event(&mut self, key: DecodedKey) -> bool {
+        if !self.visible {
+            return false;
         }
+        
+        let shift = modifiers().shift;
+        match key {
+            DecodedKey::Unicode(_) if modifiers().ctrl => return false,
+            DecodedKey::Unicode(c) => return self.handle_input(c as u8),
+            DecodedKey::RawKey(KeyCode::ArrowUp) => self.navigate_up(),
+            DecodedKey::RawKey(KeyCode::ArrowDown) => self.navigate_down(),
+            DecodedKey::RawKey(KeyCode::Return) => self.open_selected(),
+            DecodedKey::RawKey(KeyCode::Delete) => self.delete_selected(),
+            // F2 - byt namn
+            DecodedKey::RawKey(KeyCode::F2) => self.rename_selected(),
+            // Shift+F4 - ny fil
+            DecodedKey::RawKey(KeyCode::F4) if shift => self.create_file(),
+            // F6 - sorteringsordning, Shift+F6 vänder riktningen
+            DecodedKey::RawKey(KeyCode::F6) => self.toggle_sort(shift),
+            // F7 - ny katalog
+            DecodedKey::RawKey(KeyCode::F7) => self.make_directory(),
+            DecodedKey::RawKey(_) => return false,
+        }
+        true
+    }
+    
+    fn is_visible(&self) -> bool {
+        self.visible
     }
 }
 
+// Run a menu action on the file manager
+fn with_file_manager(action: impl FnOnce(&mut FileManager)) {
+    if let Some(mut file_manager) = FILE_MANAGER.try_lock() {
+        action(&mut file_manager);
+    }
+}
+
+fn menu_open() {
+    with_file_manager(|fm| fm.open_selected());
+}
+
+fn menu_new_file() {
+    with_file_manager(|fm| fm.create_file());
+}
+
+fn menu_new_directory() {
+    with_file_manager(|fm| fm.make_directory());
+}
+
+fn menu_close() {
+    with_file_manager(|fm| fm.hide());
+}
+
+fn menu_rename() {
+    with_file_manager(|fm| fm.rename_selected());
+}
+
+fn menu_delete() {
+    with_file_manager(|fm| fm.delete_selected());
+}
+
+fn menu_sort() {
+    with_file_manager(|fm| fm.toggle_sort(false));
+}
+
+fn menu_reverse() {
+    with_file_manager(|fm| fm.toggle_sort(true));
+}
+
+fn menu_refresh() {
+    with_file_manager(|fm| {
+        fm.refresh_file_list();
+        fm.redraw();
+    })
+}
+
+fn file_manager_menu() -> MenuBar {
+    MenuBar::new(vec![
+        Menu {
+            title: "File",
+            hotkey: 'f',
+            items: vec![
+                MenuItem { label: "Open", action: menu_open },
+                MenuItem { label: "New file", action: menu_new_file },
+                MenuItem { label: "New directory", action: menu_new_directory },
+                MenuItem { label: "Close", action: menu_close },
+            ],
+        },
+        Menu {
+            title: "Edit",
+            hotkey: 'e',
+            items: vec![
+                MenuItem { label: "Rename", action: menu_rename },
+                MenuItem { label: "Delete", action: menu_delete },
+            ],
+        },
+        Menu {
+            title: "View",
+            hotkey: 'v',
+            items: vec![
+                MenuItem { label: "Next sort column", action: menu_sort },
+                MenuItem { label: "Reverse order", action: menu_reverse },
+                MenuItem { label: "Refresh", action: menu_refresh },
+            ],
+        },
+    ])
+}
+
 // Global instances
 lazy_static! {
     pub static ref FILE_MANAGER: Mutex<FileManager> = Mutex::new(FileManager::new());
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn entry(name: &str, file_type: FileType, size: usize) -> FileEntry {
+        FileEntry { name: FsString::from(name), file_type, size }
+    }
+
+    #[test_case]
+    fn names_compare_case_insensitively() {
+        assert_eq!(compare_names("abc", "ABD"), Ordering::Less);
+        assert_eq!(compare_names("Readme", "readme"), Ordering::Equal);
+        assert_eq!(compare_names("ab", "abc"), Ordering::Less);
+    }
+
+    #[test_case]
+    fn directories_sort_before_files() {
+        let mut files = vec![
+            entry("b.txt", FileType::Regular, 10),
+            entry("zdir", FileType::Directory, 0),
+            entry("a.txt", FileType::Regular, 99),
+        ];
+        files.sort_by(|a, b| compare_entries(a, b, SortMode::Name, false));
+        let names: Vec<&str> = files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["zdir", "b.txt", "a.txt"]);
+
+        files.sort_by(|a, b| compare_entries(a, b, SortMode::Size, true));
+        let names: Vec<&str> = files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["zdir", "b.txt", "a.txt"]);
+    }
+}
@@ -0,0 +1,297 @@
+// src/ui/menu.rs
+// DOS-style menu bar with dropdown menus
+
+use alloc::vec::Vec;
+use pc_keyboard::{DecodedKey, KeyCode};
+use crate::ui::{draw_text_at, fill_rect, Canvas, Component, Rect, UITheme};
+use crate::vga_buffer::{get_current_theme, WRITER, BUFFER_WIDTH};
+
+// Row the bar is drawn on
+const BAR_ROW: usize = 0;
+
+/// One action in a dropdown
+pub struct MenuItem {
+    pub label: &'static str,
+    pub action: fn(),
+}
+
+/// A top-level entry in the bar and the actions under it. Alt and
+/// `hotkey` opens it, the letter is highlighted in the title.
+pub struct Menu {
+    pub title: &'static str,
+    pub hotkey: char,
+    pub items: Vec<MenuItem>,
+}
+
+/// What a key did to the menu bar
+pub enum MenuEvent {
+    /// The key was not meant for the menu
+    Ignored,
+    /// The menu used the key, redraw it
+    Consumed,
+    /// An item was chosen and the menu closed. The caller runs the action,
+    /// once it no longer holds any locks the action might need.
+    Selected(fn()),
+}
+
+pub struct MenuBar {
+    menus: Vec<Menu>,
+    open: Option<usize>,
+    selected: usize,
+}
+
+impl MenuBar {
+    pub fn new(menus: Vec<Menu>) -> Self {
+        Self {
+            menus,
+            open: None,
+            selected: 0,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open.is_some()
+    }
+
+    pub fn close(&mut self) {
+        self.open = None;
+    }
+
+    // Column each menu title starts at, one space padding on both sides
+    fn title_x(&self, index: usize) -> usize {
+        1 + self.menus[..index].iter().map(|menu| menu.title.len() + 3).sum::<usize>()
+    }
+
+    // The dropdown of an open menu, including its frame
+    fn dropdown_rect(&self, index: usize) -> Rect {
+        let menu = &self.menus[index];
+        let width = menu.items.iter().map(|item| item.label.len()).max().unwrap_or(0) + 4;
+        Rect {
+            x: self.title_x(index).min(BUFFER_WIDTH.saturating_sub(width)),
+            y: BAR_ROW + 1,
+            width,
+            height: menu.items.len() + 2,
+        }
+    }
+
+    fn open_menu(&mut self, index: usize) {
+        self.open = Some(index);
+        self.selected = 0;
+    }
+
+    fn find_hotkey(&self, c: char) -> Option<usize> {
+        let c = c.to_ascii_lowercase();
+        self.menus.iter().position(|menu| menu.hotkey.to_ascii_lowercase() == c)
+    }
+
+    /// Handle a key. `alt` tells whether Alt was held, which is what opens
+    /// a menu from its hotkey.
+    pub fn handle_key(&mut self, key: DecodedKey, alt: bool) -> MenuEvent {
+        let open = match self.open {
+            Some(open) => open,
+            None => {
+                return match key {
+                    DecodedKey::Unicode(c) if alt => match self.find_hotkey(c) {
+                        Some(index) => {
+                            self.open_menu(index);
+                            MenuEvent::Consumed
+                        }
+                        None => MenuEvent::Ignored,
+                    },
+                    _ => MenuEvent::Ignored,
+                };
+            }
+        };
+
+        // An open menu takes every key until it is closed
+        let count = self.menus.len();
+        let items = self.menus[open].items.len();
+        match key {
+            DecodedKey::Unicode('\n') | DecodedKey::RawKey(KeyCode::Return) => {
+                self.close();
+                if let Some(item) = self.menus[open].items.get(self.selected) {
+                    return MenuEvent::Selected(item.action);
+                }
+            }
+            DecodedKey::Unicode('\u{001B}') | DecodedKey::RawKey(KeyCode::Escape) => self.close(),
+            DecodedKey::RawKey(KeyCode::ArrowLeft) => self.open_menu((open + count - 1) % count),
+            DecodedKey::RawKey(KeyCode::ArrowRight) => self.open_menu((open + 1) % count),
+            DecodedKey::RawKey(KeyCode::ArrowUp) if items > 0 => {
+                self.selected = (self.selected + items - 1) % items;
+            }
+            DecodedKey::RawKey(KeyCode::ArrowDown) if items > 0 => {
+                self.selected = (self.selected + 1) % items;
+            }
+            DecodedKey::Unicode(c) if alt => {
+                if let Some(index) = self.find_hotkey(c) {
+                    self.open_menu(index);
+                }
+            }
+            _ => {}
+        }
+        MenuEvent::Consumed
+    }
+
+    /// Draw the bar, and the dropdown if a menu is open
+    pub fn draw<C: Canvas>(&self, canvas: &mut C) {
+        let theme = UITheme::for_theme(get_current_theme());
+        let (fg, bg) = (theme.menu_fg, theme.menu_bg);
+
+        fill_rect(canvas, Rect { x: 0, y: BAR_ROW, width: BUFFER_WIDTH, height: 1 }, ' ', fg, bg);
+        for (i, menu) in self.menus.iter().enumerate() {
+            // The open menu's title is inverted
+            let (title_fg, title_bg) = if self.open == Some(i) { (bg, fg) } else { (fg, bg) };
+            let x = self.title_x(i);
+            draw_text_at(canvas, x, BAR_ROW, " ", title_fg, title_bg);
+            draw_text_at(canvas, x + 1, BAR_ROW, menu.title, title_fg, title_bg);
+            draw_text_at(canvas, x + 1 + menu.title.len(), BAR_ROW, " ", title_fg, title_bg);
+
+            let hotkey = menu.title.find(|c: char| c.eq_ignore_ascii_case(&menu.hotkey));
+            if let Some(offset) = hotkey {
+                let letter = &menu.title[offset..offset + 1];
+                draw_text_at(canvas, x + 1 + offset, BAR_ROW, letter, theme.highlight_color, title_bg);
+            }
+        }
+
+        if let Some(open) = self.open {
+            let rect = self.dropdown_rect(open);
+            let right = rect.x + rect.width - 1;
+            let bottom = rect.y + rect.height - 1;
+
+            fill_rect(canvas, rect, ' ', fg, bg);
+            fill_rect(canvas, Rect { x: rect.x + 1, y: rect.y, width: rect.width - 2, height: 1 }, '─', fg, bg);
+            fill_rect(canvas, Rect { x: rect.x + 1, y: bottom, width: rect.width - 2, height: 1 }, '─', fg, bg);
+            fill_rect(canvas, Rect { x: rect.x, y: rect.y + 1, width: 1, height: rect.height - 2 }, '│', fg, bg);
+            fill_rect(canvas, Rect { x: right, y: rect.y + 1, width: 1, height: rect.height - 2 }, '│', fg, bg);
+            canvas.put_char(rect.x, rect.y, '┌', fg, bg);
+            canvas.put_char(right, rect.y, '┐', fg, bg);
+            canvas.put_char(rect.x, bottom, '└', fg, bg);
+            canvas.put_char(right, bottom, '┘', fg, bg);
+
+            for (i, item) in self.menus[open].items.iter().enumerate() {
+                let (item_fg, item_bg) = if i == self.selected { (bg, fg) } else { (fg, bg) };
+                let row = Rect { x: rect.x + 1, y: rect.y + 1 + i, width: rect.width - 2, height: 1 };
+                fill_rect(canvas, row, ' ', item_fg, item_bg);
+                draw_text_at(canvas, rect.x + 2, row.y, item.label, item_fg, item_bg);
+            }
+        }
+    }
+}
+
+impl Component for MenuBar {
+    fn render(&self) {
+        self.draw(&mut *WRITER.lock());
+    }
+
+    /// Plain keys only, so Alt hotkeys can't open a menu from here. A chosen
+    /// action runs right away, use `handle_key` when holding locks.
+    fn handle_input(&mut self, key: u8) -> bool {
+        match self.handle_key(DecodedKey::Unicode(key as char), false) {
+            MenuEvent::Ignored => false,
+            MenuEvent::Consumed => true,
+            MenuEvent::Selected(action) => {
+                action();
+                true
+            }
+        }
+    }
+
+    fn get_bounds(&self) -> Rect {
+        match self.open {
+            Some(open) => {
+                let dropdown = self.dropdown_rect(open);
+                Rect { x: 0, y: BAR_ROW, width: BUFFER_WIDTH, height: dropdown.y + dropdown.height - BAR_ROW }
+            }
+            None => Rect { x: 0, y: BAR_ROW, width: BUFFER_WIDTH, height: 1 },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    static CHOSEN: AtomicUsize = AtomicUsize::new(0);
+
+    fn first() {
+        CHOSEN.store(1, Ordering::Relaxed);
+    }
+
+    fn second() {
+        CHOSEN.store(2, Ordering::Relaxed);
+    }
+
+    fn test_bar() -> MenuBar {
+        MenuBar::new(vec![
+            Menu {
+                title: "File",
+                hotkey: 'f',
+                items: vec![
+                    MenuItem { label: "First", action: first },
+                    MenuItem { label: "Second", action: second },
+                ],
+            },
+            Menu {
+                title: "View",
+                hotkey: 'v',
+                items: vec![MenuItem { label: "First", action: first }],
+            },
+        ])
+    }
+
+    fn key(code: KeyCode) -> DecodedKey {
+        DecodedKey::RawKey(code)
+    }
+
+    #[test_case]
+    fn alt_hotkey_opens_menu() {
+        let mut bar = test_bar();
+        assert!(matches!(bar.handle_key(DecodedKey::Unicode('v'), false), MenuEvent::Ignored));
+        assert!(!bar.is_open());
+
+        assert!(matches!(bar.handle_key(DecodedKey::Unicode('V'), true), MenuEvent::Consumed));
+        assert_eq!(bar.open, Some(1));
+    }
+
+    #[test_case]
+    fn arrows_navigate_and_enter_selects() {
+        let mut bar = test_bar();
+        bar.handle_key(DecodedKey::Unicode('f'), true);
+        bar.handle_key(key(KeyCode::ArrowUp), false);
+        assert_eq!(bar.selected, 1);
+
+        match bar.handle_key(DecodedKey::Unicode('\n'), false) {
+            MenuEvent::Selected(action) => action(),
+            _ => panic!("Enter did not select an item"),
+        }
+        assert_eq!(CHOSEN.load(Ordering::Relaxed), 2);
+        assert!(!bar.is_open());
+    }
+
+    #[test_case]
+    fn left_and_right_switch_menus() {
+        let mut bar = test_bar();
+        bar.handle_key(DecodedKey::Unicode('f'), true);
+        bar.handle_key(key(KeyCode::ArrowDown), false);
+        bar.handle_key(key(KeyCode::ArrowRight), false);
+        assert_eq!(bar.open, Some(1));
+        assert_eq!(bar.selected, 0);
+
+        bar.handle_key(key(KeyCode::ArrowRight), false);
+        assert_eq!(bar.open, Some(0));
+
+        bar.handle_key(DecodedKey::Unicode('\u{001B}'), false);
+        assert!(!bar.is_open());
+    }
+
+    #[test_case]
+    fn bounds_cover_the_dropdown() {
+        let mut bar = test_bar();
+        assert_eq!(bar.get_bounds().height, 1);
+        bar.handle_key(DecodedKey::Unicode('f'), true);
+        // Bar, frame, two items and the bottom frame
+        assert_eq!(bar.get_bounds().height, 5);
+    }
+}
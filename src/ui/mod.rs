@@ -1,7 +1,9 @@
 //! User interface module for ScreammOS
 
+use alloc::vec::Vec;
+use pc_keyboard::DecodedKey;
 use spin::Mutex;
-use crate::vga_buffer::{Color, WRITER};
+use crate::vga_buffer::{Color, Theme, Writer, WRITER, BUFFER_WIDTH, BUFFER_HEIGHT};
 use crate::println;
 use crate::ui::command_line::CommandLine;
 
@@ -11,9 +13,15 @@ pub mod text_editor;
 pub mod command_line;
 pub mod splash_screen;
 pub mod retro_commands;
+pub mod pager;
+pub mod screensaver;
+pub mod menu;
 
 pub static UI_STATE: Mutex<CommandLine> = Mutex::new(CommandLine::new());
 
+/// Vilken komponent som tar emot tangenttryckningar
+pub static FOCUS: Mutex<FocusManager> = Mutex::new(FocusManager::new());
+
 /// Different UI themes for ScreammOS
 pub enum UIThemeType {
     Classic,
@@ -35,6 +43,15 @@ pub struct UITheme {
 }
 
 impl UITheme {
+    /// The window theme that matches a screen color theme
+    pub fn for_theme(theme: Theme) -> Self {
+        match theme {
+            Theme::Classic | Theme::Modern => Self::dos_classic(),
+            Theme::Green => Self::green_crt(),
+            Theme::Amber => Self::amber_terminal(),
+        }
+    }
+
     /// Create the classic DOS theme
     pub fn dos_classic() -> Self {
         Self {
@@ -92,115 +109,315 @@ pub trait Component {
     fn render(&self);
     fn handle_input(&mut self, key: u8) -> bool;
     fn get_bounds(&self) -> Rect;
+
+    /// Hela tangenten, även pil- och funktionstangenter. Som standard
+    /// skickas ASCII-tecken vidare till `handle_input`.
+    fn handle_key_event(&mut self, key: DecodedKey) -> bool {
+        match key {
+            DecodedKey::Unicode(c) if c.is_ascii() => self.handle_input(c as u8),
+            _ => false,
+        }
+    }
+
+    /// Dolda komponenter hoppas över när fokus flyttas
+    fn is_visible(&self) -> bool {
+        true
+    }
+}
+
+/// En komponent som fokushanteraren kan skicka tangenter till
+pub type ComponentHandle = &'static Mutex<dyn Component + Send>;
+
+/// Håller komponenterna i tabbordning och vet vilken som har fokus.
+/// En komponent som blir synlig får fokus, och när den fokuserade
+/// komponenten döljs går fokus bakåt till närmaste synliga.
+pub struct FocusManager {
+    components: Vec<ComponentHandle>,
+    // Om varje komponent var synlig förra gången vi tittade
+    shown: Vec<bool>,
+    focused: usize,
+}
+
+impl FocusManager {
+    pub const fn new() -> Self {
+        Self {
+            components: Vec::new(),
+            shown: Vec::new(),
+            focused: 0,
+        }
+    }
+
+    /// Lägg till en komponent sist i tabbordningen
+    pub fn register(&mut self, component: ComponentHandle) {
+        self.components.push(component);
+        self.shown.push(false);
+    }
+
+    /// Komponenten som har fokus
+    pub fn focused(&mut self) -> Option<ComponentHandle> {
+        self.update();
+        self.components.get(self.focused).copied()
+    }
+
+    /// Flytta fokus till nästa synliga komponent och rita den
+    pub fn focus_next(&mut self) -> bool {
+        self.update();
+        self.step(1) && self.render_focused()
+    }
+
+    /// Flytta fokus till föregående synliga komponent och rita den
+    pub fn focus_prev(&mut self) -> bool {
+        self.update();
+        let back = self.components.len().saturating_sub(1);
+        self.step(back) && self.render_focused()
+    }
+
+    /// Skicka en tangent till komponenten som har fokus. Returnerar om
+    /// komponenten använde den.
+    pub fn dispatch(&mut self, key: DecodedKey) -> bool {
+        match self.focused().and_then(|component| component.try_lock()) {
+            Some(mut component) => component.handle_key_event(key),
+            None => false,
+        }
+    }
+
+    // Följ upp komponenter som visats eller dolts sedan förra gången
+    fn update(&mut self) {
+        for (i, component) in self.components.iter().enumerate() {
+            // En upptagen komponent behåller sitt förra läge
+            let visible = component.try_lock().map_or(self.shown[i], |component| component.is_visible());
+            if visible && !self.shown[i] {
+                self.focused = i;
+            }
+            self.shown[i] = visible;
+        }
+
+        if !self.shown.get(self.focused).copied().unwrap_or(true) {
+            let back = self.components.len() - 1;
+            self.step(back);
+        }
+    }
+
+    // Gå `step` platser i taget tills en synlig komponent hittas
+    fn step(&mut self, step: usize) -> bool {
+        let count = self.components.len();
+        for i in 1..count {
+            let index = (self.focused + i * step) % count;
+            if self.shown[index] {
+                self.focused = index;
+                return true;
+            }
+        }
+        false
+    }
+
+    fn render_focused(&self) -> bool {
+        if let Some(component) = self.components[self.focused].try_lock() {
+            component.render();
+        }
+        true
+    }
 }
 
 /// Border types for DOS-style UI
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum BorderStyle {
     Single,     // ─ │ ┌ ┐ └ ┘
     Double,     // ═ ║ ╔ ╗ ╚ ╝
     SingleHeavy, // ━ ┃ ┏ ┓ ┗ ┛
+    Rounded,    // ─ │ . . ' '  (437 saknar rundade hörn)
     None,
 }
 
+impl BorderStyle {
+    pub const ALL: [BorderStyle; 5] = [
+        BorderStyle::Single,
+        BorderStyle::Double,
+        BorderStyle::SingleHeavy,
+        BorderStyle::Rounded,
+        BorderStyle::None,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BorderStyle::Single => "Single",
+            BorderStyle::Double => "Double",
+            BorderStyle::SingleHeavy => "Heavy",
+            BorderStyle::Rounded => "Rounded",
+            BorderStyle::None => "None",
+        }
+    }
+}
+
 /// Draw a DOS-style box
 pub fn draw_box(rect: Rect, style: BorderStyle, title: Option<&str>) {
     let mut writer = WRITER.lock();
-    
-    // Spara nuvarande position
-    let saved_row = writer.row_position;
-    let saved_col = writer.column_position;
+    let (fg, bg) = writer.get_color();
+    draw_box_on(&mut *writer, rect, style, title, fg, bg);
+}
+
+/// Rita en ram på valfri yta. Rektanglar smalare eller lägre än två
+/// tecken ritas inte, och det som hamnar utanför skärmen klipps bort.
+pub fn draw_box_on<C: Canvas>(canvas: &mut C, rect: Rect, style: BorderStyle, title: Option<&str>, fg: Color, bg: Color) {
+    if rect.width < 2 || rect.height < 2 {
+        return;
+    }
     
     // Välj tecken för ramen baserat på stil
     let (top_left, top_right, bottom_left, bottom_right, horizontal, vertical) = match style {
-        BorderStyle::Single => (b'\xDA', b'\xBF', b'\xC0', b'\xD9', b'\xC4', b'\xB3'),
-        BorderStyle::Double => (b'\xC9', b'\xBB', b'\xC8', b'\xBC', b'\xCD', b'\xBA'),
-        BorderStyle::SingleHeavy => (0xD5, 0xB8, 0xD4, 0xBE, 0xCD, 0xB3),
-        BorderStyle::None => (b' ', b' ', b' ', b' ', b' ', b' '),
+        BorderStyle::Single => ('┌', '┐', '└', '┘', '─', '│'),
+        BorderStyle::Double => ('╔', '╗', '╚', '╝', '═', '║'),
+        BorderStyle::SingleHeavy => ('╒', '╕', '╘', '╛', '═', '│'),
+        BorderStyle::Rounded => ('.', '.', '\'', '\'', '─', '│'),
+        BorderStyle::None => (' ', ' ', ' ', ' ', ' ', ' '),
     };
     
-    // Rita övre ramen
-    writer.row_position = rect.y;
-    writer.column_position = rect.x;
-    writer.write_byte(top_left);
+    let right = rect.x.saturating_add(rect.width - 1);
+    let bottom = rect.y.saturating_add(rect.height - 1);
+    let inner = Rect { x: rect.x.saturating_add(1), y: rect.y.saturating_add(1), width: rect.width - 2, height: rect.height - 2 };
     
-    // Rita titeln om den finns
-    if let Some(title_text) = title {
-        let title_len = title_text.len();
-        let available_width = rect.width - 2;
-        
-        if title_len <= available_width {
-            let padding_before = (available_width - title_len) / 2;
-            let padding_after = available_width - title_len - padding_before;
-            
-            for _ in 0..padding_before {
-                writer.write_byte(horizontal);
-            }
-            
-            writer.write_string(title_text);
-            
-            for _ in 0..padding_after {
-                writer.write_byte(horizontal);
-            }
-        } else {
-            for _ in 0..(rect.width-2) {
-                writer.write_byte(horizontal);
-            }
-        }
-    } else {
-        for _ in 0..(rect.width-2) {
-            writer.write_byte(horizontal);
-        }
-    }
-    
-    writer.write_byte(top_right);
+    // Övre och nedre ramen
+    fill_rect(canvas, Rect { height: 1, y: rect.y, ..inner }, horizontal, fg, bg);
+    fill_rect(canvas, Rect { height: 1, y: bottom, ..inner }, horizontal, fg, bg);
+    put_clipped(canvas, rect.x, rect.y, top_left, fg, bg);
+    put_clipped(canvas, right, rect.y, top_right, fg, bg);
+    put_clipped(canvas, rect.x, bottom, bottom_left, fg, bg);
+    put_clipped(canvas, right, bottom, bottom_right, fg, bg);
     
-    // Rita sidoramarna
-    for y in 1..(rect.height-1) {
-        writer.row_position = rect.y + y;
-        writer.column_position = rect.x;
-        writer.write_byte(vertical);
-        
-        writer.column_position = rect.x + rect.width - 1;
-        writer.write_byte(vertical);
-    }
+    // Sidoramarna
+    fill_rect(canvas, Rect { x: rect.x, width: 1, ..inner }, vertical, fg, bg);
+    fill_rect(canvas, Rect { x: right, width: 1, ..inner }, vertical, fg, bg);
     
-    // Rita nedre ramen
-    writer.row_position = rect.y + rect.height - 1;
-    writer.column_position = rect.x;
-    writer.write_byte(bottom_left);
-    
-    for _ in 0..(rect.width-2) {
-        writer.write_byte(horizontal);
+    // Titeln centreras i övre ramen om den får plats
+    if let Some(title) = title {
+        let title_len = title.chars().count();
+        if title_len <= inner.width {
+            let x = inner.x + (inner.width - title_len) / 2;
+            for (i, c) in title.chars().enumerate() {
+                put_clipped(canvas, x + i, rect.y, c, fg, bg);
+            }
+        }
     }
-    
-    writer.write_byte(bottom_right);
-    
-    // Återställ skrivarpositionen
-    writer.row_position = saved_row;
-    writer.column_position = saved_col;
 }
 
 /// Rensa insidan av en rektangel
 pub fn clear_rect(rect: Rect) {
-    let mut writer = WRITER.lock();
-    
-    // Spara nuvarande position
-    let saved_row = writer.row_position;
-    let saved_col = writer.column_position;
+    if rect.width < 2 || rect.height < 2 {
+        return;
+    }
     
-    // Rensa insidan av rektangeln
-    for y in 1..(rect.height-1) {
-        writer.row_position = rect.y + y;
-        writer.column_position = rect.x + 1;
-        
-        for _ in 0..(rect.width-2) {
-            writer.write_byte(b' ');
+    let mut writer = WRITER.lock();
+    let (fg, bg) = writer.get_color();
+    let inner = Rect { x: rect.x.saturating_add(1), y: rect.y.saturating_add(1), width: rect.width - 2, height: rect.height - 2 };
+    fill_rect(&mut *writer, inner, ' ', fg, bg);
+}
+
+/// Den del av en rektangel som ligger på skärmen
+pub fn clip_to_screen(rect: Rect) -> Rect {
+    let x = rect.x.min(BUFFER_WIDTH);
+    let y = rect.y.min(BUFFER_HEIGHT);
+    Rect {
+        x,
+        y,
+        width: rect.x.saturating_add(rect.width).min(BUFFER_WIDTH) - x,
+        height: rect.y.saturating_add(rect.height).min(BUFFER_HEIGHT) - y,
+    }
+}
+
+// Rita ett tecken om det hamnar på skärmen
+fn put_clipped<C: Canvas>(canvas: &mut C, x: usize, y: usize, c: char, fg: Color, bg: Color) {
+    if x < BUFFER_WIDTH && y < BUFFER_HEIGHT {
+        canvas.put_char(x, y, c, fg, bg);
+    }
+}
+
+/// Något som går att rita tecken på, VGA-skrivaren eller en testbuffert
+pub trait Canvas {
+    fn put_char(&mut self, x: usize, y: usize, c: char, fg: Color, bg: Color);
+}
+
+impl Canvas for Writer {
+    fn put_char(&mut self, x: usize, y: usize, c: char, fg: Color, bg: Color) {
+        self.write_char_at(x, y, cp437(c), fg, bg);
+    }
+}
+
+/// Översätt ett tecken till teckentabell 437, som VGA-textläget använder.
+/// Tecken som saknas blir ■ precis som i `Writer::write_string`.
+pub fn cp437(c: char) -> char {
+    let byte = match c {
+        c if c.is_ascii() => c as u8,
+        '█' => 0xDB,
+        '░' => 0xB0,
+        '■' => 0xFE,
+        '═' => 0xCD,
+        '║' => 0xBA,
+        '╔' => 0xC9,
+        '╗' => 0xBB,
+        '╚' => 0xC8,
+        '╝' => 0xBC,
+        '─' => 0xC4,
+        '│' => 0xB3,
+        '┌' => 0xDA,
+        '┐' => 0xBF,
+        '└' => 0xC0,
+        '┘' => 0xD9,
+        '╒' => 0xD5,
+        '╕' => 0xB8,
+        '╘' => 0xD4,
+        '╛' => 0xBE,
+        '↑' => 0x18,
+        '↓' => 0x19,
+        _ => 0xFE,
+    };
+    byte as char
+}
+
+/// Rita text med början i (x, y). Returnerar antalet kolumner som ritades.
+pub fn draw_text_at<C: Canvas>(canvas: &mut C, x: usize, y: usize, text: &str, fg: Color, bg: Color) -> usize {
+    let mut width = 0;
+    for (i, c) in text.chars().enumerate() {
+        canvas.put_char(x + i, y, c, fg, bg);
+        width += 1;
+    }
+    width
+}
+
+/// Rita text från (x, y) men högst `width` kolumner. Text som inte får
+/// plats kortas av och avslutas med "...".
+pub fn draw_text_fitted<C: Canvas>(canvas: &mut C, x: usize, y: usize, width: usize, text: &str, fg: Color, bg: Color) -> usize {
+    let len = text.chars().count();
+    if len <= width {
+        return draw_text_at(canvas, x, y, text, fg, bg);
+    }
+
+    let ellipsis = "...".len().min(width);
+    let kept = width - ellipsis;
+    for (i, c) in text.chars().take(kept).enumerate() {
+        canvas.put_char(x + i, y, c, fg, bg);
+    }
+    for i in 0..ellipsis {
+        canvas.put_char(x + kept + i, y, '.', fg, bg);
+    }
+    width
+}
+
+/// Rita text centrerad på raden `y_offset` i rektangeln, avkortad om den
+/// är bredare än rektangeln
+pub fn draw_text_centered<C: Canvas>(canvas: &mut C, rect: Rect, y_offset: usize, text: &str, fg: Color, bg: Color) {
+    let len = text.chars().count().min(rect.width);
+    let x = rect.x + (rect.width - len) / 2;
+    draw_text_fitted(canvas, x, rect.y + y_offset, len, text, fg, bg);
+}
+
+/// Fyll hela rektangeln med ett tecken
+pub fn fill_rect<C: Canvas>(canvas: &mut C, rect: Rect, ch: char, fg: Color, bg: Color) {
+    let rect = clip_to_screen(rect);
+    for y in rect.y..rect.y + rect.height {
+        for x in rect.x..rect.x + rect.width {
+            canvas.put_char(x, y, ch, fg, bg);
         }
     }
-    
-    // Återställ skrivarpositionen
-    writer.row_position = saved_row;
-    writer.column_position = saved_col;
 }
 
 /// Initialisera UI-systemet
@@ -209,7 +426,213 @@ pub fn init() {
     
     // Registrera nödvändiga komponenter
     window_manager::init();
+
+    // Tabbordning: kommandoraden, filhanteraren och textredigeraren
+    let mut focus = FOCUS.lock();
+    focus.register(&UI_STATE);
+    focus.register(&*file_manager::FILE_MANAGER);
+    focus.register(&*text_editor::TEXT_EDITOR);
+    drop(focus);
     
     println!("UI: Användargränssnitt initierat");
     println!("UI: Använd F1 för filhanteraren");
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    const WIDTH: usize = BUFFER_WIDTH;
+    const HEIGHT: usize = BUFFER_HEIGHT;
+
+    // Ett skärmstort rutnät som går att läsa tillbaka i testerna
+    struct TestCanvas {
+        cells: Vec<(char, Color, Color)>,
+        // Antal försök att rita utanför skärmen
+        outside: usize,
+    }
+
+    impl TestCanvas {
+        fn new() -> Self {
+            Self { cells: vec![(' ', Color::LightGray, Color::Black); WIDTH * HEIGHT], outside: 0 }
+        }
+
+        fn cell(&self, x: usize, y: usize) -> (char, Color, Color) {
+            self.cells[y * WIDTH + x]
+        }
+
+        fn text(&self, x: usize, y: usize, len: usize) -> String {
+            (x..x + len).map(|col| self.cell(col, y).0).collect()
+        }
+    }
+
+    impl Canvas for TestCanvas {
+        fn put_char(&mut self, x: usize, y: usize, c: char, fg: Color, bg: Color) {
+            if x < WIDTH && y < HEIGHT {
+                self.cells[y * WIDTH + x] = (c, fg, bg);
+            } else {
+                self.outside += 1;
+            }
+        }
+    }
+
+    fn rect(x: usize, y: usize, width: usize, height: usize) -> Rect {
+        Rect { x, y, width, height }
+    }
+
+    #[test_case]
+    fn text_is_drawn_at_position() {
+        let mut canvas = TestCanvas::new();
+        assert_eq!(draw_text_at(&mut canvas, 3, 2, "Hello", Color::White, Color::Blue), 5);
+        assert_eq!(canvas.text(3, 2, 5), "Hello");
+        assert!(canvas.cell(3, 2) == ('H', Color::White, Color::Blue));
+    }
+
+    #[test_case]
+    fn text_is_centered_in_rect() {
+        let mut canvas = TestCanvas::new();
+        draw_text_centered(&mut canvas, rect(10, 4, 10, 3), 1, "abcd", Color::White, Color::Black);
+        assert_eq!(canvas.text(10, 5, 10), "   abcd   ");
+    }
+
+    #[test_case]
+    fn long_text_is_truncated_with_ellipsis() {
+        let mut canvas = TestCanvas::new();
+        draw_text_centered(&mut canvas, rect(0, 0, 8, 1), 0, "a long title", Color::White, Color::Black);
+        assert_eq!(canvas.text(0, 0, 9), "a lon... ");
+
+        draw_text_fitted(&mut canvas, 0, 1, 2, "abc", Color::White, Color::Black);
+        assert_eq!(canvas.text(0, 1, 3), ".. ");
+    }
+
+    #[test_case]
+    fn fill_covers_the_whole_rect() {
+        let mut canvas = TestCanvas::new();
+        fill_rect(&mut canvas, rect(1, 1, 3, 2), '#', Color::Yellow, Color::Black);
+        assert_eq!(canvas.text(0, 1, 5), " ### ");
+        assert_eq!(canvas.text(0, 2, 5), " ### ");
+        assert_eq!(canvas.text(0, 3, 5), "     ");
+    }
+
+    #[test_case]
+    fn box_glyphs_map_to_code_page_437() {
+        assert_eq!(cp437('█') as u8, 0xDB);
+        assert_eq!(cp437('═') as u8, 0xCD);
+        assert_eq!(cp437('A'), 'A');
+        assert_eq!(cp437('é') as u8, 0xFE);
+    }
+
+    fn boxed(rect: Rect) -> TestCanvas {
+        let mut canvas = TestCanvas::new();
+        draw_box_on(&mut canvas, rect, BorderStyle::Single, Some("Title"), Color::White, Color::Blue);
+        canvas
+    }
+
+    #[test_case]
+    fn degenerate_boxes_draw_nothing() {
+        for &r in [rect(5, 5, 1, 6), rect(5, 5, 6, 0), rect(5, 5, 0, 0)].iter() {
+            let canvas = boxed(r);
+            assert_eq!(canvas.outside, 0);
+            assert_eq!(canvas.text(0, 5, WIDTH), TestCanvas::new().text(0, 5, WIDTH));
+        }
+    }
+
+    #[test_case]
+    fn box_is_drawn_with_centered_title() {
+        let canvas = boxed(rect(2, 1, 11, 3));
+        assert_eq!(canvas.text(2, 1, 11), "┌──Title──┐");
+        assert_eq!(canvas.text(2, 2, 11), "│         │");
+        assert_eq!(canvas.text(2, 3, 11), "└─────────┘");
+    }
+
+    #[test_case]
+    fn box_at_screen_edge_is_clipped() {
+        let mut canvas = TestCanvas::new();
+        draw_box_on(&mut canvas, rect(WIDTH - 3, HEIGHT - 2, 10, 6), BorderStyle::Single, None, Color::White, Color::Blue);
+        assert_eq!(canvas.outside, 0);
+        assert_eq!(canvas.text(WIDTH - 3, HEIGHT - 2, 3), "┌──");
+        assert_eq!(canvas.text(WIDTH - 3, HEIGHT - 1, 3), "│  ");
+
+        let far_away = boxed(rect(usize::MAX - 1, 3, 4, 4));
+        assert_eq!(far_away.outside, 0);
+    }
+
+    // En komponent som bara räknar tangenterna den får
+    struct Probe {
+        visible: bool,
+        keys: usize,
+    }
+
+    impl Probe {
+        const fn new(visible: bool) -> Self {
+            Self { visible, keys: 0 }
+        }
+    }
+
+    impl Component for Probe {
+        fn render(&self) {}
+
+        fn handle_input(&mut self, _key: u8) -> bool {
+            self.keys += 1;
+            true
+        }
+
+        fn get_bounds(&self) -> Rect {
+            rect(0, 0, 1, 1)
+        }
+
+        fn is_visible(&self) -> bool {
+            self.visible
+        }
+    }
+
+    static PROMPT: Mutex<Probe> = Mutex::new(Probe::new(true));
+    static HIDDEN: Mutex<Probe> = Mutex::new(Probe::new(false));
+    static PANEL: Mutex<Probe> = Mutex::new(Probe::new(true));
+
+    #[test_case]
+    fn focus_skips_hidden_components() {
+        let mut focus = FocusManager::new();
+        focus.register(&PROMPT);
+        focus.register(&HIDDEN);
+        focus.register(&PANEL);
+
+        // The last visible component starts out focused
+        assert!(focus.dispatch(DecodedKey::Unicode('a')));
+        assert_eq!(PANEL.lock().keys, 1);
+        assert_eq!(PROMPT.lock().keys, 0);
+
+        assert!(focus.focus_next());
+        focus.dispatch(DecodedKey::Unicode('b'));
+        assert_eq!(PROMPT.lock().keys, 1);
+
+        assert!(focus.focus_next());
+        focus.dispatch(DecodedKey::Unicode('c'));
+        assert_eq!(PANEL.lock().keys, 2);
+        assert_eq!(HIDDEN.lock().keys, 0);
+    }
+
+    static BASE: Mutex<Probe> = Mutex::new(Probe::new(true));
+    static POPUP: Mutex<Probe> = Mutex::new(Probe::new(false));
+
+    #[test_case]
+    fn shown_component_takes_focus_until_hidden() {
+        let mut focus = FocusManager::new();
+        focus.register(&BASE);
+        focus.register(&POPUP);
+        focus.dispatch(DecodedKey::Unicode('a'));
+        assert_eq!(BASE.lock().keys, 1);
+
+        POPUP.lock().visible = true;
+        focus.dispatch(DecodedKey::Unicode('b'));
+        assert_eq!(POPUP.lock().keys, 1);
+
+        POPUP.lock().visible = false;
+        focus.dispatch(DecodedKey::Unicode('c'));
+        assert_eq!(BASE.lock().keys, 2);
+        assert_eq!(POPUP.lock().keys, 1);
+    }
+}
@@ -0,0 +1,250 @@
+// src/ui/pager.rs
+// Buffered page model for the `more`/`less`-style pager
+
+use alloc::vec::Vec;
+use crate::vga_buffer::{Color, WRITER, BUFFER_WIDTH, BUFFER_HEIGHT};
+use crate::simple_fs::SimpleString;
+
+// The bottom row is reserved for the status/prompt line
+const PAGE_HEIGHT: usize = BUFFER_HEIGHT - 1;
+
+/// Outcome of a search through the buffered lines
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SearchResult {
+    Found(usize),    // Line index of the next match
+    Wrapped(usize),  // Match found after wrapping back to the top
+    NotFound,
+}
+
+/// A set of buffered lines that can be paged through and searched
+pub struct Pager<'a> {
+    lines: Vec<&'a str>,
+    top_line: usize,
+    pattern: SimpleString,
+    match_line: Option<usize>,
+    entering_pattern: bool,
+    message: &'static str,
+}
+
+impl<'a> Pager<'a> {
+    /// Create a pager over the given text
+    pub fn new(text: &'a str) -> Self {
+        Self {
+            lines: text.lines().collect(),
+            top_line: 0,
+            pattern: SimpleString::new(),
+            match_line: None,
+            entering_pattern: false,
+            message: "",
+        }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn top_line(&self) -> usize {
+        self.top_line
+    }
+
+    pub fn match_line(&self) -> Option<usize> {
+        self.match_line
+    }
+
+    /// True when the last line is on screen
+    pub fn at_end(&self) -> bool {
+        self.top_line + PAGE_HEIGHT >= self.lines.len()
+    }
+
+    /// Start a new search for `pattern` from the current page
+    pub fn search(&mut self, pattern: &str) -> SearchResult {
+        self.pattern.clear();
+        self.pattern.push_str(pattern);
+        self.match_line = None;
+        self.search_next()
+    }
+
+    /// Jump to the next line containing the current pattern (the `n` key)
+    pub fn search_next(&mut self) -> SearchResult {
+        if self.pattern.is_empty() || self.lines.is_empty() {
+            return SearchResult::NotFound;
+        }
+
+        // Continue after the previous match, or from the top of the page
+        let start = match self.match_line {
+            Some(line) => line + 1,
+            None => self.top_line,
+        };
+
+        let result = match find_line(&self.lines, self.pattern.as_str(), start) {
+            Some(line) if line >= start => SearchResult::Found(line),
+            Some(line) => SearchResult::Wrapped(line),
+            None => SearchResult::NotFound,
+        };
+
+        match result {
+            SearchResult::Found(line) | SearchResult::Wrapped(line) => {
+                self.match_line = Some(line);
+                self.top_line = line;
+            },
+            SearchResult::NotFound => {},
+        }
+
+        self.message = match result {
+            SearchResult::Found(_) => "",
+            SearchResult::Wrapped(_) => "Search wrapped to top",
+            SearchResult::NotFound => "Pattern not found",
+        };
+
+        result
+    }
+
+    /// Handle a key press; returns false when the pager should close
+    pub fn handle_key(&mut self, key: char) -> bool {
+        if self.entering_pattern {
+            match key {
+                '\n' => {
+                    self.entering_pattern = false;
+                    let mut pattern = SimpleString::new();
+                    pattern.push_str(self.pattern.as_str());
+                    self.search(pattern.as_str());
+                },
+                '\u{001B}' => {
+                    self.entering_pattern = false;
+                    self.pattern.clear();
+                },
+                '\u{0008}' => {
+                    self.pattern.pop();
+                },
+                c if !c.is_control() => self.pattern.push(c),
+                _ => {},
+            }
+            self.render();
+            return true;
+        }
+
+        match key {
+            'q' => return false,
+            // Like `more`, paging past the end closes the pager
+            ' ' | '\n' if self.at_end() => return false,
+            ' ' => self.scroll(PAGE_HEIGHT),
+            '\n' => self.scroll(1),
+            '/' => {
+                self.entering_pattern = true;
+                self.pattern.clear();
+            },
+            'n' => {
+                self.search_next();
+            },
+            _ => {},
+        }
+
+        self.render();
+        true
+    }
+
+    fn scroll(&mut self, lines: usize) {
+        let last_top = self.lines.len().saturating_sub(PAGE_HEIGHT);
+        self.top_line = (self.top_line + lines).min(last_top);
+        self.message = "";
+    }
+
+    /// Draw the current page, highlighting the match in inverse video
+    pub fn render(&self) {
+        let mut writer = WRITER.lock();
+
+        for row in 0..PAGE_HEIGHT {
+            let line = self.lines.get(self.top_line + row).copied().unwrap_or("");
+            let highlight = if self.match_line == Some(self.top_line + row) {
+                line.find(self.pattern.as_str())
+                    .map(|start| (start, start + self.pattern.len()))
+            } else {
+                None
+            };
+
+            for x in 0..BUFFER_WIDTH {
+                let c = line.as_bytes().get(x).copied().unwrap_or(b' ') as char;
+                match highlight {
+                    Some((start, end)) if x >= start && x < end => {
+                        writer.write_char_at(x, row, c, Color::Black, Color::LightGray);
+                    },
+                    _ => writer.write_char_at(x, row, c, Color::LightGray, Color::Black),
+                }
+            }
+        }
+
+        // Status line
+        let mut status = SimpleString::new();
+        if self.entering_pattern {
+            status.push('/');
+            status.push_str(self.pattern.as_str());
+        } else if !self.message.is_empty() {
+            status.push_str(self.message);
+        } else {
+            status.push_str("-- More -- (Space: page, Enter: line, /: search, n: next, q: quit)");
+        }
+
+        for x in 0..BUFFER_WIDTH {
+            let c = status.as_str().as_bytes().get(x).copied().unwrap_or(b' ') as char;
+            writer.write_char_at(x, PAGE_HEIGHT, c, Color::Black, Color::LightGray);
+        }
+    }
+}
+
+/// Find the first line at or after `start` containing `pattern`, wrapping
+/// around to the top of the buffer if nothing matches before the end
+fn find_line(lines: &[&str], pattern: &str, start: usize) -> Option<usize> {
+    let count = lines.len();
+    for offset in 0..count {
+        let index = (start + offset) % count;
+        if lines[index].contains(pattern) {
+            return Some(index);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "alpha\nbeta\ngamma\nalphabet\ndelta";
+
+    #[test_case]
+    fn search_finds_first_match_from_top() {
+        let mut pager = Pager::new(TEXT);
+        assert_eq!(pager.search("alpha"), SearchResult::Found(0));
+        assert_eq!(pager.match_line(), Some(0));
+    }
+
+    #[test_case]
+    fn search_next_moves_to_following_match() {
+        let mut pager = Pager::new(TEXT);
+        pager.search("alpha");
+        assert_eq!(pager.search_next(), SearchResult::Found(3));
+        assert_eq!(pager.top_line(), 3);
+    }
+
+    #[test_case]
+    fn search_next_wraps_at_end() {
+        let mut pager = Pager::new(TEXT);
+        pager.search("alpha");
+        pager.search_next();
+        assert_eq!(pager.search_next(), SearchResult::Wrapped(0));
+    }
+
+    #[test_case]
+    fn paging_past_the_end_closes() {
+        let mut pager = Pager::new(TEXT);
+        assert!(pager.at_end());
+        assert!(!pager.handle_key(' '));
+    }
+
+    #[test_case]
+    fn search_missing_pattern_keeps_position() {
+        let mut pager = Pager::new(TEXT);
+        assert_eq!(pager.search("omega"), SearchResult::NotFound);
+        assert_eq!(pager.top_line(), 0);
+        assert_eq!(pager.match_line(), None);
+    }
+}
@@ -0,0 +1,145 @@
+// src/ui/screensaver.rs
+// Idle screensaver for ScreammOS
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use spin::Mutex;
+use crate::vga_buffer::{Color, SavedRegion, WRITER, BUFFER_WIDTH, BUFFER_HEIGHT};
+use crate::log_info;
+
+const DEFAULT_TIMEOUT_SECONDS: u64 = 300;
+
+// Set by the timer interrupt once the timeout passes. Blanking allocates
+// the saved screen and logs, so the main loop does that part.
+static ACTIVATE_PENDING: AtomicBool = AtomicBool::new(false);
+
+pub struct Screensaver {
+    timeout_ms: u64,
+    last_input_tick: u64,
+    saved_screen: Option<SavedRegion>,
+}
+
+impl Screensaver {
+    pub const fn new() -> Self {
+        Self {
+            timeout_ms: DEFAULT_TIMEOUT_SECONDS * 1000,
+            last_input_tick: 0,
+            saved_screen: None,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.saved_screen.is_some()
+    }
+
+    /// Set the idle timeout in seconds, 0 disables the screensaver
+    pub fn set_timeout_seconds(&mut self, seconds: u64) {
+        self.timeout_ms = seconds * 1000;
+    }
+
+    pub fn timeout_seconds(&self) -> u64 {
+        self.timeout_ms / 1000
+    }
+
+    // Whether the system has been idle for the whole timeout at tick `now`
+    fn idle_at(&self, now: u64) -> bool {
+        idle_timeout_elapsed(self.last_input_tick, now, crate::interrupts::ticks_for_ms(self.timeout_ms))
+    }
+
+    // Save the screen and blank it
+    fn activate(&mut self) {
+        // Try again on a later tick if someone else is drawing
+        if let Some(mut writer) = WRITER.try_lock() {
+            self.saved_screen = Some(writer.save_region(0, 0, BUFFER_WIDTH, BUFFER_HEIGHT));
+            for y in 0..BUFFER_HEIGHT {
+                for x in 0..BUFFER_WIDTH {
+                    writer.write_char_at(x, y, ' ', Color::Black, Color::Black);
+                }
+            }
+        }
+    }
+
+    // Put back exactly what was on screen before blanking
+    fn deactivate(&mut self) {
+        if let Some(saved) = self.saved_screen.take() {
+            WRITER.lock().restore_region(&saved);
+        }
+    }
+}
+
+pub static SCREENSAVER: Mutex<Screensaver> = Mutex::new(Screensaver::new());
+
+/// Returns true once `timeout` ticks have passed since the last input.
+/// A timeout of 0 means the screensaver is disabled.
+pub fn idle_timeout_elapsed(last_input_tick: u64, now: u64, timeout: u64) -> bool {
+    timeout != 0 && now.saturating_sub(last_input_tick) >= timeout
+}
+
+/// Check the idle time, called from the timer interrupt
+pub fn on_timer_tick() {
+    let now = crate::interrupts::ticks();
+
+    if let Some(saver) = SCREENSAVER.try_lock() {
+        if !saver.is_active() && saver.idle_at(now) {
+            ACTIVATE_PENDING.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Blank the screen if the timer found the system idle, called from
+/// the main loop
+pub fn process_idle() {
+    if !ACTIVATE_PENDING.swap(false, Ordering::Relaxed) {
+        return;
+    }
+
+    let mut saver = SCREENSAVER.lock();
+    // A key may have come in since the tick that set the flag
+    if saver.is_active() || !saver.idle_at(crate::interrupts::ticks()) {
+        return;
+    }
+    saver.activate();
+    if saver.is_active() {
+        drop(saver);
+        log_info!("Screensaver activated");
+    }
+}
+
+/// Record a keypress. Returns true if the key woke the screen up and
+/// should not be passed on.
+pub fn notify_input() -> bool {
+    let mut saver = SCREENSAVER.lock();
+    saver.last_input_tick = crate::interrupts::ticks();
+
+    if saver.is_active() {
+        saver.deactivate();
+        true
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn idle_timeout_not_reached() {
+        assert!(!idle_timeout_elapsed(100, 150, 60));
+    }
+
+    #[test_case]
+    fn idle_timeout_reached_exactly() {
+        assert!(idle_timeout_elapsed(100, 160, 60));
+        assert!(idle_timeout_elapsed(100, 500, 60));
+    }
+
+    #[test_case]
+    fn idle_timeout_disabled_when_zero() {
+        assert!(!idle_timeout_elapsed(0, 1_000_000, 0));
+    }
+
+    #[test_case]
+    fn idle_timeout_ignores_input_after_now() {
+        assert!(!idle_timeout_elapsed(200, 100, 60));
+    }
+}
@@ -1,25 +1,40 @@
 // src/ui/splash_screen.rs
 // Retro splash screen for ScreammOS
 
-use crate::vga_buffer::{Color, WRITER};
-use crate::{print, println};
+use crate::vga_buffer::{Color, WRITER, BUFFER_WIDTH, BUFFER_HEIGHT};
+use crate::ui::{draw_text_at, draw_text_centered, fill_rect, Rect};
 use spin::Mutex;
 use lazy_static::lazy_static;
-use alloc::vec::Vec;
-use crate::string_ext::StringExt;
-
-const SPLASH_ART: &str = r#"
-    ███████╗ ██████╗██████╗ ███████╗ █████╗ ███╗   ███╗███╗   ███╗ ██████╗ ███████╗
-    ██╔════╝██╔════╝██╔══██╗██╔════╝██╔══██╗████╗ ████║████╗ ████║██╔═══██╗██╔════╝
-    ███████╗██║     ██████╔╝█████╗  ███████║██╔████╔██║██╔████╔██║██║   ██║███████╗
-    ╚════██║██║     ██╔══██╗██╔══╝  ██╔══██║██║╚██╔╝██║██║╚██╔╝██║██║   ██║╚════██║
-    ███████║╚██████╗██║  ██║███████╗██║  ██║██║ ╚═╝ ██║██║ ╚═╝ ██║╚██████╔╝███████║
-    ╚══════╝ ╚═════╝╚═╝  ╚═╝╚══════╝╚═╝  ╚═╝╚═╝     ╚═╝╚═╝     ╚═╝ ╚═════╝ ╚══════╝
-"#;
+
+// The logo, one entry per screen row
+const SPLASH_ART: &[&str] = &[
+    "███████╗ ██████╗██████╗ ███████╗ █████╗ ███╗   ███╗███╗   ███╗ ██████╗ ███████╗",
+    "██╔════╝██╔════╝██╔══██╗██╔════╝██╔══██╗████╗ ████║████╗ ████║██╔═══██╗██╔════╝",
+    "███████╗██║     ██████╔╝█████╗  ███████║██╔████╔██║██╔████╔██║██║   ██║███████╗",
+    "╚════██║██║     ██╔══██╗██╔══╝  ██╔══██║██║╚██╔╝██║██║╚██╔╝██║██║   ██║╚════██║",
+    "███████║╚██████╗██║  ██║███████╗██║  ██║██║ ╚═╝ ██║██║ ╚═╝ ██║╚██████╔╝███████║",
+    "╚══════╝ ╚═════╝╚═╝  ╚═╝╚══════╝╚═╝  ╚═╝╚═╝     ╚═╝╚═╝     ╚═╝ ╚═════╝ ╚══════╝",
+];
+
+// Frames advance on every timer tick (~18.2Hz)
+pub const MIN_FRAMES: u32 = 36;
+const FRAMES_PER_DOT: u32 = 5;
+
+// Padded to one width so a shorter frame covers a longer one
+const LOADING_FRAMES: [&str; 4] = ["Loading   ", "Loading.  ", "Loading.. ", "Loading..."];
+
+const PROGRESS_WIDTH: usize = 40;
+const PROGRESS_ROW: usize = 18;
+
+/// Number of filled cells in a progress bar `width` cells wide
+pub fn progress_cells(percent: u8, width: usize) -> usize {
+    width * percent.min(100) as usize / 100
+}
 
 pub struct SplashScreen {
     visible: bool,
     frame: u32,
+    progress: u8,
 }
 
 impl SplashScreen {
@@ -27,12 +42,14 @@ impl SplashScreen {
         Self {
             visible: false,
             frame: 0,
+            progress: 0,
         }
     }
 
     pub fn show(&mut self) {
         self.visible = true;
         self.frame = 0;
+        self.progress = 0;
         self.render();
     }
 
@@ -42,43 +59,124 @@ impl SplashScreen {
         writer.clear_screen();
     }
 
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Frames drawn since the splash was shown
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Fill the progress bar to `percent`, capped at 100
+    pub fn set_progress(&mut self, percent: u8) {
+        self.progress = percent.min(100);
+        if self.visible {
+            self.render();
+        }
+    }
+
+    /// Advance the loading animation. Only the dots are redrawn, so this
+    /// is cheap enough for the timer interrupt.
     pub fn update(&mut self) {
         if self.visible {
             self.frame += 1;
-            self.render();
+            self.render_loading();
         }
     }
 
+    /// Keep animating until `max_frames` frames have been shown since
+    /// `show`, or return early with true once a key is pressed. The key is
+    /// consumed so it doesn't reach the shell.
+    pub fn wait_or_skip(&mut self, max_frames: u32) -> bool {
+        // The timer can't reach us while we hold the lock, so count
+        // its ticks and animate here instead
+        let mut last_tick = crate::interrupts::ticks();
+        while self.visible && self.frame < max_frames {
+            if crate::keyboard::read_key().is_some() {
+                return true;
+            }
+            x86_64::instructions::hlt();
+            let now = crate::interrupts::ticks();
+            if now != last_tick {
+                last_tick = now;
+                self.update();
+            }
+        }
+        false
+    }
+
+    // Redraw everything, which also covers up any boot messages printed
+    // since the last frame
     fn render(&self) {
-        let mut writer = WRITER.lock();
-        writer.clear_screen();
-        
-        // Set retro color scheme
-        writer.set_color(Color::LightCyan, Color::Black);
-        
-        // Calculate center position
-        let lines: Vec<&str> = SPLASH_ART.lines().filter(|line| !line.is_empty()).collect();
-        let start_y = (25 - lines.len()) / 2;
-        
-        // Draw ASCII art
-        for (i, line) in lines.iter().enumerate() {
-            let x = (80 - line.len()) / 2;
-            writer.set_position(x, start_y + i);
-            print!("{}", line);
+        {
+            // Skip the frame if someone is drawing
+            let mut writer = match WRITER.try_lock() {
+                Some(writer) => writer,
+                None => return,
+            };
+            writer.clear_screen();
+            
+            // Calculate center position
+            let screen = Rect {
+                x: 0,
+                y: (BUFFER_HEIGHT - SPLASH_ART.len()) / 2 - 2,
+                width: BUFFER_WIDTH,
+                height: SPLASH_ART.len(),
+            };
+            
+            // Draw ASCII art in a retro color scheme
+            for (i, line) in SPLASH_ART.iter().enumerate() {
+                draw_text_centered(&mut *writer, screen, i, line, Color::LightCyan, Color::Black);
+            }
+            
+            // Progress bar beneath the logo
+            let left = (BUFFER_WIDTH - PROGRESS_WIDTH - 2) / 2;
+            let filled = progress_cells(self.progress, PROGRESS_WIDTH);
+            let bar = |x, width| Rect { x: left + 1 + x, y: PROGRESS_ROW, width, height: 1 };
+            draw_text_at(&mut *writer, left, PROGRESS_ROW, "[", Color::LightGray, Color::Black);
+            fill_rect(&mut *writer, bar(0, filled), '█', Color::LightCyan, Color::Black);
+            fill_rect(&mut *writer, bar(filled, PROGRESS_WIDTH - filled), '░', Color::DarkGray, Color::Black);
+            draw_text_at(&mut *writer, left + 1 + PROGRESS_WIDTH, PROGRESS_ROW, "]", Color::LightGray, Color::Black);
+            
+            // Draw version info
+            draw_text_at(&mut *writer, 35, 20, "Version 1.0.0", Color::LightGray, Color::Black);
         }
-        
-        // Draw version info
-        writer.set_color(Color::LightGray, Color::Black);
-        writer.set_position(35, 20);
-        print!("Version 1.0.0");
-        
-        // Draw loading animation
-        let dots = (self.frame / 10) % 4;
-        writer.set_position(35, 21);
-        print!("Loading{}", ".".repeat(dots as usize));
+        self.render_loading();
+    }
+
+    // The loading dots, the only part that changes between frames.
+    // Called from the timer interrupt, so it must not allocate.
+    fn render_loading(&self) {
+        let mut writer = match WRITER.try_lock() {
+            Some(writer) => writer,
+            None => return,
+        };
+        let dots = (self.frame / FRAMES_PER_DOT) as usize % LOADING_FRAMES.len();
+        draw_text_at(&mut *writer, 35, 21, LOADING_FRAMES[dots], Color::LightGray, Color::Black);
     }
 }
 
 lazy_static! {
     pub static ref SPLASH_SCREEN: Mutex<SplashScreen> = Mutex::new(SplashScreen::new());
-} 
\ No newline at end of file
+}
+
+/// Advance the loading animation, called from the timer interrupt
+pub fn on_timer_tick() {
+    if let Some(mut splash) = SPLASH_SCREEN.try_lock() {
+        splash.update();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn progress_fills_proportionally() {
+        assert_eq!(progress_cells(0, 40), 0);
+        assert_eq!(progress_cells(50, 40), 20);
+        assert_eq!(progress_cells(100, 40), 40);
+        assert_eq!(progress_cells(250, 40), 40);
+    }
+}
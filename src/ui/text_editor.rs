@@ -2,10 +2,20 @@
 // Text editor for ScreammOS
 
 use crate::vga_buffer::{BUFFER_HEIGHT, BUFFER_WIDTH, WRITER, Color};
-use crate::ui::{Rect, BorderStyle, draw_box};
-use crate::simple_fs::{FILESYSTEM, SimpleString};
+use crate::ui::{Rect, BorderStyle, Component, draw_box};
+use crate::ui::window_manager::WINDOW_MANAGER;
+use crate::keyboard::modifiers;
+use pc_keyboard::{DecodedKey, KeyCode};
+use crate::simple_fs::{FILESYSTEM, SimpleString, MAX_FILENAME_LENGTH};
+use crate::error_handler::report_filesystem_error;
+use crate::{format, format_into};
 use spin::Mutex;
+use core::cell::RefCell;
 use core::fmt::Write;
+use alloc::string::String;
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
 
 const EDITOR_WIDTH: usize = 60;
 const EDITOR_HEIGHT: usize = 20;
@@ -13,6 +23,93 @@ const EDITOR_TEXT_HEIGHT: usize = EDITOR_HEIGHT - 6; // Space for title and stat
 
 const MAX_LINES: usize = 100; // Max number of lines we can edit
 const MAX_LINE_LENGTH: usize = 80; // Max length per line
+const MAX_UNDO_DEPTH: usize = 50; // Oldest undo steps are dropped past this
+const GUTTER_WIDTH: usize = 5; // 4-char line number column and a space
+const INDENT_WIDTH: usize = 4; // Spaces per indent level
+const MAX_BUFFERS: usize = 8; // Open files at the same time
+
+// Colors used by the code highlighter
+const KEYWORD_COLOR: Color = Color::Yellow;
+const STRING_COLOR: Color = Color::LightGreen;
+const COMMENT_COLOR: Color = Color::DarkGray;
+
+// Rust and C keywords the code highlighter knows
+const KEYWORDS: &[&str] = &[
+    "as", "break", "case", "char", "const", "continue", "default", "define", "do",
+    "else", "enum", "extern", "false", "fn", "for", "if", "impl", "include", "int",
+    "let", "loop", "match", "mod", "mut", "pub", "return", "self", "sizeof", "static",
+    "struct", "switch", "trait", "true", "typedef", "unsafe", "unsigned", "use",
+    "void", "where", "while",
+];
+
+/// Colors the characters of a line. `out` starts out in the text color,
+/// the highlighter only overwrites what it wants to color.
+pub type Highlighter = fn(line: &str, out: &mut [Color; MAX_LINE_LENGTH]);
+
+// Text shared by cut/copy/paste
+static CLIPBOARD: Mutex<SimpleString> = Mutex::new(SimpleString::new());
+
+/// A snapshot of the document taken before an edit
+struct UndoEntry {
+    text: String,
+    cursor_x: usize,
+    cursor_y: usize,
+}
+
+/// The kind of the last edit, used to coalesce typing into one undo step
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EditKind {
+    None,
+    Insert,
+    Other,
+}
+
+/// How to finish closing an editor with unsaved changes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CloseAction {
+    Save,
+    Discard,
+    Cancel,
+}
+
+/// One open file. The active buffer is edited in the editor's own fields
+/// and its slot holds an empty placeholder; switching swaps them.
+struct EditorBuffer {
+    filename: SimpleString,
+    content: [SimpleString; MAX_LINES],
+    line_count: usize,
+    cursor_x: usize,
+    cursor_y: usize,
+    scroll_offset: usize,
+    h_scroll_offset: usize,
+    modified: bool,
+    undo_stack: VecDeque<UndoEntry>,
+    redo_stack: VecDeque<UndoEntry>,
+    last_edit: EditKind,
+    read_only: bool,
+    highlighter: Highlighter,
+}
+
+impl EditorBuffer {
+    fn new() -> Self {
+        const EMPTY_LINE: SimpleString = SimpleString::new();
+        Self {
+            filename: SimpleString::new(),
+            content: [EMPTY_LINE; MAX_LINES],
+            line_count: 1,
+            cursor_x: 0,
+            cursor_y: 0,
+            scroll_offset: 0,
+            h_scroll_offset: 0,
+            modified: false,
+            undo_stack: VecDeque::new(),
+            redo_stack: VecDeque::new(),
+            last_edit: EditKind::None,
+            read_only: false,
+            highlighter: no_highlight,
+        }
+    }
+}
 
 /// A simple text editor
 pub struct TextEditor {
@@ -22,9 +119,32 @@ pub struct TextEditor {
     cursor_x: usize,
     cursor_y: usize,
     scroll_offset: usize,
+    h_scroll_offset: usize, // First visible column of every line
     rect: Rect,
     pub visible: bool,
     modified: bool,
+    undo_stack: VecDeque<UndoEntry>,
+    redo_stack: VecDeque<UndoEntry>,
+    last_edit: EditKind,
+    selection_anchor: Option<usize>, // Line where a Shift+Arrow selection started
+    search_term: SimpleString,
+    search_prompt: bool,                 // True while typing a term after Ctrl+F
+    search_match: Option<(usize, usize)>, // Line and column of the highlighted match
+    goto_prompt: bool,                   // True while typing a line number after Ctrl+L
+    goto_input: SimpleString,
+    save_prompt: bool,                   // True while typing a name after Ctrl+Shift+S
+    save_input: SimpleString,
+    status_message: &'static str,
+    // Reused by every render for the cursor position
+    position: RefCell<SimpleString>,
+    show_line_numbers: bool,
+    confirm_close: bool, // Waiting for S)ave D)iscard C)ancel after Esc
+    read_only: bool,
+    highlighter: Highlighter, // Picked from the file extension
+    auto_indent: bool,         // New lines copy the indent of the line above
+    bracket_auto_indent: bool, // ...plus one level after a `{`
+    buffers: Vec<EditorBuffer>, // Every open file, see EditorBuffer
+    active: usize,              // Index of the buffer being edited
 }
 
 // Helper function for formatting
@@ -46,77 +166,6 @@ fn format_title(prefix: &str, name: &str, modified: bool) -> SimpleString {
     s
 }
 
-// Helper function for status bar
-fn format_status(row: usize, total_rows: usize, col: usize) -> SimpleString {
-    let mut s = SimpleString::new();
-    s.push_str("Row: ");
-    // Convert row to string
-    let mut row_str = SimpleString::new();
-    let row_plus_one = row + 1;
-    let mut digits = [0; 10];
-    let mut i = 0;
-    let mut num = row_plus_one;
-    while num > 0 {
-        digits[i] = (num % 10) as u8;
-        num /= 10;
-        i += 1;
-    }
-    if i == 0 {
-        row_str.push('0');
-    } else {
-        for j in (0..i).rev() {
-            row_str.push((digits[j] + b'0') as char);
-        }
-    }
-    s.push_str(row_str.as_str());
-    s.push_str("/");
-    
-    // Convert total_rows to string using same technique
-    let mut total_str = SimpleString::new();
-    let mut digits = [0; 10];
-    let mut i = 0;
-    let mut num = total_rows;
-    while num > 0 {
-        digits[i] = (num % 10) as u8;
-        num /= 10;
-        i += 1;
-    }
-    if i == 0 {
-        total_str.push('0');
-    } else {
-        for j in (0..i).rev() {
-            total_str.push((digits[j] + b'0') as char);
-        }
-    }
-    s.push_str(total_str.as_str());
-    
-    s.push_str(" Col: ");
-    
-    // Convert col to string
-    let mut col_str = SimpleString::new();
-    let col_plus_one = col + 1;
-    let mut digits = [0; 10];
-    let mut i = 0;
-    let mut num = col_plus_one;
-    while num > 0 {
-        digits[i] = (num % 10) as u8;
-        num /= 10;
-        i += 1;
-    }
-    if i == 0 {
-        col_str.push('0');
-    } else {
-        for j in (0..i).rev() {
-            col_str.push((digits[j] + b'0') as char);
-        }
-    }
-    s.push_str(col_str.as_str());
-    
-    // Add shortcuts info
-    s.push_str(" | Esc: Close | Ctrl+S: Save");
-    s
-}
-
 impl TextEditor {
     /// Create a new text editor
     pub fn new() -> Self {
@@ -125,7 +174,8 @@ impl TextEditor {
         let y = (BUFFER_HEIGHT - EDITOR_HEIGHT) / 2;
         
         // Initialize all lines as empty strings
-        let content = [SimpleString::new(); MAX_LINES];
+        const EMPTY_LINE: SimpleString = SimpleString::new();
+        let content = [EMPTY_LINE; MAX_LINES];
         
         Self {
             filename: SimpleString::new(),
@@ -134,6 +184,7 @@ impl TextEditor {
             cursor_x: 0,
             cursor_y: 0,
             scroll_offset: 0,
+            h_scroll_offset: 0,
             rect: Rect {
                 x,
                 y,
@@ -142,56 +193,58 @@ impl TextEditor {
             },
             visible: false,
             modified: false,
+            undo_stack: VecDeque::new(),
+            redo_stack: VecDeque::new(),
+            last_edit: EditKind::None,
+            selection_anchor: None,
+            search_term: SimpleString::new(),
+            search_prompt: false,
+            search_match: None,
+            goto_prompt: false,
+            goto_input: SimpleString::new(),
+            save_prompt: false,
+            save_input: SimpleString::new(),
+            status_message: "",
+            position: RefCell::new(SimpleString::new()),
+            show_line_numbers: false,
+            confirm_close: false,
+            read_only: false,
+            highlighter: no_highlight,
+            auto_indent: true,
+            bracket_auto_indent: true,
+            buffers: vec![EditorBuffer::new()],
+            active: 0,
         }
     }
     
-    /// Open a file for editing
+    /// Open a file for editing. A file that is already open is switched
+    /// to instead of being loaded again.
     pub fn open_file(&mut self, filename: &str) -> bool {
+        if let Some(index) = self.find_buffer(filename) {
+            self.switch_to(index);
+            return true;
+        }
+        if !self.make_room() {
+            return false;
+        }
+        
         let fs = FILESYSTEM.lock();
+        self.read_only = false;
+        self.highlighter = highlighter_for(filename);
         
         match fs.read_file(filename) {
             Ok(content) => {
-                // Clear existing content
-                for i in 0..MAX_LINES {
-                    self.content[i] = SimpleString::new();
-                }
-                
                 // Set the filename
                 self.filename = SimpleString::new();
                 self.filename.push_str(filename);
                 
-                // Split content into lines
-                let mut line_index = 0;
-                let mut current_line = SimpleString::new();
-                
-                for c in content.chars() {
-                    if c == '\n' {
-                        // Save the line and move to the next
-                        self.content[line_index] = current_line;
-                        line_index += 1;
-                        
-                        if line_index >= MAX_LINES {
-                            break; // Too many lines
-                        }
-                        
-                        current_line = SimpleString::new();
-                    } else {
-                        // Add character to current line
-                        current_line.push(c);
-                    }
-                }
-                
-                // Handle the last line if it doesn't end with a line break
-                if current_line.len() > 0 && line_index < MAX_LINES {
-                    self.content[line_index] = current_line;
-                    line_index += 1;
-                }
-                
-                self.line_count = line_index;
+                self.load_text(content);
                 self.cursor_x = 0;
                 self.cursor_y = 0;
                 self.scroll_offset = 0;
+                self.h_scroll_offset = 0;
                 self.modified = false;
+                self.clear_history();
                 
                 true
             },
@@ -209,21 +262,55 @@ impl TextEditor {
                 self.cursor_x = 0;
                 self.cursor_y = 0;
                 self.scroll_offset = 0;
+                self.h_scroll_offset = 0;
                 self.modified = true; // Mark as modified since it's new
+                self.clear_history();
                 
                 true
             }
         }
     }
     
-    /// Save the file
-    pub fn save_file(&mut self) -> bool {
-        if self.filename.len() == 0 {
-            return false; // No filename
+    /// Replace the buffer with `text`, split into lines
+    fn load_text(&mut self, text: &str) {
+        // Clear existing content
+        for i in 0..MAX_LINES {
+            self.content[i] = SimpleString::new();
+        }
+        
+        // Split content into lines
+        let mut line_index = 0;
+        let mut current_line = SimpleString::new();
+        
+        for c in text.chars() {
+            if c == '\n' {
+                // Save the line and move to the next
+                self.content[line_index] = current_line;
+                line_index += 1;
+                
+                if line_index >= MAX_LINES {
+                    break; // Too many lines
+                }
+                
+                current_line = SimpleString::new();
+            } else {
+                // Add character to current line
+                current_line.push(c);
+            }
+        }
+        
+        // Handle the last line if it doesn't end with a line break
+        if current_line.len() > 0 && line_index < MAX_LINES {
+            self.content[line_index] = current_line;
+            line_index += 1;
         }
         
-        // Build up the content as a string
-        let mut content = SimpleString::new();
+        self.line_count = line_index;
+    }
+    
+    /// The whole buffer joined with line breaks
+    fn text(&self) -> String {
+        let mut content = String::new();
         
         for i in 0..self.line_count {
             content.push_str(self.content[i].as_str());
@@ -232,38 +319,416 @@ impl TextEditor {
             }
         }
         
-        // Save the file
-        let mut fs = FILESYSTEM.lock();
-        match fs.create_file(self.filename.as_str(), content.as_str()) {
-            Ok(_) => {
-                self.modified = false;
-                true
-            },
-            Err(_) => false
+        content
+    }
+    
+    /// Save the file
+    pub fn save_file(&mut self) -> bool {
+        if self.filename.len() == 0 {
+            // An unnamed buffer asks for a name first
+            self.start_save_as();
+            return false;
+        }
+        
+        let name = self.filename.clone();
+        self.write_to(name.as_str())
+    }
+    
+    // Save the file under `name`, creating it if it doesn't exist yet
+    fn write_to(&mut self, name: &str) -> bool {
+        let content = self.text();
+        
+        let saved = FILESYSTEM.lock().write_file(name, &content);
+        if saved {
+            self.modified = false;
+        } else {
+            self.status_message = "Save failed";
+            report_filesystem_error(&format!("Could not save {}", name)).ok();
+        }
+        saved
+    }
+    
+    /// Save the buffer under a new name, which becomes the editor's file
+    pub fn save_as(&mut self, name: &str) -> bool {
+        let name = name.trim();
+        // The filesystem keeps a name shorter than its buffer
+        if name.is_empty() || name.len() >= MAX_FILENAME_LENGTH {
+            self.status_message = "Invalid file name";
+            return false;
+        }
+        
+        // Keep the old name if the file can't be written
+        if !self.write_to(name) {
+            return false;
+        }
+        self.filename.clear();
+        self.filename.push_str(name);
+        self.highlighter = highlighter_for(name);
+        true
+    }
+    
+    /// Start editing an empty buffer with no file name
+    pub fn new_file(&mut self) {
+        if !self.make_room() {
+            return;
+        }
+        self.filename.clear();
+        self.read_only = false;
+        self.highlighter = no_highlight;
+        self.load_text("");
+        self.line_count = 1; // An empty line
+        self.cursor_x = 0;
+        self.cursor_y = 0;
+        self.scroll_offset = 0;
+        self.h_scroll_offset = 0;
+        self.modified = false;
+        self.clear_history();
+    }
+    
+    /// Number of open buffers
+    pub fn buffer_count(&self) -> usize {
+        self.buffers.len()
+    }
+    
+    // Index of the buffer editing `filename`
+    fn find_buffer(&self, filename: &str) -> Option<usize> {
+        if self.filename.as_str() == filename {
+            return Some(self.active);
+        }
+        self.buffers.iter().position(|buffer| !buffer.filename.is_empty() && buffer.filename.as_str() == filename)
+    }
+    
+    // An empty, unnamed and unchanged buffer is reused instead of kept
+    fn is_pristine(&self) -> bool {
+        self.filename.is_empty() && !self.modified && self.line_count <= 1 && self.content[0].is_empty()
+    }
+    
+    // Make the active buffer free for a new file, opening another buffer
+    // unless the current one is pristine. False if all buffers are in use.
+    fn make_room(&mut self) -> bool {
+        if self.is_pristine() {
+            return true;
+        }
+        if self.buffers.len() >= MAX_BUFFERS {
+            self.status_message = "Too many open files";
+            self.render();
+            return false;
+        }
+        
+        // Park the active buffer and take the new slot's placeholder
+        self.swap_buffer(self.active);
+        self.buffers.push(EditorBuffer::new());
+        self.active = self.buffers.len() - 1;
+        self.swap_buffer(self.active);
+        self.reset_view();
+        true
+    }
+    
+    // Exchange the editor's document state with the buffer in slot `index`
+    fn swap_buffer(&mut self, index: usize) {
+        let buffer = &mut self.buffers[index];
+        core::mem::swap(&mut self.filename, &mut buffer.filename);
+        core::mem::swap(&mut self.content, &mut buffer.content);
+        core::mem::swap(&mut self.line_count, &mut buffer.line_count);
+        core::mem::swap(&mut self.cursor_x, &mut buffer.cursor_x);
+        core::mem::swap(&mut self.cursor_y, &mut buffer.cursor_y);
+        core::mem::swap(&mut self.scroll_offset, &mut buffer.scroll_offset);
+        core::mem::swap(&mut self.h_scroll_offset, &mut buffer.h_scroll_offset);
+        core::mem::swap(&mut self.modified, &mut buffer.modified);
+        core::mem::swap(&mut self.undo_stack, &mut buffer.undo_stack);
+        core::mem::swap(&mut self.redo_stack, &mut buffer.redo_stack);
+        core::mem::swap(&mut self.last_edit, &mut buffer.last_edit);
+        core::mem::swap(&mut self.read_only, &mut buffer.read_only);
+        core::mem::swap(&mut self.highlighter, &mut buffer.highlighter);
+    }
+    
+    // Forget what belonged to the previous buffer's view
+    fn reset_view(&mut self) {
+        self.selection_anchor = None;
+        self.search_match = None;
+        self.confirm_close = false;
+        self.status_message = "";
+    }
+    
+    /// Make buffer `index` the one being edited
+    pub fn switch_to(&mut self, index: usize) {
+        if index == self.active || index >= self.buffers.len() {
+            return;
+        }
+        self.swap_buffer(self.active);
+        self.active = index;
+        self.swap_buffer(self.active);
+        self.reset_view();
+        self.render();
+    }
+    
+    /// Switch to the next buffer, wrapping around (Ctrl+Tab)
+    pub fn next_buffer(&mut self) {
+        self.switch_to((self.active + 1) % self.buffers.len());
+    }
+    
+    /// Switch to the previous buffer, wrapping around (Ctrl+Shift+Tab)
+    pub fn prev_buffer(&mut self) {
+        let count = self.buffers.len();
+        self.switch_to((self.active + count - 1) % count);
+    }
+    
+    // Drop the active buffer and edit its neighbour. Closing the last one
+    // hides the editor and keeps its contents, as before.
+    fn close_buffer(&mut self) {
+        if self.buffers.len() == 1 {
+            self.hide();
+            return;
         }
+        
+        self.buffers.remove(self.active);
+        self.active = self.active.min(self.buffers.len() - 1);
+        // The closed document ends up in the slot, which becomes the placeholder
+        self.swap_buffer(self.active);
+        self.buffers[self.active] = EditorBuffer::new();
+        self.reset_view();
+        self.render();
     }
     
     /// Show the editor
     pub fn show(&mut self) {
         self.visible = true;
+        // Block cursor while editing
+        WRITER.lock().enable_cursor(0, 15);
         self.render();
     }
     
     /// Hide the editor
     pub fn hide(&mut self) {
         self.visible = false;
+        self.confirm_close = false;
+        // Back to the command line's underline cursor
+        WRITER.lock().enable_cursor(14, 15);
+    }
+    
+    /// Close the active buffer (Esc), asking first if there are unsaved
+    /// changes. The editor hides when the last buffer closes.
+    pub fn close(&mut self) {
+        if self.modified && !self.read_only {
+            self.confirm_close = true;
+            self.render();
+        } else {
+            self.close_buffer();
+        }
+    }
+    
+    /// Block or allow changes to the buffer
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+        self.render();
+    }
+    
+    /// True while a prompt in the status bar is consuming keystrokes
+    pub fn is_modal_active(&self) -> bool {
+        self.search_prompt || self.goto_prompt || self.save_prompt || self.confirm_close
+    }
+    
+    /// Route a key to whichever status bar prompt is open
+    pub fn handle_modal_key(&mut self, c: char) {
+        if self.confirm_close {
+            self.handle_confirm_key(c);
+        } else if self.search_prompt {
+            self.handle_search_key(c);
+        } else if self.goto_prompt {
+            self.handle_goto_key(c);
+        } else if self.save_prompt {
+            self.handle_save_key(c);
+        }
+    }
+    
+    // Resolve the unsaved changes prompt, other keys are swallowed
+    fn handle_confirm_key(&mut self, c: char) {
+        match c {
+            's' | 'S' => self.close_with(CloseAction::Save),
+            'd' | 'D' => self.close_with(CloseAction::Discard),
+            'c' | 'C' | '\u{001B}' => self.close_with(CloseAction::Cancel),
+            _ => {},
+        }
+    }
+    
+    /// True when closing would lose edits
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.modified && !self.read_only
+    }
+    
+    /// Finish closing once the user has answered the unsaved-changes question
+    pub fn close_with(&mut self, action: CloseAction) {
+        self.confirm_close = false;
+        match action {
+            CloseAction::Save => {
+                if self.save_file() {
+                    self.close_buffer();
+                } else {
+                    // Shows the failure, or the name prompt for a new file
+                    self.render();
+                }
+            },
+            CloseAction::Discard => {
+                self.modified = false;
+                self.close_buffer();
+            },
+            CloseAction::Cancel => self.render(),
+        }
+    }
+    
+    fn snapshot(&self) -> UndoEntry {
+        UndoEntry {
+            text: self.text(),
+            cursor_x: self.cursor_x,
+            cursor_y: self.cursor_y,
+        }
+    }
+    
+    fn restore(&mut self, entry: UndoEntry) {
+        self.load_text(&entry.text);
+        if self.line_count == 0 {
+            self.line_count = 1;
+        }
+        self.cursor_y = entry.cursor_y.min(self.line_count - 1);
+        self.cursor_x = entry.cursor_x.min(self.content[self.cursor_y].len());
+        self.modified = true;
+    }
+    
+    fn clear_history(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.last_edit = EditKind::None;
+        self.selection_anchor = None;
+    }
+    
+    /// Record the state before an edit. Consecutive inserts share one step.
+    fn push_undo(&mut self, kind: EditKind) {
+        if kind == EditKind::Insert && self.last_edit == EditKind::Insert {
+            return;
+        }
+        
+        if self.undo_stack.len() >= MAX_UNDO_DEPTH {
+            self.undo_stack.pop_front();
+        }
+        let entry = self.snapshot();
+        self.undo_stack.push_back(entry);
+        self.redo_stack.clear();
+        self.last_edit = kind;
+    }
+    
+    /// Undo the last edit (Ctrl+Z)
+    pub fn undo(&mut self) {
+        if self.read_only {
+            return;
+        }
+        if let Some(entry) = self.undo_stack.pop_back() {
+            let current = self.snapshot();
+            self.redo_stack.push_back(current);
+            self.restore(entry);
+            self.last_edit = EditKind::None;
+            self.ensure_cursor_visible();
+            self.render();
+        }
+    }
+    
+    /// Redo the last undone edit (Ctrl+Y)
+    pub fn redo(&mut self) {
+        if self.read_only {
+            return;
+        }
+        if let Some(entry) = self.redo_stack.pop_back() {
+            let current = self.snapshot();
+            self.undo_stack.push_back(current);
+            self.restore(entry);
+            self.last_edit = EditKind::None;
+            self.ensure_cursor_visible();
+            self.render();
+        }
     }
     
     /// Insert a character at the cursor position
     pub fn insert_char(&mut self, c: char) {
-        if !self.visible {
+        if !self.visible || self.read_only {
             return;
         }
         
+        if c == '\n' {
+            self.push_undo(EditKind::Other);
+            let indent = self.indent_for_new_line();
+            let line_before = self.cursor_y;
+            self.insert_at_cursor(c);
+            // A full buffer refuses the newline, the indent would land
+            // in the middle of the current line instead
+            if self.cursor_y == line_before {
+                self.ensure_cursor_visible();
+                self.render();
+                return;
+            }
+            for ch in indent.as_str().chars() {
+                self.insert_at_cursor(ch);
+            }
+        } else {
+            self.push_undo(EditKind::Insert);
+            self.insert_at_cursor(c);
+        }
+        
+        self.ensure_cursor_visible();
+        self.render();
+    }
+    
+    /// Indent for a line split off at the cursor: the current line's
+    /// leading whitespace, one level deeper after a `{`
+    fn indent_for_new_line(&self) -> SimpleString {
+        let mut indent = SimpleString::new();
+        if !self.auto_indent {
+            return indent;
+        }
+        
+        let text = self.content[self.cursor_y].as_str();
+        let before = &text[..self.cursor_x.min(text.len())];
+        let width = before.len() - before.trim_start_matches(|c: char| c == ' ' || c == '\t').len();
+        indent.push_str(&before[..width]);
+        if self.bracket_auto_indent && before.trim_end().ends_with('{') {
+            indent.push_str(&" ".repeat(INDENT_WIDTH));
+        }
+        indent
+    }
+    
+    /// Characters a backspace removes: back to the previous indent level
+    /// inside leading spaces, otherwise one
+    fn backspace_width(&self) -> usize {
+        let text = self.content[self.cursor_y].as_str();
+        let before = &text[..self.cursor_x.min(text.len())];
+        if !self.auto_indent || !before.bytes().all(|b| b == b' ') {
+            return 1;
+        }
+        (self.cursor_x - 1) % INDENT_WIDTH + 1
+    }
+    
+    /// Turn copying the indent onto new lines on or off
+    pub fn set_auto_indent(&mut self, enabled: bool) {
+        self.auto_indent = enabled;
+    }
+    
+    /// Turn the extra indent level after a line ending in `{` on or off
+    pub fn set_bracket_auto_indent(&mut self, enabled: bool) {
+        self.bracket_auto_indent = enabled;
+    }
+    
+    // Insert a character without recording undo or redrawing
+    fn insert_at_cursor(&mut self, c: char) {
+        // An empty buffer still has the line the cursor is on
+        if self.cursor_y >= self.line_count && self.cursor_y < MAX_LINES {
+            self.line_count = self.cursor_y + 1;
+        }
+        
         if c == '\n' {
             // Handle line break, split the current line
-            if self.line_count < MAX_LINES {
-                // Make space for the new line
+            if self.line_count >= MAX_LINES {
+                self.status_message = "Line limit reached";
+            } else {
+                // Move the lines below the cursor down by one. The last one
+                // lands on index line_count, which is still below MAX_LINES.
                 for i in (self.cursor_y + 1..self.line_count).rev() {
                     self.content[i + 1] = self.content[i].clone();
                 }
@@ -329,28 +794,30 @@ impl TextEditor {
                 }
             }
         }
-        
-        self.ensure_cursor_visible();
-        self.render();
     }
     
     /// Handle backspace
     pub fn handle_backspace(&mut self) {
-        if !self.visible || (self.cursor_x == 0 && self.cursor_y == 0) {
+        if !self.visible || self.read_only || (self.cursor_x == 0 && self.cursor_y == 0) {
             return;
         }
         
+        self.push_undo(EditKind::Other);
+        
         if self.cursor_x > 0 {
             // Delete a character on the current line
             let current_line_index = self.cursor_y;
             let current_text = self.content[current_line_index].as_str();
             
-            // Create a new string without the character at cursor-1
+            // A whole indent level goes at once in leading spaces
+            let count = self.backspace_width();
+            
+            // Create a new string without the characters before the cursor
             let mut new_content = SimpleString::new();
             
-            // Copy all characters except the one to be deleted
+            // Copy all characters except the ones to be deleted
             for i in 0..current_text.len() {
-                if i != self.cursor_x - 1 {
+                if i + count < self.cursor_x || i >= self.cursor_x {
                     new_content.push(current_text.chars().nth(i).unwrap_or(' '));
                 }
             }
@@ -359,7 +826,7 @@ impl TextEditor {
             self.content[current_line_index] = new_content;
             
             // Move cursor back
-            self.cursor_x -= 1;
+            self.cursor_x -= count;
             self.modified = true;
         } else if self.cursor_y > 0 {
             // We are at the beginning of a line, merge with the previous line
@@ -404,10 +871,17 @@ impl TextEditor {
     
     /// Handle deletion (delete key)
     pub fn handle_delete(&mut self) {
-        if !self.visible {
+        if !self.visible || self.read_only {
             return;
         }
         
+        // Nothing to delete at the very end of the buffer
+        let at_end = self.cursor_y + 1 >= self.line_count
+            && self.cursor_x >= self.content[self.cursor_y].len();
+        if !at_end {
+            self.push_undo(EditKind::Other);
+        }
+        
         if self.cursor_y < self.line_count {
             let current_line_index = self.cursor_y;
             let current_text = self.content[current_line_index].as_str();
@@ -460,44 +934,334 @@ impl TextEditor {
         self.render();
     }
     
-    /// Navigate up
-    pub fn move_up(&mut self) {
-        if self.cursor_y > 0 {
-            self.cursor_y -= 1;
-            
-            // Adjust X position if it's outside the line
-            let line_len = self.content[self.cursor_y].len();
-            if self.cursor_x > line_len {
-                self.cursor_x = line_len;
-            }
-            
-            self.ensure_cursor_visible();
-            self.render();
+    /// First and last selected line, or the current line with no selection
+    fn selected_lines(&self) -> (usize, usize) {
+        match self.selection_anchor {
+            Some(anchor) => (anchor.min(self.cursor_y), anchor.max(self.cursor_y)),
+            None => (self.cursor_y, self.cursor_y),
         }
     }
     
-    /// Navigate down
-    pub fn move_down(&mut self) {
-        if self.cursor_y < self.line_count - 1 {
-            self.cursor_y += 1;
-            
-            // Adjust X position if it's outside the line
-            let line_len = self.content[self.cursor_y].len();
-            if self.cursor_x > line_len {
-                self.cursor_x = line_len;
-            }
-            
-            self.ensure_cursor_visible();
-            self.render();
+    fn is_line_selected(&self, line: usize) -> bool {
+        let (start, end) = self.selected_lines();
+        self.selection_anchor.is_some() && line >= start && line <= end
+    }
+    
+    /// Extend the line selection up (Shift+Up)
+    pub fn select_up(&mut self) {
+        if self.selection_anchor.is_none() {
+            self.selection_anchor = Some(self.cursor_y);
         }
+        self.move_up_keep_selection();
+        self.render();
     }
     
-    /// Navigate left
-    pub fn move_left(&mut self) {
-        if self.cursor_x > 0 {
-            self.cursor_x -= 1;
-            self.render();
-        } else if self.cursor_y > 0 {
+    /// Extend the line selection down (Shift+Down)
+    pub fn select_down(&mut self) {
+        if self.selection_anchor.is_none() {
+            self.selection_anchor = Some(self.cursor_y);
+        }
+        self.move_down_keep_selection();
+        self.render();
+    }
+    
+    // Put lines start..=end on the clipboard, each ending in a line break
+    fn copy_lines(&self, start: usize, end: usize) {
+        let mut clipboard = CLIPBOARD.lock();
+        clipboard.clear();
+        for i in start..=end {
+            clipboard.push_str(self.content[i].as_str());
+            clipboard.push('\n');
+        }
+    }
+    
+    /// Copy the selected lines to the clipboard (Ctrl+C)
+    pub fn copy_selection(&mut self) {
+        if self.line_count == 0 {
+            return;
+        }
+        
+        let (start, end) = self.selected_lines();
+        self.copy_lines(start, end.min(self.line_count - 1));
+        self.selection_anchor = None;
+        self.render();
+    }
+    
+    /// Move the selected lines to the clipboard (Ctrl+X)
+    pub fn cut_selection(&mut self) {
+        if self.line_count == 0 || self.read_only {
+            return;
+        }
+        
+        self.push_undo(EditKind::Other);
+        let (start, end) = self.selected_lines();
+        let end = end.min(self.line_count - 1);
+        self.copy_lines(start, end);
+        self.selection_anchor = None;
+        
+        // Shift the remaining lines up over the cut ones
+        let removed = end - start + 1;
+        for i in start..self.line_count {
+            self.content[i] = if i + removed < self.line_count {
+                self.content[i + removed].clone()
+            } else {
+                SimpleString::new()
+            };
+        }
+        self.line_count -= removed;
+        
+        // Always keep one line to type into
+        if self.line_count == 0 {
+            self.line_count = 1;
+        }
+        
+        self.cursor_y = start.min(self.line_count - 1);
+        self.cursor_x = 0;
+        self.modified = true;
+        self.ensure_cursor_visible();
+        self.render();
+    }
+    
+    /// Insert the clipboard at the cursor (Ctrl+V)
+    pub fn paste(&mut self) {
+        let mut text = SimpleString::new();
+        text.push_str(CLIPBOARD.lock().as_str());
+        if text.is_empty() || self.read_only {
+            return;
+        }
+        
+        self.push_undo(EditKind::Other);
+        self.selection_anchor = None;
+        for c in text.as_str().chars() {
+            self.insert_at_cursor(c);
+        }
+        
+        self.ensure_cursor_visible();
+        self.render();
+    }
+    
+    /// Show or hide the line number gutter (Ctrl+G)
+    pub fn toggle_line_numbers(&mut self) {
+        self.show_line_numbers = !self.show_line_numbers;
+        self.ensure_cursor_visible();
+        self.render();
+    }
+    
+    fn gutter_width(&self) -> usize {
+        if self.show_line_numbers { GUTTER_WIDTH } else { 0 }
+    }
+    
+    // Screen column where the text area starts
+    fn text_start_x(&self) -> usize {
+        self.rect.x + 2 + self.gutter_width()
+    }
+    
+    // Number of text columns that fit inside the frame
+    fn text_width(&self) -> usize {
+        self.rect.width - 4 - self.gutter_width()
+    }
+    
+    /// True while the Ctrl+F prompt is consuming keystrokes
+    pub fn is_search_prompt_active(&self) -> bool {
+        self.search_prompt
+    }
+    
+    /// Open the find prompt in the status bar (Ctrl+F)
+    pub fn start_search(&mut self) {
+        self.search_prompt = true;
+        self.search_term.clear();
+        self.status_message = "";
+        self.render();
+    }
+    
+    /// Handle a key typed into the find prompt
+    pub fn handle_search_key(&mut self, c: char) {
+        match c {
+            '\n' => {
+                self.search_prompt = false;
+                let mut needle = SimpleString::new();
+                needle.push_str(self.search_term.as_str());
+                self.find(needle.as_str());
+                return;
+            },
+            '\u{001B}' => self.search_prompt = false,
+            '\u{0008}' => {
+                self.search_term.pop();
+            },
+            c if !c.is_control() => self.search_term.push(c),
+            _ => {},
+        }
+        self.render();
+    }
+    
+    /// Open the go-to-line prompt in the status bar (Ctrl+L)
+    pub fn start_goto(&mut self) {
+        self.goto_prompt = true;
+        self.goto_input.clear();
+        self.status_message = "";
+        self.render();
+    }
+    
+    /// Handle a key typed into the go-to-line prompt
+    fn handle_goto_key(&mut self, c: char) {
+        match c {
+            '\n' => {
+                self.goto_prompt = false;
+                match self.goto_input.as_str().parse() {
+                    Ok(line) => {
+                        self.goto_line(line);
+                        return;
+                    }
+                    Err(_) => self.status_message = "Not a line number",
+                }
+            },
+            '\u{001B}' => self.goto_prompt = false,
+            '\u{0008}' => {
+                self.goto_input.pop();
+            },
+            c if c.is_ascii_digit() => self.goto_input.push(c),
+            _ => {},
+        }
+        self.render();
+    }
+    
+    /// Open the save-as prompt in the status bar (Ctrl+Shift+S)
+    pub fn start_save_as(&mut self) {
+        self.save_prompt = true;
+        self.save_input.clear();
+        self.save_input.push_str(self.filename.as_str());
+        self.status_message = "";
+        self.render();
+    }
+    
+    /// Handle a key typed into the save-as prompt
+    fn handle_save_key(&mut self, c: char) {
+        match c {
+            '\n' => {
+                self.save_prompt = false;
+                let mut name = SimpleString::new();
+                name.push_str(self.save_input.as_str());
+                self.save_as(name.as_str());
+            },
+            '\u{001B}' => self.save_prompt = false,
+            '\u{0008}' => {
+                self.save_input.pop();
+            },
+            c if !c.is_control() && self.save_input.len() + 1 < MAX_FILENAME_LENGTH => self.save_input.push(c),
+            _ => {},
+        }
+        self.render();
+    }
+    
+    /// Move the cursor to the indentation of line `n`, counting from 1.
+    /// Numbers past the end go to the last line.
+    pub fn goto_line(&mut self, n: usize) {
+        if self.line_count == 0 {
+            return;
+        }
+        
+        self.cursor_y = n.clamp(1, self.line_count) - 1;
+        let text = self.content[self.cursor_y].as_str();
+        self.cursor_x = text.len() - text.trim_start_matches(|c: char| c == ' ' || c == '\t').len();
+        self.selection_anchor = None;
+        self.ensure_cursor_visible();
+        self.render();
+    }
+    
+    /// Move the cursor to the first match of `needle` at or after the cursor
+    pub fn find(&mut self, needle: &str) -> bool {
+        if self.search_term.as_str() != needle {
+            self.search_term.clear();
+            self.search_term.push_str(needle);
+        }
+        self.find_from(self.cursor_y, self.cursor_x)
+    }
+    
+    /// Jump to the next occurrence of the last search term (F3)
+    pub fn find_next(&mut self) -> bool {
+        self.find_from(self.cursor_y, self.cursor_x + 1)
+    }
+    
+    fn find_from(&mut self, line: usize, col: usize) -> bool {
+        let found = find_in_lines(&self.content[..self.line_count], self.search_term.as_str(), line, col);
+        
+        match found {
+            Some((match_line, match_col)) => {
+                self.status_message = if (match_line, match_col) < (line, col) {
+                    "Search wrapped to top"
+                } else {
+                    ""
+                };
+                self.cursor_y = match_line;
+                self.cursor_x = match_col;
+                self.search_match = Some((match_line, match_col));
+                self.selection_anchor = None;
+                self.last_edit = EditKind::None;
+                self.ensure_cursor_visible();
+            },
+            None => {
+                self.status_message = "Not found";
+                self.search_match = None;
+            },
+        }
+        
+        self.render();
+        found.is_some()
+    }
+    
+    /// Navigate up
+    pub fn move_up(&mut self) {
+        self.selection_anchor = None;
+        self.move_up_keep_selection();
+        self.render();
+    }
+    
+    fn move_up_keep_selection(&mut self) {
+        self.last_edit = EditKind::None;
+        if self.cursor_y > 0 {
+            self.cursor_y -= 1;
+            
+            // Adjust X position if it's outside the line
+            let line_len = self.content[self.cursor_y].len();
+            if self.cursor_x > line_len {
+                self.cursor_x = line_len;
+            }
+            
+            self.ensure_cursor_visible();
+        }
+    }
+    
+    /// Navigate down
+    pub fn move_down(&mut self) {
+        self.selection_anchor = None;
+        self.move_down_keep_selection();
+        self.render();
+    }
+    
+    fn move_down_keep_selection(&mut self) {
+        self.last_edit = EditKind::None;
+        if self.cursor_y < self.line_count - 1 {
+            self.cursor_y += 1;
+            
+            // Adjust X position if it's outside the line
+            let line_len = self.content[self.cursor_y].len();
+            if self.cursor_x > line_len {
+                self.cursor_x = line_len;
+            }
+            
+            self.ensure_cursor_visible();
+        }
+    }
+    
+    /// Navigate left
+    pub fn move_left(&mut self) {
+        self.last_edit = EditKind::None;
+        self.selection_anchor = None;
+        if self.cursor_x > 0 {
+            self.cursor_x -= 1;
+            self.ensure_cursor_visible();
+            self.render();
+        } else if self.cursor_y > 0 {
             // Go to the end of previous line
             self.cursor_y -= 1;
             self.cursor_x = self.content[self.cursor_y].len();
@@ -508,10 +1272,13 @@ impl TextEditor {
     
     /// Navigate right
     pub fn move_right(&mut self) {
+        self.last_edit = EditKind::None;
+        self.selection_anchor = None;
         let line_len = self.content[self.cursor_y].len();
         
         if self.cursor_x < line_len {
             self.cursor_x += 1;
+            self.ensure_cursor_visible();
             self.render();
         } else if self.cursor_y < self.line_count - 1 {
             // Go to the beginning of next line
@@ -529,14 +1296,77 @@ impl TextEditor {
         } else if self.cursor_y >= self.scroll_offset + EDITOR_TEXT_HEIGHT {
             self.scroll_offset = self.cursor_y - EDITOR_TEXT_HEIGHT + 1;
         }
+        
+        // Same for the horizontal axis
+        let width = self.text_width();
+        if self.cursor_x < self.h_scroll_offset {
+            self.h_scroll_offset = self.cursor_x;
+        } else if self.cursor_x >= self.h_scroll_offset + width {
+            self.h_scroll_offset = self.cursor_x - width + 1;
+        }
+    }
+    
+    /// Position of the bracket under the cursor, or else just before it
+    fn bracket_at_cursor(&self) -> Option<(usize, usize)> {
+        let text = self.content.get(self.cursor_y)?.as_str().as_bytes();
+        [self.cursor_x, self.cursor_x.wrapping_sub(1)]
+            .into_iter()
+            .find(|&col| text.get(col).map_or(false, |&byte| bracket_pair(byte).is_some()))
+            .map(|col| (self.cursor_y, col))
+    }
+    
+    /// Screen cell of a buffer position, if it is scrolled into view
+    fn cell_on_screen(&self, line: usize, col: usize) -> Option<(usize, usize)> {
+        let rows = self.scroll_offset..self.scroll_offset + EDITOR_TEXT_HEIGHT;
+        let cols = self.h_scroll_offset..self.h_scroll_offset + self.text_width();
+        if !rows.contains(&line) || !cols.contains(&col) {
+            return None;
+        }
+        Some((self.text_start_x() + col - self.h_scroll_offset, self.rect.y + 2 + line - self.scroll_offset))
+    }
+    
+    /// Title row listing every buffer, the active one in brackets and
+    /// modified ones marked with `*`
+    fn tab_strip(&self) -> SimpleString {
+        let mut tabs = SimpleString::new();
+        for (index, buffer) in self.buffers.iter().enumerate() {
+            let (name, modified) = if index == self.active {
+                (self.filename.as_str(), self.modified)
+            } else {
+                (buffer.filename.as_str(), buffer.modified)
+            };
+            let name = if name.is_empty() { "untitled" } else { name };
+            let star = if modified { "*" } else { "" };
+            if index == self.active {
+                let _ = write!(tabs, "[{}{}] ", name, star);
+            } else {
+                let _ = write!(tabs, " {}{}  ", name, star);
+            }
+        }
+        while tabs.as_str().ends_with(' ') {
+            tabs.pop();
+        }
+        tabs
+    }
+    
+    /// Replace the highlighter picked from the file extension
+    pub fn set_highlighter(&mut self, highlighter: Highlighter) {
+        self.highlighter = highlighter;
     }
     
     /// Draw the editor
-    pub fn render(&mut self) {
+    pub fn render(&self) {
         if !self.visible {
             return;
         }
-        let title = format_title("Text editor -", self.filename.as_str(), self.modified);
+        let mut title = if self.buffers.len() > 1 {
+            self.tab_strip()
+        } else {
+            format_title("Text editor -", self.filename.as_str(), self.modified)
+        };
+        if self.read_only {
+            title.push_str(" [RO]");
+        }
         draw_box(self.rect, BorderStyle::Double, Some(title.as_str()));
         
         // Draw the content
@@ -546,30 +1376,82 @@ impl TextEditor {
             if line_index < self.line_count {
                 // Draw the line
                 let line = &self.content[line_index];
-                let x = self.rect.x + 2;
+                let x = self.text_start_x();
                 let y = self.rect.y + 2 + i;
                 
-                // Save current cursor position
                 let mut writer = WRITER.lock();
-                let saved_row = writer.column_position;
-                let saved_col = writer.row_position;
                 
-                // Place cursor and write
-                writer.column_position = y;
-                writer.row_position = x;
+                // Line numbers are 1-based and follow the scroll position
+                if self.show_line_numbers {
+                    let mut number = SimpleString::new();
+                    write!(number, "{:>4} ", line_index + 1).unwrap();
+                    let (_, bg) = writer.get_color();
+                    for (offset, c) in number.as_str().chars().enumerate() {
+                        writer.write_char_at(self.rect.x + 2 + offset, y, c, Color::DarkGray, bg);
+                    }
+                }
                 
-                // Display the line
-                write!(writer, "{}", line.as_str()).unwrap();
+                // Colors for the whole line, so the highlighter sees
+                // tokens that start left of the scrolled view
+                let (fg, bg) = writer.get_color();
+                let text = line.as_str();
+                let mut colors = [fg; MAX_LINE_LENGTH];
+                (self.highlighter)(text, &mut colors);
+                let selected = self.is_line_selected(line_index);
                 
-                // Restore cursor
-                writer.column_position = saved_row;
-                writer.row_position = saved_col;
+                // Only the horizontally visible slice of the line
+                let start = self.h_scroll_offset.min(text.len());
+                let end = (self.h_scroll_offset + self.text_width()).min(text.len());
+                for (i, &byte) in text.as_bytes()[start..end].iter().enumerate() {
+                    let color = colors.get(start + i).copied().unwrap_or(fg);
+                    // Selected lines in inverse video, without highlighting
+                    let (cell_fg, cell_bg) = if selected { (bg, fg) } else { (color, bg) };
+                    writer.write_char_at(x + i, y, byte as char, cell_fg, cell_bg);
+                }
+            }
+        }
+        
+        let mut writer = WRITER.lock();
+        
+        // Highlight the last search match
+        if let Some((line, col)) = self.search_match {
+            if line >= self.scroll_offset && line < self.scroll_offset + EDITOR_TEXT_HEIGHT {
+                let y = self.rect.y + 2 + (line - self.scroll_offset);
+                let text = self.content[line].as_str().as_bytes();
+                let start = col.max(self.h_scroll_offset);
+                let end = (col + self.search_term.len())
+                    .min(text.len())
+                    .min(self.h_scroll_offset + self.text_width());
+                for i in start..end {
+                    let x = self.text_start_x() + i - self.h_scroll_offset;
+                    writer.write_char_at(x, y, text[i] as char, Color::Black, Color::Yellow);
+                }
+            }
+        }
+        
+        // Show the partner of the bracket at the cursor in inverse video,
+        // or the bracket itself in red if it has none
+        if let Some((line, col)) = self.bracket_at_cursor() {
+            match find_matching_bracket(&self.content[..self.line_count], line, col) {
+                Some((match_line, match_col)) => {
+                    if let Some((x, y)) = self.cell_on_screen(match_line, match_col) {
+                        if let Some((c, fg, bg)) = writer.read_cell_at(x, y) {
+                            writer.write_char_at(x, y, c as char, bg, fg);
+                        }
+                    }
+                }
+                None => {
+                    if let Some((x, y)) = self.cell_on_screen(line, col) {
+                        if let Some((c, _, bg)) = writer.read_cell_at(x, y) {
+                            writer.write_char_at(x, y, c as char, Color::Red, bg);
+                        }
+                    }
+                }
             }
         }
         
         // Draw help text at the bottom
         let y = self.rect.y + self.rect.height - 2;
-        let mut writer = WRITER.lock();
         for x in self.rect.x+1..self.rect.x+self.rect.width-1 {
             writer.write_char_at(x, y, ' ', Color::Black, Color::LightGray);
         }
@@ -577,20 +1459,775 @@ impl TextEditor {
         let x = self.rect.x + 2;
         writer.set_cursor_position(x, y);
         writer.set_color(Color::Black, Color::LightGray);
-        write!(writer, "Ctrl+X: Cut | Ctrl+C: Copy | Ctrl+V: Paste | Ctrl+S: Save | Esc: Close").unwrap();
+        if self.confirm_close {
+            write!(writer, "Unsaved changes - S)ave D)iscard C)ancel").unwrap();
+        } else if self.search_prompt {
+            write!(writer, "Find: {}", self.search_term.as_str()).unwrap();
+        } else if self.goto_prompt {
+            write!(writer, "Go to line: {}", self.goto_input.as_str()).unwrap();
+        } else if self.save_prompt {
+            write!(writer, "Save as: {}", self.save_input.as_str()).unwrap();
+        } else if !self.status_message.is_empty() {
+            write!(writer, "{}", self.status_message).unwrap();
+        } else {
+            write!(writer, "Ctrl+X: Cut | Ctrl+C: Copy | Ctrl+V: Paste | Ctrl+S: Save | Ctrl+F: Find | Esc: Close").unwrap();
+        }
+        
+        // Cursor position, right-aligned in the bottom border
+        let mut position = self.position.borrow_mut();
+        format_into!(&mut *position, " Ln {}/{}, Col {} ", self.cursor_y + 1, self.line_count.max(1), self.cursor_x + 1);
+        let bottom = self.rect.y + self.rect.height - 1;
+        let start = self.rect.x + self.rect.width - 2 - position.len();
+        for (i, c) in position.as_str().chars().enumerate() {
+            writer.write_char_at(start + i, bottom, c, Color::Black, Color::LightGray);
+        }
         
         // Set the visual cursor
         if self.cursor_y >= self.scroll_offset && self.cursor_y < self.scroll_offset + EDITOR_TEXT_HEIGHT {
             let cursor_screen_y = self.rect.y + 2 + (self.cursor_y - self.scroll_offset);
-            let cursor_screen_x = self.rect.x + 2 + self.cursor_x;
+            let cursor_screen_x = self.text_start_x() + self.cursor_x - self.h_scroll_offset;
             
-            writer.column_position = cursor_screen_y;
-            writer.row_position = cursor_screen_x;
+            writer.set_cursor_position(cursor_screen_x, cursor_screen_y);
+        }
+    }
+}
+
+impl Component for TextEditor {
+    fn render(&self) {
+        TextEditor::render(self);
+    }
+    
+    /// Typing, Backspace (0x08) and Delete (0x7F). Open status bar prompts
+    /// get every key.
+    fn handle_input(&mut self, key: u8) -> bool {
+        if !self.visible {
+            return false;
+        }
+        
+        let c = key as char;
+        if self.is_modal_active() {
+            self.handle_modal_key(c);
+            return true;
+        }
+        
+        match key {
+            0x08 => self.handle_backspace(),
+            0x7F => self.handle_delete(),
+            b'\n' | 0x20..=0x7E => self.insert_char(c),
+            _ => return false,
+        }
+        true
+    }
+    
+    fn get_bounds(&self) -> Rect {
+        self.rect
+    }
+    
+    /// Editing keys, cursor movement (Shift selects) and the Ctrl shortcuts.
+    /// Every Ctrl key is taken so none of them reach the command line.
+    fn handle_key_event(&mut self, key: DecodedKey) -> bool {
+        if !self.visible {
+            return false;
+        }
+        
+        let shift = modifiers().shift;
+        match key {
+            DecodedKey::Unicode(c) if self.is_modal_active() => self.handle_modal_key(c),
+            // Ctrl+Tab cycles the buffers. With only one it moves the focus.
+            DecodedKey::Unicode('\t') if modifiers().ctrl => {
+                if self.buffers.len() < 2 {
+                    return false;
+                }
+                if shift {
+                    self.prev_buffer();
+                } else {
+                    self.next_buffer();
+                }
+            },
+            DecodedKey::Unicode(c) if modifiers().ctrl => match c.to_ascii_lowercase() {
+                'c' => self.copy_selection(),
+                'x' => self.cut_selection(),
+                'v' => self.paste(),
+                's' if modifiers().shift => self.start_save_as(),
+                's' => { self.save_file(); },
+                'f' => self.start_search(),
+                'g' => self.toggle_line_numbers(),
+                'l' => self.start_goto(),
+                'z' => self.undo(),
+                'y' => self.redo(),
+                _ => {}
+            },
+            // Escape - stäng, fråga först om ändringar ska sparas
+            DecodedKey::Unicode('\u{001B}') => {
+                if self.has_unsaved_changes() {
+                    let choice = WINDOW_MANAGER.lock().show_dialog(
+                        "Unsaved changes",
+                        "Save changes before closing?",
+                        &["Save", "Discard", "Cancel"],
+                    );
+                    self.close_with(match choice {
+                        Some(0) => CloseAction::Save,
+                        Some(1) => CloseAction::Discard,
+                        _ => CloseAction::Cancel,
+                    });
+                } else {
+                    self.close();
+                }
+            },
+            DecodedKey::Unicode('\u{0008}') => self.handle_backspace(),
+            DecodedKey::Unicode('\u{007F}') => self.handle_delete(),
+            DecodedKey::Unicode(c) => self.insert_char(c),
+            // An open prompt ignores the other raw keys
+            DecodedKey::RawKey(_) if self.is_modal_active() => {},
+            DecodedKey::RawKey(KeyCode::ArrowUp) if shift => self.select_up(),
+            DecodedKey::RawKey(KeyCode::ArrowUp) => self.move_up(),
+            DecodedKey::RawKey(KeyCode::ArrowDown) if shift => self.select_down(),
+            DecodedKey::RawKey(KeyCode::ArrowDown) => self.move_down(),
+            DecodedKey::RawKey(KeyCode::ArrowLeft) => self.move_left(),
+            DecodedKey::RawKey(KeyCode::ArrowRight) => self.move_right(),
+            DecodedKey::RawKey(KeyCode::Delete) => self.handle_delete(),
+            DecodedKey::RawKey(KeyCode::Return) => self.insert_char('\n'),
+            // The status line already says whether there was a match
+            DecodedKey::RawKey(KeyCode::F3) => {
+                self.find_next();
+            },
+            DecodedKey::RawKey(_) => return false,
         }
+        true
+    }
+    
+    fn is_visible(&self) -> bool {
+        self.visible
     }
 }
 
+/// Highlighter for files without a known extension
+fn no_highlight(_line: &str, _out: &mut [Color; MAX_LINE_LENGTH]) {}
+
+/// Highlighter for Rust and C: keywords, string literals and `//` comments
+fn highlight_code(line: &str, out: &mut [Color; MAX_LINE_LENGTH]) {
+    let bytes = line.as_bytes();
+    let len = bytes.len().min(MAX_LINE_LENGTH);
+    let mut i = 0;
+    while i < len {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                out[i..len].fill(COMMENT_COLOR);
+                return;
+            }
+            b'"' => {
+                let start = i;
+                i += 1;
+                while i < len && bytes[i] != b'"' {
+                    // Skip the escaped character
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                // Include the closing quote
+                i = (i + 1).min(len);
+                out[start..i].fill(STRING_COLOR);
+            }
+            byte if byte.is_ascii_alphanumeric() || byte == b'_' => {
+                let start = i;
+                while i < len && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                    i += 1;
+                }
+                if KEYWORDS.contains(&&line[start..i]) {
+                    out[start..i].fill(KEYWORD_COLOR);
+                }
+            }
+            _ => i += 1,
+        }
+    }
+}
+
+/// Pick a highlighter from the file extension
+fn highlighter_for(filename: &str) -> Highlighter {
+    let extension = filename.rsplit_once('.').map_or("", |(_, ext)| ext);
+    if ["rs", "c", "h"].iter().any(|known| known.eq_ignore_ascii_case(extension)) {
+        highlight_code
+    } else {
+        no_highlight
+    }
+}
+
+/// Find the first occurrence of `needle` at or after (`line`, `col`),
+/// wrapping around to the top of the buffer
+fn find_in_lines(lines: &[SimpleString], needle: &str, line: usize, col: usize) -> Option<(usize, usize)> {
+    if needle.is_empty() || lines.is_empty() {
+        return None;
+    }
+    
+    let count = lines.len();
+    // One extra pass revisits the start line before the cursor
+    for offset in 0..=count {
+        let index = (line + offset) % count;
+        let text = lines[index].as_str();
+        let mut start = if offset == 0 { col.min(text.len()) } else { 0 };
+        // Find-next starts one byte past a match, which can be inside a
+        // multi-byte character
+        while !text.is_char_boundary(start) {
+            start += 1;
+        }
+        
+        if let Some(pos) = text[start..].find(needle) {
+            return Some((index, start + pos));
+        }
+    }
+    
+    None
+}
+
+/// The bracket that pairs with `byte`, and whether `byte` opens
+fn bracket_pair(byte: u8) -> Option<(u8, bool)> {
+    match byte {
+        b'(' => Some((b')', true)),
+        b'[' => Some((b']', true)),
+        b'{' => Some((b'}', true)),
+        b')' => Some((b'(', false)),
+        b']' => Some((b'[', false)),
+        b'}' => Some((b'{', false)),
+        _ => None,
+    }
+}
+
+/// Find the bracket matching the one at (`line`, `col`), searching forward
+/// from an opener and backward from a closer across lines. Brackets of the
+/// same kind in between nest.
+fn find_matching_bracket(lines: &[SimpleString], line: usize, col: usize) -> Option<(usize, usize)> {
+    let bracket = *lines.get(line)?.as_str().as_bytes().get(col)?;
+    let (partner, forward) = bracket_pair(bracket)?;
+    
+    let mut depth = 0;
+    let mut visit = |line: usize, col: usize| {
+        let byte = lines[line].as_str().as_bytes()[col];
+        if byte == bracket {
+            depth += 1;
+        } else if byte == partner {
+            depth -= 1;
+            if depth == 0 {
+                return Some((line, col));
+            }
+        }
+        None
+    };
+    
+    if forward {
+        for l in line..lines.len() {
+            let start = if l == line { col } else { 0 };
+            for c in start..lines[l].len() {
+                if let Some(found) = visit(l, c) {
+                    return Some(found);
+                }
+            }
+        }
+    } else {
+        for l in (0..=line).rev() {
+            let end = if l == line { col + 1 } else { lines[l].len() };
+            for c in (0..end).rev() {
+                if let Some(found) = visit(l, c) {
+                    return Some(found);
+                }
+            }
+        }
+    }
+    None
+}
+
 // Create a global instance of the text editor
 lazy_static::lazy_static! {
     pub static ref TEXT_EDITOR: Mutex<TextEditor> = Mutex::new(TextEditor::new());
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor_with(text: &str) -> TextEditor {
+        let mut editor = TextEditor::new();
+        editor.load_text(text);
+        editor.visible = true;
+        editor
+    }
+
+    #[test_case]
+    fn undo_coalesces_typing() {
+        let mut editor = editor_with("");
+        editor.line_count = 1;
+        for c in "abc".chars() {
+            editor.insert_char(c);
+        }
+        assert_eq!(editor.undo_stack.len(), 1);
+
+        editor.undo();
+        assert_eq!(editor.text(), "");
+        assert_eq!((editor.cursor_x, editor.cursor_y), (0, 0));
+    }
+
+    #[test_case]
+    fn redo_reapplies_undone_edit() {
+        let mut editor = editor_with("hello");
+        editor.cursor_x = 5;
+        editor.handle_backspace();
+        assert_eq!(editor.text(), "hell");
+
+        editor.undo();
+        assert_eq!(editor.text(), "hello");
+        assert_eq!(editor.cursor_x, 5);
+
+        editor.redo();
+        assert_eq!(editor.text(), "hell");
+    }
+
+    #[test_case]
+    fn undo_depth_is_bounded() {
+        let mut editor = editor_with("x");
+        for _ in 0..MAX_UNDO_DEPTH + 10 {
+            editor.cursor_x = 0;
+            editor.insert_char('\n');
+        }
+        assert_eq!(editor.undo_stack.len(), MAX_UNDO_DEPTH);
+    }
+
+    #[test_case]
+    fn cut_without_selection_takes_current_line() {
+        let mut editor = editor_with("one\ntwo\nthree");
+        editor.cursor_y = 1;
+        editor.cut_selection();
+        assert_eq!(editor.text(), "one\nthree");
+        assert_eq!(CLIPBOARD.lock().as_str(), "two\n");
+    }
+
+    #[test_case]
+    fn enter_splits_top_middle_and_end() {
+        let mut editor = editor_with("one\ntwo\nthree");
+        editor.insert_char('\n');
+        assert_eq!(editor.text(), "\none\ntwo\nthree");
+
+        editor.cursor_y = 2;
+        editor.cursor_x = 1;
+        editor.insert_char('\n');
+        assert_eq!(editor.text(), "\none\nt\nwo\nthree");
+
+        editor.cursor_y = 4;
+        editor.cursor_x = 5;
+        editor.insert_char('\n');
+        assert_eq!(editor.text(), "\none\nt\nwo\nthree\n");
+        assert_eq!((editor.line_count, editor.cursor_y), (6, 5));
+    }
+
+    #[test_case]
+    fn enter_in_empty_buffer_makes_two_lines() {
+        let mut editor = editor_with("");
+        editor.insert_char('a');
+        editor.insert_char('\n');
+        editor.insert_char('b');
+        assert_eq!(editor.text(), "a\nb");
+    }
+
+    #[test_case]
+    fn enter_stops_at_line_limit() {
+        let mut text = String::new();
+        for n in 0..MAX_LINES {
+            writeln!(text, "{}", n).unwrap();
+        }
+        let mut editor = editor_with(&text);
+        assert_eq!(editor.line_count, MAX_LINES);
+
+        editor.cursor_y = 10;
+        editor.insert_char('\n');
+        assert_eq!(editor.line_count, MAX_LINES);
+        assert_eq!(editor.content[MAX_LINES - 1].as_str(), format!("{}", MAX_LINES - 1).as_str());
+        assert_eq!(editor.content[10].as_str(), "10");
+
+        let mut text = String::new();
+        for n in 0..MAX_LINES {
+            writeln!(text, "    {}", n).unwrap();
+        }
+        let mut editor = editor_with(&text);
+        editor.cursor_y = 10;
+        editor.cursor_x = 6;
+        editor.insert_char('\n');
+        assert_eq!(editor.line_count, MAX_LINES);
+        assert_eq!(editor.content[10].as_str(), "    10");
+        assert_eq!(editor.cursor_x, 6);
+    }
+
+    #[test_case]
+    fn paste_splits_lines_at_cursor() {
+        let mut editor = editor_with("one\ntwo\nthree");
+        editor.select_down();
+        editor.copy_selection();
+        assert_eq!(CLIPBOARD.lock().as_str(), "one\ntwo\n");
+
+        editor.cursor_y = 2;
+        editor.cursor_x = 0;
+        editor.paste();
+        assert_eq!(editor.text(), "one\ntwo\none\ntwo\nthree");
+    }
+
+    #[test_case]
+    fn find_moves_cursor_to_match() {
+        let mut editor = editor_with("alpha\nbeta gamma\ndelta");
+        assert!(editor.find("gamma"));
+        assert_eq!((editor.cursor_y, editor.cursor_x), (1, 5));
+        assert_eq!(editor.search_match, Some((1, 5)));
+    }
+
+    #[test_case]
+    fn find_next_wraps_to_top() {
+        let mut editor = editor_with("ta one\nxx\nta two");
+        editor.find("ta");
+        assert_eq!((editor.cursor_y, editor.cursor_x), (0, 0));
+        editor.find_next();
+        assert_eq!((editor.cursor_y, editor.cursor_x), (2, 0));
+        editor.find_next();
+        assert_eq!((editor.cursor_y, editor.cursor_x), (0, 0));
+    }
+
+    #[test_case]
+    fn find_next_steps_over_multibyte_match() {
+        let mut editor = editor_with("å och å");
+        editor.find("å");
+        assert_eq!((editor.cursor_y, editor.cursor_x), (0, 0));
+        editor.find_next();
+        assert_eq!((editor.cursor_y, editor.cursor_x), (0, "å och ".len()));
+    }
+
+    #[test_case]
+    fn find_reports_missing_term() {
+        let mut editor = editor_with("alpha");
+        assert!(!editor.find("omega"));
+        assert_eq!(editor.search_match, None);
+    }
+
+    #[test_case]
+    fn render_places_lines_at_their_rows() {
+        FILESYSTEM.lock().write_file("render.txt", "ab\ncd");
+        let mut editor = TextEditor::new();
+        assert!(editor.open_file("render.txt"));
+        editor.show();
+
+        let (x, y) = (editor.rect.x + 2, editor.rect.y + 2);
+        let writer = WRITER.lock();
+        assert_eq!(writer.read_char_at(x, y), Some('a'));
+        assert_eq!(writer.read_char_at(x + 1, y), Some('b'));
+        assert_eq!(writer.read_char_at(x, y + 1), Some('c'));
+        assert_eq!(writer.read_char_at(x + 1, y + 1), Some('d'));
+    }
+
+    #[test_case]
+    fn gutter_shifts_text_and_numbers_follow_scroll() {
+        let mut editor = editor_with("a\nb\nc");
+        editor.show_line_numbers = true;
+        editor.scroll_offset = 1;
+        editor.render();
+
+        let (x, y) = (editor.rect.x + 2, editor.rect.y + 2);
+        let writer = WRITER.lock();
+        assert_eq!(writer.read_char_at(x + 3, y), Some('2'));
+        assert_eq!(writer.read_char_at(x + GUTTER_WIDTH, y), Some('b'));
+    }
+
+    #[test_case]
+    fn saving_a_new_file_asks_for_a_name() {
+        let mut editor = editor_with("");
+        editor.new_file();
+        editor.insert_char('x');
+        assert!(!editor.save_file());
+        assert!(editor.is_modal_active());
+
+        for c in "saveas.txt\n".chars() {
+            editor.handle_modal_key(c);
+        }
+        assert!(!editor.is_modal_active());
+        assert_eq!(editor.filename.as_str(), "saveas.txt");
+        assert!(!editor.modified);
+        assert_eq!(FILESYSTEM.lock().read_file("saveas.txt"), Ok("x"));
+    }
+
+    #[test_case]
+    fn save_as_rejects_long_names() {
+        let mut editor = editor_with("x");
+        let long = "n".repeat(MAX_FILENAME_LENGTH);
+        assert!(!editor.save_as(&long));
+        assert!(!editor.save_as("  "));
+        assert_eq!(editor.filename.as_str(), "");
+    }
+
+    #[test_case]
+    fn failed_save_as_keeps_the_old_name() {
+        let mut editor = editor_with("x");
+        assert!(editor.save_as("keep_name.txt"));
+        // A directory can't be written as a file
+        assert!(!editor.save_as("system"));
+        assert_eq!(editor.filename.as_str(), "keep_name.txt");
+    }
+
+    #[test_case]
+    fn opening_files_adds_buffers() {
+        FILESYSTEM.lock().write_file("buf_a.txt", "alpha");
+        FILESYSTEM.lock().write_file("buf_b.txt", "beta");
+        let mut editor = TextEditor::new();
+        assert!(editor.open_file("buf_a.txt"));
+        assert_eq!(editor.buffer_count(), 1);
+        assert!(editor.open_file("buf_b.txt"));
+        assert_eq!(editor.buffer_count(), 2);
+        assert_eq!(editor.text(), "beta");
+
+        // Reopening switches back instead of loading a second copy
+        assert!(editor.open_file("buf_a.txt"));
+        assert_eq!(editor.buffer_count(), 2);
+        assert_eq!(editor.text(), "alpha");
+
+        editor.next_buffer();
+        assert_eq!(editor.filename.as_str(), "buf_b.txt");
+        assert_eq!(editor.tab_strip().as_str(), " buf_a.txt  [buf_b.txt]");
+    }
+
+    #[test_case]
+    fn opening_into_a_new_buffer_clears_the_view() {
+        FILESYSTEM.lock().write_file("buf_f.txt", "find me");
+        FILESYSTEM.lock().write_file("buf_g.txt", "other");
+        let mut editor = TextEditor::new();
+        assert!(editor.open_file("buf_f.txt"));
+        assert!(editor.find("me"));
+        editor.selection_anchor = Some(0);
+
+        assert!(editor.open_file("buf_g.txt"));
+        assert_eq!(editor.search_match, None);
+        assert_eq!(editor.selection_anchor, None);
+    }
+
+    #[test_case]
+    fn buffers_keep_their_edits() {
+        FILESYSTEM.lock().write_file("buf_c.txt", "one");
+        let mut editor = editor_with("");
+        editor.new_file();
+        editor.insert_char('x');
+        assert!(editor.open_file("buf_c.txt"));
+        editor.prev_buffer();
+
+        assert_eq!(editor.text(), "x");
+        assert!(editor.modified);
+        assert_eq!(editor.tab_strip().as_str(), "[untitled*]  buf_c.txt");
+    }
+
+    #[test_case]
+    fn closing_a_buffer_activates_its_neighbour() {
+        FILESYSTEM.lock().write_file("buf_d.txt", "d");
+        FILESYSTEM.lock().write_file("buf_e.txt", "e");
+        let mut editor = TextEditor::new();
+        editor.open_file("buf_d.txt");
+        editor.open_file("buf_e.txt");
+        editor.show();
+
+        editor.close();
+        assert_eq!(editor.buffer_count(), 1);
+        assert_eq!(editor.filename.as_str(), "buf_d.txt");
+        assert!(editor.visible);
+
+        editor.close();
+        assert!(!editor.visible);
+    }
+
+    #[test_case]
+    fn goto_line_scrolls_to_the_line() {
+        let mut text = String::new();
+        for n in 1..=50 {
+            writeln!(text, "  line {}", n).unwrap();
+        }
+        FILESYSTEM.lock().write_file("goto.txt", &text);
+        let mut editor = TextEditor::new();
+        assert!(editor.open_file("goto.txt"));
+        editor.show();
+
+        editor.goto_line(30);
+        assert_eq!(editor.cursor_y, 29);
+        assert_eq!(editor.cursor_x, 2);
+        assert!(editor.scroll_offset <= 29 && 29 < editor.scroll_offset + EDITOR_TEXT_HEIGHT);
+
+        editor.goto_line(500);
+        assert_eq!(editor.cursor_y, editor.line_count - 1);
+        editor.goto_line(0);
+        assert_eq!(editor.cursor_y, 0);
+    }
+
+    #[test_case]
+    fn goto_prompt_reads_a_number() {
+        let mut editor = editor_with("a\nb\nc");
+        editor.start_goto();
+        assert!(editor.is_modal_active());
+        for c in "3\n".chars() {
+            editor.handle_modal_key(c);
+        }
+        assert!(!editor.is_modal_active());
+        assert_eq!(editor.cursor_y, 2);
+    }
+
+    #[test_case]
+    fn enter_keeps_the_indent() {
+        let mut editor = editor_with("    let x = 1;");
+        editor.cursor_x = editor.content[0].len();
+        editor.insert_char('\n');
+        assert_eq!(editor.content[1].as_str(), "    ");
+        assert_eq!(editor.cursor_x, 4);
+
+        editor.set_auto_indent(false);
+        editor.insert_char('\n');
+        assert_eq!(editor.content[2].as_str(), "");
+    }
+
+    #[test_case]
+    fn enter_after_brace_indents_one_level() {
+        let mut editor = editor_with("  fn main() {}");
+        editor.cursor_x = editor.content[0].len() - 1;
+        editor.insert_char('\n');
+        assert_eq!(editor.content[0].as_str(), "  fn main() {");
+        assert_eq!(editor.content[1].as_str(), "      }");
+        assert_eq!(editor.cursor_x, 6);
+
+        // One undo step takes back the split and the indent
+        editor.undo();
+        assert_eq!(editor.content[0].as_str(), "  fn main() {}");
+    }
+
+    #[test_case]
+    fn backspace_removes_an_indent_level() {
+        let mut editor = editor_with("      x");
+        editor.cursor_x = 6;
+        editor.handle_backspace();
+        assert_eq!(editor.content[0].as_str(), "    x");
+        assert_eq!(editor.cursor_x, 4);
+
+        // Past text only one character goes
+        let mut editor = editor_with("  ab");
+        editor.cursor_x = 4;
+        editor.handle_backspace();
+        assert_eq!(editor.content[0].as_str(), "  a");
+    }
+
+    #[test_case]
+    fn matching_bracket_respects_nesting() {
+        let editor = editor_with("fn f(a: [u8; (2)]) {\n    g(x[0]);\n}");
+        let lines = &editor.content[..editor.line_count];
+        assert_eq!(find_matching_bracket(lines, 0, 4), Some((0, 17)));
+        assert_eq!(find_matching_bracket(lines, 0, 17), Some((0, 4)));
+        assert_eq!(find_matching_bracket(lines, 0, 19), Some((2, 0)));
+        assert_eq!(find_matching_bracket(lines, 2, 0), Some((0, 19)));
+        assert_eq!(find_matching_bracket(lines, 1, 5), Some((1, 10)));
+        assert_eq!(find_matching_bracket(lines, 1, 7), Some((1, 9)));
+        // Not a bracket
+        assert_eq!(find_matching_bracket(lines, 0, 0), None);
+    }
+
+    #[test_case]
+    fn unbalanced_brackets_have_no_match() {
+        let editor = editor_with("((a)\nb]");
+        let lines = &editor.content[..editor.line_count];
+        assert_eq!(find_matching_bracket(lines, 0, 0), None);
+        assert_eq!(find_matching_bracket(lines, 0, 1), Some((0, 3)));
+        assert_eq!(find_matching_bracket(lines, 1, 1), None);
+    }
+
+    #[test_case]
+    fn bracket_before_cursor_is_used() {
+        let mut editor = editor_with("(x)");
+        editor.cursor_x = 3;
+        assert_eq!(editor.bracket_at_cursor(), Some((0, 2)));
+        editor.cursor_x = 0;
+        assert_eq!(editor.bracket_at_cursor(), Some((0, 0)));
+        editor.cursor_x = 1;
+        assert_eq!(editor.bracket_at_cursor(), Some((0, 0)));
+    }
+
+    #[test_case]
+    fn code_highlighter_colors_tokens() {
+        let line = "let s = \"a\\\"b\"; // fn";
+        let mut colors = [Color::White; MAX_LINE_LENGTH];
+        highlight_code(line, &mut colors);
+
+        assert_eq!(colors[0], KEYWORD_COLOR);
+        assert_eq!(colors[3], Color::White);
+        let quote = line.find('"').unwrap();
+        let comment = line.find("//").unwrap();
+        assert!(colors[quote..comment - 2].iter().all(|&c| c == STRING_COLOR));
+        assert_eq!(colors[comment - 2], Color::White);
+        assert!(colors[comment..line.len()].iter().all(|&c| c == COMMENT_COLOR));
+        assert_eq!(colors[line.len()], Color::White);
+    }
+
+    #[test_case]
+    fn highlighter_follows_extension() {
+        assert!(highlighter_for("main.RS") as usize == highlight_code as usize);
+        assert!(highlighter_for("notes.txt") as usize == no_highlight as usize);
+        assert!(highlighter_for("Makefile") as usize == no_highlight as usize);
+    }
+
+    #[test_case]
+    fn highlighting_follows_horizontal_scroll() {
+        let mut editor = editor_with("x = 1; // note");
+        editor.set_highlighter(highlight_code);
+        editor.cursor_x = 7;
+        editor.h_scroll_offset = 7;
+        editor.render();
+
+        let (x, y) = (editor.rect.x + 2, editor.rect.y + 2);
+        let writer = WRITER.lock();
+        assert_eq!(writer.read_char_at(x, y), Some('/'));
+        assert_eq!(writer.color_at(x, y).map(|(fg, _)| fg), Some(COMMENT_COLOR));
+    }
+
+    #[test_case]
+    fn long_lines_scroll_horizontally() {
+        let long: String = (0..MAX_LINE_LENGTH).map(|i| (b'a' + (i % 26) as u8) as char).collect();
+        let mut editor = editor_with(&long);
+        let width = editor.text_width();
+
+        for _ in 0..MAX_LINE_LENGTH {
+            editor.move_right();
+        }
+        assert_eq!(editor.h_scroll_offset, MAX_LINE_LENGTH - width + 1);
+
+        for _ in 0..MAX_LINE_LENGTH {
+            editor.move_left();
+        }
+        assert_eq!(editor.h_scroll_offset, 0);
+    }
+
+    #[test_case]
+    fn close_with_changes_asks_first() {
+        let mut editor = editor_with("text");
+        editor.insert_char('x');
+        editor.close();
+        assert!(editor.visible);
+        assert!(editor.is_modal_active());
+
+        // Other keys are swallowed until the prompt is resolved
+        editor.handle_modal_key('q');
+        assert!(editor.is_modal_active());
+
+        editor.handle_modal_key('c');
+        assert!(editor.visible);
+        assert!(!editor.is_modal_active());
+
+        editor.close();
+        editor.handle_modal_key('d');
+        assert!(!editor.visible);
+    }
+
+    #[test_case]
+    fn read_only_blocks_edits() {
+        let mut editor = editor_with("text");
+        editor.set_read_only(true);
+        editor.cursor_x = 4;
+        editor.insert_char('x');
+        editor.handle_backspace();
+        editor.cursor_x = 0;
+        editor.handle_delete();
+        assert_eq!(editor.text(), "text");
+        assert!(!editor.modified);
+    }
+}
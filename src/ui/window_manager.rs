@@ -1,42 +1,386 @@
-use crate::ui::{Rect, BorderStyle, draw_box};
-use crate::vga_buffer::Theme;
+use alloc::string::String;
+use alloc::vec::Vec;
+use lazy_static::lazy_static;
+use pc_keyboard::{DecodedKey, KeyCode};
+use spin::Mutex;
+use crate::ui::{Rect, BorderStyle, Component, UITheme, draw_box, draw_text_fitted};
+use crate::vga_buffer::{Color, Theme, SavedRegion, WRITER, BUFFER_WIDTH, BUFFER_HEIGHT};
 
+lazy_static! {
+    pub static ref WINDOW_MANAGER: Mutex<WindowManager> = Mutex::new(WindowManager::new());
+}
+
+/// Identifierar ett fönster hos fönsterhanteraren
+pub type WindowId = usize;
+
+struct Window {
+    id: WindowId,
+    rect: Rect,
+    title: String,
+    message: String,
+    // Knappar längst ner i dialogrutor, och den som har fokus
+    buttons: Vec<String>,
+    focused: usize,
+    // Skugga till höger om och under fönstret
+    shadow: bool,
+    border: BorderStyle,
+    // Det som låg under fönstret (och skuggan) när det ritades
+    saved_under: Option<SavedRegion>,
+}
+
+impl Window {
+    /// Spara det som ligger under fönstret och rita det sedan
+    fn draw(&mut self) {
+        // Skuggan tar en kolumn till höger och en rad nedanför
+        let extra = if self.shadow { 1 } else { 0 };
+        self.saved_under = Some(WRITER.lock().save_region(
+            self.rect.x,
+            self.rect.y,
+            self.rect.width + extra,
+            self.rect.height + extra,
+        ));
+
+        self.render();
+    }
+
+    /// Byt ramstil, syns nästa gång fönstret ritas
+    fn set_border_style(&mut self, style: BorderStyle) {
+        self.border = style;
+    }
+
+    /// Rita knapparna centrerade på näst sista raden, den med fokus inverterad
+    fn draw_buttons(&self, writer: &mut crate::vga_buffer::Writer) {
+        if self.buttons.is_empty() {
+            return;
+        }
+
+        let total: usize = self.buttons.iter().map(|label| label.len() + 4).sum::<usize>()
+            + self.buttons.len() - 1;
+        let mut x = self.rect.x + self.rect.width.saturating_sub(total) / 2;
+        let y = self.rect.y + self.rect.height.saturating_sub(2);
+        let (fg, bg) = writer.get_color();
+
+        for (i, label) in self.buttons.iter().enumerate() {
+            let (button_fg, button_bg) = if i == self.focused { (bg, fg) } else { (fg, bg) };
+            writer.write_char_at(x, y, '[', button_fg, button_bg);
+            writer.write_char_at(x + 1, y, ' ', button_fg, button_bg);
+            for (offset, c) in label.chars().enumerate() {
+                writer.write_char_at(x + 2 + offset, y, c, button_fg, button_bg);
+            }
+            writer.write_char_at(x + 2 + label.len(), y, ' ', button_fg, button_bg);
+            writer.write_char_at(x + 3 + label.len(), y, ']', button_fg, button_bg);
+            x += label.len() + 5;
+        }
+    }
+
+    /// Lägg tillbaka det som låg under fönstret
+    fn restore(&mut self) {
+        if let Some(region) = self.saved_under.take() {
+            WRITER.lock().restore_region(&region);
+        }
+    }
+}
+
+impl Component for Window {
+    fn render(&self) {
+        draw_box(self.rect, self.border, Some(&self.title));
+
+        // Rita meddelandet innanför ramen, avkortat till fönstrets bredd
+        let mut writer = WRITER.lock();
+        let (fg, bg) = writer.get_color();
+
+        let inner_width = self.rect.width.saturating_sub(4);
+        let inner_height = self.rect.height.saturating_sub(4);
+        for (i, line) in self.message.lines().take(inner_height).enumerate() {
+            draw_text_fitted(&mut *writer, self.rect.x + 2, self.rect.y + 2 + i, inner_width, line, fg, bg);
+        }
+
+        self.draw_buttons(&mut writer);
+
+        if self.shadow {
+            draw_shadow(&mut writer, self.rect);
+        }
+    }
+
+    /// Tab och pilarna flyttar fokus mellan knapparna. Enter och Escape
+    /// hanteras av fönsterhanteraren, som vet vad de ska leda till.
+    fn handle_input(&mut self, key: u8) -> bool {
+        if self.buttons.is_empty() {
+            return false;
+        }
+        match dialog_action(DecodedKey::Unicode(key as char), self.focused, self.buttons.len()) {
+            DialogAction::Focus(focused) => {
+                self.focused = focused;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn get_bounds(&self) -> Rect {
+        self.rect
+    }
+}
+
+/// Mörka ner kolumnen till höger om och raden under fönstret, klippt
+/// vid skärmkanten
+fn draw_shadow(writer: &mut crate::vga_buffer::Writer, rect: Rect) {
+    let right = rect.x + rect.width;
+    let bottom = rect.y + rect.height;
+
+    if right < BUFFER_WIDTH {
+        for y in rect.y + 1..=bottom.min(BUFFER_HEIGHT - 1) {
+            writer.set_color_at(right, y, Color::DarkGray, Color::Black);
+        }
+    }
+    if bottom < BUFFER_HEIGHT {
+        for x in rect.x + 1..=right.min(BUFFER_WIDTH - 1) {
+            writer.set_color_at(x, bottom, Color::DarkGray, Color::Black);
+        }
+    }
+}
+
+/// Vad en tangent gör i en dialogruta
+#[derive(Debug, PartialEq)]
+enum DialogAction {
+    Focus(usize),
+    Activate(usize),
+    Cancel,
+    Ignore,
+}
+
+// Tab och pilarna flyttar fokus, Enter väljer och Escape avbryter
+fn dialog_action(key: DecodedKey, focused: usize, count: usize) -> DialogAction {
+    match key {
+        DecodedKey::Unicode('\n') => DialogAction::Activate(focused),
+        DecodedKey::Unicode('\u{001B}') => DialogAction::Cancel,
+        DecodedKey::Unicode('\t') | DecodedKey::RawKey(KeyCode::ArrowRight) => {
+            DialogAction::Focus((focused + 1) % count)
+        }
+        DecodedKey::RawKey(KeyCode::ArrowLeft) => DialogAction::Focus((focused + count - 1) % count),
+        _ => DialogAction::Ignore,
+    }
+}
+
+/// Håller ordning på fönster i z-ordning, underst först. Varje fönster
+/// sparar cellerna det täcker så att de kan återställas när det döljs
+/// eller flyttas.
 pub struct WindowManager {
-    // För framtida utökning
+    windows: Vec<Window>,
+    next_id: WindowId,
+    theme: UITheme,
 }
 
 impl WindowManager {
     pub fn new() -> Self {
         Self {
-            // För framtida utökning
+            windows: Vec::new(),
+            next_id: 0,
+            theme: UITheme::dos_classic(),
+        }
+    }
+
+    /// Byt tema för fönster som skapas härefter
+    pub fn set_theme(&mut self, theme: UITheme) {
+        self.theme = theme;
+    }
+
+    /// Använd fönstertemat som hör till ett färgtema
+    pub fn follow_theme(&mut self, theme: Theme) {
+        self.theme = UITheme::for_theme(theme);
+    }
+
+    pub fn show_message(&mut self, title: &str, message: &str) -> WindowId {
+        self.open(title, message, &[])
+    }
+
+    /// Visa en dialogruta och vänta tills användaren väljer en knapp.
+    /// Returnerar knappens index, eller None om dialogen avbröts med Escape.
+    pub fn show_dialog(&mut self, title: &str, message: &str, buttons: &[&str]) -> Option<usize> {
+        if buttons.is_empty() {
+            return None;
         }
+
+        let id = self.open(title, message, buttons);
+        let index = self.windows.len() - 1;
+
+        let choice = loop {
+            // Läs direkt från tangentbordskön, vanliga kortkommandon gäller inte här
+            let key = match crate::keyboard::read_key() {
+                Some(key) => key,
+                None => {
+                    x86_64::instructions::hlt();
+                    continue;
+                }
+            };
+
+            match dialog_action(key, self.windows[index].focused, buttons.len()) {
+                DialogAction::Focus(focused) => {
+                    self.windows[index].focused = focused;
+                    self.restore_from(index);
+                    self.draw_from(index);
+                }
+                DialogAction::Activate(button) => break Some(button),
+                DialogAction::Cancel => break None,
+                DialogAction::Ignore => {}
+            }
+        };
+
+        self.hide(id);
+        choice
+    }
+
+    /// Be användaren skriva en rad text. Returnerar None om Escape trycks.
+    pub fn show_input(&mut self, title: &str, message: &str) -> Option<String> {
+        let mut text = String::new();
+        let id = self.open(title, message, &[]);
+        let index = self.windows.len() - 1;
+        // Inmatningen visas på raden under meddelandet
+        let rect = self.windows[index].rect;
+        let (x, y) = (rect.x + 2, rect.y + rect.height.saturating_sub(3));
+        let width = rect.width.saturating_sub(4);
+
+        let result = loop {
+            {
+                let mut writer = WRITER.lock();
+                let (fg, bg) = writer.get_color();
+                let shown = &text[text.len().saturating_sub(width - 1)..];
+                for col in 0..width {
+                    let c = shown.chars().nth(col).unwrap_or(if col == shown.len() { '_' } else { ' ' });
+                    writer.write_char_at(x + col, y, c, bg, fg);
+                }
+            }
+
+            let key = match crate::keyboard::read_key() {
+                Some(key) => key,
+                None => {
+                    x86_64::instructions::hlt();
+                    continue;
+                }
+            };
+
+            match key {
+                DecodedKey::Unicode('\n') => break Some(text),
+                DecodedKey::Unicode('\u{001B}') => break None,
+                DecodedKey::Unicode('\u{0008}') => {
+                    text.pop();
+                }
+                DecodedKey::Unicode(c) if c.is_ascii() && !c.is_control() => text.push(c),
+                _ => {}
+            }
+        };
+
+        self.hide(id);
+        result
     }
-    
-    pub fn show_message(&mut self, title: &str, message: &str, _theme: Theme) {
-        // Skapa ett enkelt meddelandefönster
+
+    /// Fråga Ja/Nej. Escape räknas som Nej.
+    pub fn show_yes_no(&mut self, title: &str, message: &str) -> bool {
+        self.show_dialog(title, message, &["Yes", "No"]) == Some(0)
+    }
+
+    fn open(&mut self, title: &str, message: &str, buttons: &[&str]) -> WindowId {
+        // Skapa fönstret överst
         let rect = Rect {
             x: 10,
             y: 5,
             width: 40,
             height: 10,
         };
-        
-        draw_box(rect, BorderStyle::Double, Some(title));
-        
-        // Rita meddelandet centrerat i fönstret
-        let mut writer = crate::vga_buffer::WRITER.lock();
-        let saved_row = writer.column_position;
-        let saved_col = writer.row_position;
-        
-        let x = rect.x + 2;
-        let y = rect.y + 2;
-        
-        writer.column_position = y;
-        writer.row_position = x;
-        writer.write_string(message);
-        
-        writer.column_position = saved_row;
-        writer.row_position = saved_col;
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let mut window = Window {
+            id,
+            rect,
+            title: String::from(title),
+            message: String::from(message),
+            buttons: buttons.iter().map(|label| String::from(*label)).collect(),
+            focused: 0,
+            shadow: self.theme.shadow_enabled,
+            border: BorderStyle::Double,
+            saved_under: None,
+        };
+        window.draw();
+        self.windows.push(window);
+
+        id
+    }
+
+    /// Ta bort fönstret och återställ det som låg bakom det
+    pub fn hide(&mut self, id: WindowId) -> bool {
+        let index = match self.index_of(id) {
+            Some(index) => index,
+            None => return false,
+        };
+
+        self.restore_from(index);
+        self.windows.remove(index);
+        self.draw_from(index);
+        true
+    }
+
+    pub fn move_to(&mut self, id: WindowId, x: usize, y: usize) -> bool {
+        self.update_rect(id, |rect| {
+            rect.x = x;
+            rect.y = y;
+        })
+    }
+
+    pub fn resize(&mut self, id: WindowId, width: usize, height: usize) -> bool {
+        self.update_rect(id, |rect| {
+            // En ram behöver minst två rader och kolumner
+            rect.width = width.max(2);
+            rect.height = height.max(2);
+        })
+    }
+
+    /// Byt ramstil på ett öppet fönster
+    pub fn set_border_style(&mut self, id: WindowId, style: BorderStyle) -> bool {
+        self.update_window(id, |window| window.set_border_style(style))
+    }
+
+    /// Rita om alla fönster nerifrån och upp
+    pub fn render_all(&mut self) {
+        self.restore_from(0);
+        self.draw_from(0);
+    }
+
+    fn index_of(&self, id: WindowId) -> Option<usize> {
+        self.windows.iter().position(|window| window.id == id)
+    }
+
+    fn update_rect(&mut self, id: WindowId, update: impl FnOnce(&mut Rect)) -> bool {
+        self.update_window(id, |window| update(&mut window.rect))
+    }
+
+    // Ändra ett fönster och rita om det och allt ovanför
+    fn update_window(&mut self, id: WindowId, update: impl FnOnce(&mut Window)) -> bool {
+        let index = match self.index_of(id) {
+            Some(index) => index,
+            None => return false,
+        };
+
+        self.restore_from(index);
+        update(&mut self.windows[index]);
+        self.draw_from(index);
+        true
+    }
+
+    // Ta bort fönstren från `index` och uppåt från skärmen, överst först,
+    // så att varje sparad yta läggs tillbaka i rätt ordning
+    fn restore_from(&mut self, index: usize) {
+        for window in self.windows[index..].iter_mut().rev() {
+            window.restore();
+        }
+    }
+
+    // Rita fönstren från `index` och uppåt, underst först
+    fn draw_from(&mut self, index: usize) {
+        for window in self.windows[index..].iter_mut() {
+            window.draw();
+        }
     }
 }
 
@@ -44,4 +388,98 @@ impl WindowManager {
 pub fn init() {
     // Lägg till init-kod här senare
     // För tillfället behöver vi bara denna funktion för att kompilera
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screen_text(x: usize, y: usize, len: usize) -> String {
+        let writer = WRITER.lock();
+        (x..x + len).filter_map(|col| writer.read_char_at(col, y)).collect()
+    }
+
+    #[test_case]
+    fn hide_restores_the_screen_behind() {
+        let before = screen_text(10, 7, 40);
+
+        let mut manager = WindowManager::new();
+        let id = manager.show_message("Test", "Hello");
+        assert_eq!(screen_text(12, 7, 5), "Hello");
+
+        assert!(manager.hide(id));
+        assert_eq!(screen_text(10, 7, 40), before);
+    }
+
+    #[test_case]
+    fn overlapping_windows_restore_in_order() {
+        let before = screen_text(10, 7, 40);
+
+        let mut manager = WindowManager::new();
+        let bottom = manager.show_message("Bottom", "Under");
+        let top = manager.show_message("Top", "Over");
+
+        // Hiding the bottom window keeps the top one intact
+        assert!(manager.hide(bottom));
+        assert_eq!(screen_text(12, 7, 4), "Over");
+
+        assert!(manager.hide(top));
+        assert_eq!(screen_text(10, 7, 40), before);
+    }
+
+    #[test_case]
+    fn shadow_is_drawn_and_removed() {
+        let before = WRITER.lock().save_region(0, 0, BUFFER_WIDTH, BUFFER_HEIGHT);
+
+        let mut manager = WindowManager::new();
+        let id = manager.show_message("Shadow", "");
+        // The window spans x 10..50 and y 5..15, the shadow sits just outside
+        let after = WRITER.lock().save_region(0, 0, BUFFER_WIDTH, BUFFER_HEIGHT);
+        assert!(after.color_at(50, 6) == Some((Color::DarkGray, Color::Black)));
+        assert!(after.color_at(11, 15) == Some((Color::DarkGray, Color::Black)));
+
+        manager.hide(id);
+        let restored = WRITER.lock().save_region(0, 0, BUFFER_WIDTH, BUFFER_HEIGHT);
+        assert!(restored.color_at(50, 6) == before.color_at(50, 6));
+        assert!(restored.color_at(11, 15) == before.color_at(11, 15));
+    }
+
+    #[test_case]
+    fn border_style_can_be_changed() {
+        let mut manager = WindowManager::new();
+        let id = manager.show_message("Box", "");
+        assert_eq!(WRITER.lock().read_char_at(10, 5), Some('\u{C9}'));
+
+        assert!(manager.set_border_style(id, BorderStyle::Rounded));
+        assert_eq!(WRITER.lock().read_char_at(10, 5), Some('.'));
+        assert_eq!(WRITER.lock().read_char_at(10, 14), Some('\''));
+
+        manager.hide(id);
+    }
+
+    #[test_case]
+    fn dialog_keys_move_focus_and_choose() {
+        let tab = DecodedKey::Unicode('\t');
+        assert_eq!(dialog_action(tab, 0, 3), DialogAction::Focus(1));
+        assert_eq!(dialog_action(tab, 2, 3), DialogAction::Focus(0));
+        assert_eq!(dialog_action(DecodedKey::RawKey(KeyCode::ArrowLeft), 0, 3), DialogAction::Focus(2));
+        assert_eq!(dialog_action(DecodedKey::Unicode('\n'), 1, 3), DialogAction::Activate(1));
+        assert_eq!(dialog_action(DecodedKey::Unicode('\u{001B}'), 1, 3), DialogAction::Cancel);
+        assert_eq!(dialog_action(DecodedKey::Unicode('x'), 1, 3), DialogAction::Ignore);
+    }
+
+    #[test_case]
+    fn window_component_moves_focus_with_tab() {
+        let mut manager = WindowManager::new();
+        let id = manager.open("Buttons", "", &["Ok", "Cancel"]);
+        let window = &mut manager.windows[0];
+
+        assert!(window.handle_input(b'\t'));
+        assert_eq!(window.focused, 1);
+        // Enter is left to the window manager
+        assert!(!window.handle_input(b'\n'));
+        assert_eq!(window.get_bounds().width, window.rect.width);
+
+        manager.hide(id);
+    }
+}
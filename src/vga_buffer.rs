@@ -1,16 +1,57 @@
 use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicU8, Ordering};
 use spin::Mutex;
 use x86_64::instructions::interrupts;
+use x86_64::instructions::port::Port;
 use crate::simple_fs::SimpleString;
+use alloc::vec::Vec;
+use alloc::collections::VecDeque;
+use self::ansi::{ansi_color, AnsiAction, AnsiParams, AnsiParser};
 
-const BUFFER_HEIGHT: usize = 25;
-const BUFFER_WIDTH: usize = 80;
+mod ansi;
+
+pub const BUFFER_HEIGHT: usize = 25;
+pub const BUFFER_WIDTH: usize = 80;
+
+// Number of lines kept after they scroll off the top of the screen
+const SCROLLBACK_LINES: usize = 200;
+
+// VGA CRT controller index/data ports
+const CRTC_INDEX_PORT: u16 = 0x3D4;
+const CRTC_DATA_PORT: u16 = 0x3D5;
+
+// VGA DAC palette write index/data ports
+const DAC_WRITE_INDEX_PORT: u16 = 0x3C8;
+const DAC_DATA_PORT: u16 = 0x3C9;
+
+// DAC register used by each of the 16 text colors with the default
+// attribute controller setup (brown and the bright colors are remapped)
+const DAC_INDEX: [u8; 16] = [0, 1, 2, 3, 4, 5, 20, 7, 56, 57, 58, 59, 60, 61, 62, 63];
+
+// Default 16-color text palette as 6-bit RGB
+const DEFAULT_PALETTE: [(u8, u8, u8); 16] = [
+    (0, 0, 0), (0, 0, 42), (0, 42, 0), (0, 42, 42),
+    (42, 0, 0), (42, 0, 42), (42, 21, 0), (42, 42, 42),
+    (21, 21, 21), (21, 21, 63), (21, 63, 21), (21, 63, 63),
+    (63, 21, 21), (63, 21, 63), (63, 63, 21), (63, 63, 63),
+];
 
 pub static WRITER: Mutex<Writer> = Mutex::new(Writer {
     buffer: unsafe { &mut *(0xb8000 as *mut Buffer) },
-    x: 0,
-    y: 0,
+    column_position: 0,
+    row_position: 0,
     color_code: ColorCode::new(Color::White, Color::Black),
+    word_wrap: false,
+    word_buffer: [0; BUFFER_WIDTH],
+    word_len: 0,
+    wrapped: false,
+    scrollback: VecDeque::new(),
+    scroll_offset: 0,
+    live_screen: None,
+    crt_effect_enabled: false,
+    crt_rows: None,
+    ansi: AnsiParser::new(),
+    ansi_base: None,
 });
 
 #[macro_export]
@@ -19,8 +60,10 @@ macro_rules! _print {
 }
 
 pub fn _print(args: fmt::Arguments) {
-    use core::fmt::Write;
-    WRITER.lock().write_fmt(args).unwrap();
+    // Keep interrupt handlers that print from deadlocking on WRITER
+    interrupts::without_interrupts(|| {
+        WRITER.lock().write_fmt(args).unwrap();
+    });
 }
 
 pub fn _format(args: fmt::Arguments) -> SimpleString {
@@ -29,39 +72,10 @@ pub fn _format(args: fmt::Arguments) -> SimpleString {
     s
 }
 
-#[derive(Debug, Clone)]
-pub struct SimpleString {
-    data: [u8; 256],
-    len: usize,
-}
-
-impl SimpleString {
-    pub fn new() -> Self {
-        SimpleString {
-            data: [0; 256],
-            len: 0,
-        }
-    }
-
-    pub fn push_str(&mut self, s: &str) {
-        for &byte in s.as_bytes() {
-            if self.len < 256 {
-                self.data[self.len] = byte;
-                self.len += 1;
-            }
-        }
-    }
-
-    pub fn as_str(&self) -> &str {
-        core::str::from_utf8(&self.data[..self.len]).unwrap_or("")
-    }
-}
-
-impl Write for SimpleString {
-    fn write_str(&mut self, s: &str) -> fmt::Result {
-        self.push_str(s);
-        Ok(())
-    }
+/// Replace the contents of `buf` with the formatted text, keeping its allocation
+pub fn format_into(buf: &mut SimpleString, args: fmt::Arguments) {
+    buf.clear();
+    buf.write_fmt(args).unwrap();
 }
 
 #[allow(dead_code)]
@@ -86,14 +100,113 @@ pub enum Color {
     White = 15,
 }
 
+impl Color {
+    /// Convert a 4-bit VGA color index back to a `Color`
+    pub fn from_index(index: u8) -> Color {
+        match index & 0x0F {
+            0 => Color::Black,
+            1 => Color::Blue,
+            2 => Color::Green,
+            3 => Color::Cyan,
+            4 => Color::Red,
+            5 => Color::Magenta,
+            6 => Color::Brown,
+            7 => Color::LightGray,
+            8 => Color::DarkGray,
+            9 => Color::LightBlue,
+            10 => Color::LightGreen,
+            11 => Color::LightCyan,
+            12 => Color::LightRed,
+            13 => Color::Pink,
+            14 => Color::Yellow,
+            _ => Color::White,
+        }
+    }
+}
+
+/// Built-in screen color schemes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThemeStyle {
+    DOSClassic,     // Light gray on black
+    AmberTerminal,  // Amber phosphor monitor
+    GreenCRT,       // Green phosphor monitor
+    Modern,         // White on blue
+}
+
+/// Named color themes that can be switched at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Theme {
+    Classic = 0,
+    Modern = 1,
+    Green = 2,
+    Amber = 3,
+}
+
+pub const THEME_COUNT: u8 = 4;
+
+impl Theme {
+    pub fn from_index(index: u8) -> Option<Theme> {
+        match index {
+            0 => Some(Theme::Classic),
+            1 => Some(Theme::Modern),
+            2 => Some(Theme::Green),
+            3 => Some(Theme::Amber),
+            _ => None,
+        }
+    }
+
+    /// Foreground and background colors of the theme
+    pub fn colors(self) -> (Color, Color) {
+        match self {
+            Theme::Classic => (Color::LightGray, Color::Black),
+            Theme::Modern => (Color::White, Color::Blue),
+            // Bright text, so scanlines have a darker shade to drop to
+            Theme::Green => (Color::LightGreen, Color::Black),
+            Theme::Amber => (Color::Yellow, Color::Black),
+        }
+    }
+
+    pub fn has_crt_effect(self) -> bool {
+        matches!(self, Theme::Green | Theme::Amber)
+    }
+}
+
+impl From<ThemeStyle> for Theme {
+    fn from(style: ThemeStyle) -> Theme {
+        match style {
+            ThemeStyle::DOSClassic => Theme::Classic,
+            ThemeStyle::AmberTerminal => Theme::Amber,
+            ThemeStyle::GreenCRT => Theme::Green,
+            ThemeStyle::Modern => Theme::Modern,
+        }
+    }
+}
+
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", get_theme_name(*self as u8))
+    }
+}
+
+static CURRENT_THEME: AtomicU8 = AtomicU8::new(Theme::Classic as u8);
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(transparent)]
-struct ColorCode(u8);
+pub struct ColorCode(u8);
 
 impl ColorCode {
-    fn new(foreground: Color, background: Color) -> ColorCode {
+    const fn new(foreground: Color, background: Color) -> ColorCode {
         ColorCode((background as u8) << 4 | (foreground as u8))
     }
+
+    pub fn foreground(self) -> Color {
+        Color::from_index(self.0)
+    }
+
+    pub fn background(self) -> Color {
+        Color::from_index(self.0 >> 4)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -108,17 +221,403 @@ struct Buffer {
     chars: [[ScreenChar; BUFFER_WIDTH]; BUFFER_HEIGHT],
 }
 
-pub struct Writer {
-    buffer: &'static mut Buffer,
+/// A snapshot of the cells covered by a screen region
+pub struct SavedRegion {
     x: usize,
     y: usize,
+    width: usize,
+    height: usize,
+    cells: Vec<ScreenChar>,
+}
+
+impl SavedRegion {
+    /// Foreground and background of a saved cell, in screen coordinates
+    pub fn color_at(&self, x: usize, y: usize) -> Option<(Color, Color)> {
+        if x < self.x || y < self.y || x >= self.x + self.width || y >= self.y + self.height {
+            return None;
+        }
+        let code = self.cells[(y - self.y) * self.width + (x - self.x)].color_code;
+        Some((code.foreground(), code.background()))
+    }
+}
+
+pub struct Writer {
+    buffer: &'static mut Buffer,
+    pub column_position: usize,
+    pub row_position: usize,
     color_code: ColorCode,
+    word_wrap: bool,
+    word_buffer: [u8; BUFFER_WIDTH], // Current word while word wrap is on
+    word_len: usize,
+    wrapped: bool,                   // Last line break was inserted by wrapping
+    scrollback: VecDeque<[ScreenChar; BUFFER_WIDTH]>, // Lines evicted by new_line
+    scroll_offset: usize,            // How many lines the view is scrolled back
+    live_screen: Option<SavedRegion>, // Live screen while viewing history
+    crt_effect_enabled: bool,
+    crt_rows: Option<(usize, usize)>, // Rows written since the last scanline refresh
+    ansi: AnsiParser,                // Escape sequences in write_string
+    ansi_base: Option<ColorCode>,    // Colors to go back to on ESC[0m
 }
 
 impl Writer {
+    pub fn get_position(&self) -> (usize, usize) {
+        (self.column_position, self.row_position)
+    }
+
+    pub fn set_color(&mut self, foreground: Color, background: Color) {
+        self.color_code = ColorCode::new(foreground, background);
+    }
+
+    pub fn get_color(&self) -> (Color, Color) {
+        (self.color_code.foreground(), self.color_code.background())
+    }
+
+    /// Blank the whole screen with the current colors and home the cursor
+    pub fn clear_screen(&mut self) {
+        self.snap_to_bottom();
+        for row in 0..BUFFER_HEIGHT {
+            self.clear_row(row);
+        }
+        self.column_position = 0;
+        self.row_position = 0;
+        self.update_cursor();
+
+        if self.crt_effect_enabled {
+            self.refresh_crt();
+        }
+    }
+
+    /// Move every cell from the `old` colors to the current ones. Cells drawn
+    /// in the old foreground take the new one; highlighted text keeps its color.
+    pub fn recolor_screen(&mut self, old: ColorCode) {
+        let (foreground, background) = self.get_color();
+        let old_foreground = old.foreground();
+
+        for row in 0..BUFFER_HEIGHT {
+            for col in 0..BUFFER_WIDTH {
+                let cell = &mut self.buffer.chars[row][col];
+                let cell_foreground = if cell.color_code.foreground() == old_foreground {
+                    foreground
+                } else {
+                    cell.color_code.foreground()
+                };
+                cell.color_code = ColorCode::new(cell_foreground, background);
+            }
+        }
+    }
+
+    /// Turn the CRT effect on or off. Enabling installs a slightly
+    /// desaturated palette and scanlines; disabling restores the defaults.
+    pub fn set_crt_effect(&mut self, enabled: bool) {
+        // Palette reprogramming is skipped while recovering from errors
+        let enabled = enabled && !crate::error_handler::is_in_safe_mode();
+        self.crt_effect_enabled = enabled;
+
+        for (color, &(r, g, b)) in DEFAULT_PALETTE.iter().enumerate() {
+            let rgb = if enabled { crt_color(r, g, b) } else { (r, g, b) };
+            set_palette_entry(DAC_INDEX[color], rgb);
+        }
+
+        self.refresh_crt();
+    }
+
+    /// Reapply the scanline effect: text on every odd row gets a darker
+    /// foreground, even rows get the normal one back (rows move when the
+    /// screen scrolls). The CRT themes have black backgrounds, which have
+    /// no darker shade, so it is the text that dims.
+    pub fn refresh_crt(&mut self) {
+        self.refresh_crt_rows(0, BUFFER_HEIGHT - 1);
+    }
+
+    // The scanline effect for rows `first` through `last` only
+    fn refresh_crt_rows(&mut self, first: usize, last: usize) {
+        let base = self.color_code.foreground();
+        let dimmed = dim_color(base);
+
+        for row in first..=last.min(BUFFER_HEIGHT - 1) {
+            let foreground = if self.crt_effect_enabled && row % 2 == 1 { dimmed } else { base };
+            for col in 0..BUFFER_WIDTH {
+                let cell = &mut self.buffer.chars[row][col];
+                let cell_foreground = cell.color_code.foreground();
+                if cell_foreground == base || cell_foreground == dimmed {
+                    cell.color_code = ColorCode::new(foreground, cell.color_code.background());
+                }
+            }
+        }
+    }
+
+    pub fn is_crt_effect_enabled(&self) -> bool {
+        self.crt_effect_enabled
+    }
+
+    pub fn write_byte(&mut self, byte: u8) {
+        match byte {
+            b'\n' => self.new_line(),
+            0x08 => {
+                if self.column_position > 0 {
+                    self.column_position -= 1;
+                }
+            },
+            byte => {
+                if self.column_position >= BUFFER_WIDTH {
+                    self.new_line();
+                }
+
+                self.buffer.chars[self.row_position][self.column_position] = ScreenChar {
+                    ascii_character: byte,
+                    color_code: self.color_code,
+                };
+                self.column_position += 1;
+                self.mark_crt_rows(self.row_position, self.row_position);
+            }
+        }
+        self.wrapped = false;
+    }
+
+    pub fn write_string(&mut self, s: &str) {
+        self.snap_to_bottom();
+
+        for byte in s.bytes() {
+            let byte = match self.ansi.feed(byte) {
+                AnsiAction::Print(byte) => byte,
+                AnsiAction::None => continue,
+                action => {
+                    self.flush_word();
+                    self.apply_ansi(action);
+                    continue;
+                }
+            };
+            let byte = match byte {
+                // Printable ASCII byte, newline or backspace
+                0x20..=0x7e | b'\n' | 0x08 => byte,
+                // Not part of printable ASCII range
+                _ => 0xfe,
+            };
+
+            if self.word_wrap {
+                self.write_wrapped_byte(byte);
+            } else {
+                self.write_byte(byte);
+            }
+        }
+        self.flush_word();
+        self.update_cursor();
+
+        // Only the rows this call touched, scrolling marks them all
+        if let Some((first, last)) = self.crt_rows.take() {
+            if self.crt_effect_enabled {
+                self.refresh_crt_rows(first, last);
+            }
+        }
+    }
+
+    // Remember rows that need the scanline effect reapplied
+    fn mark_crt_rows(&mut self, first: usize, last: usize) {
+        self.crt_rows = Some(match self.crt_rows {
+            Some((old_first, old_last)) => (old_first.min(first), old_last.max(last)),
+            None => (first, last),
+        });
+    }
+
+    fn apply_ansi(&mut self, action: AnsiAction) {
+        match action {
+            AnsiAction::SetGraphics(params) => self.set_graphics(params),
+            AnsiAction::MoveCursor(params) => {
+                let row = usize::from(params.get_or(0, 1)).min(BUFFER_HEIGHT);
+                let col = usize::from(params.get_or(1, 1)).min(BUFFER_WIDTH);
+                self.row_position = row - 1;
+                self.column_position = col - 1;
+            },
+            _ => {}
+        }
+    }
+
+    // Colors from `ESC [ ... m`. A reset goes back to the colors that were
+    // set before the first sequence, not to a fixed default.
+    fn set_graphics(&mut self, params: AnsiParams) {
+        // ESC[m and ESC[0m
+        if params.iter().all(|param| param == 0) {
+            self.reset_graphics();
+            return;
+        }
+
+        let base = *self.ansi_base.get_or_insert(self.color_code);
+        let (mut fg, mut bg) = self.get_color();
+        for param in params.iter() {
+            match param {
+                0 => {
+                    fg = base.foreground();
+                    bg = base.background();
+                },
+                // Bold shows as the bright variant of the color
+                1 => fg = Color::from_index(fg as u8 | 8),
+                30..=37 => fg = ansi_color(param - 30, false),
+                39 => fg = base.foreground(),
+                40..=47 => bg = ansi_color(param - 40, false),
+                49 => bg = base.background(),
+                90..=97 => fg = ansi_color(param - 90, true),
+                100..=107 => bg = ansi_color(param - 100, true),
+                _ => {}
+            }
+        }
+        self.set_color(fg, bg);
+    }
+
+    fn reset_graphics(&mut self) {
+        if let Some(base) = self.ansi_base.take() {
+            self.color_code = base;
+        }
+    }
+
+    /// Enable or disable word wrapping in `write_string`
+    pub fn set_word_wrap(&mut self, enabled: bool) {
+        self.flush_word();
+        self.word_wrap = enabled;
+    }
+
+    fn write_wrapped_byte(&mut self, byte: u8) {
+        match byte {
+            b' ' => {
+                self.flush_word();
+                if self.column_position >= BUFFER_WIDTH {
+                    // Line is full: wrap here and drop the space
+                    self.new_line();
+                    self.wrapped = true;
+                } else if !(self.wrapped && self.column_position == 0) {
+                    self.write_byte(b' ');
+                }
+            },
+            b'\n' | 0x08 => {
+                self.flush_word();
+                self.write_byte(byte);
+            },
+            byte => {
+                // A word as wide as the screen can't be wrapped, hard-break it
+                if self.word_len == BUFFER_WIDTH {
+                    self.flush_word();
+                }
+                self.word_buffer[self.word_len] = byte;
+                self.word_len += 1;
+            }
+        }
+    }
+
+    // Emit the buffered word, moving to a new line first if it won't fit
+    fn flush_word(&mut self) {
+        if self.word_len == 0 {
+            return;
+        }
+
+        if self.column_position > 0 && self.column_position + self.word_len > BUFFER_WIDTH {
+            self.new_line();
+        }
+
+        for i in 0..self.word_len {
+            let byte = self.word_buffer[i];
+            self.write_byte(byte);
+        }
+        self.word_len = 0;
+    }
+
+    fn new_line(&mut self) {
+        if self.row_position < BUFFER_HEIGHT - 1 {
+            self.row_position += 1;
+        } else {
+            // Keep the evicted top line in the scrollback history
+            if self.scrollback.len() == SCROLLBACK_LINES {
+                self.scrollback.pop_front();
+            }
+            self.scrollback.push_back(self.buffer.chars[0]);
+
+            for row in 1..BUFFER_HEIGHT {
+                for col in 0..BUFFER_WIDTH {
+                    let character = self.buffer.chars[row][col];
+                    self.buffer.chars[row - 1][col] = character;
+                }
+            }
+            self.clear_row(BUFFER_HEIGHT - 1);
+            // Every row moved to the other kind of scanline
+            self.mark_crt_rows(0, BUFFER_HEIGHT - 1);
+        }
+        self.column_position = 0;
+    }
+
+    /// Scroll the view back through the history by `lines`
+    pub fn scroll_up(&mut self, lines: usize) {
+        if self.scrollback.is_empty() {
+            return;
+        }
+
+        if self.live_screen.is_none() {
+            self.live_screen = Some(self.save_region(0, 0, BUFFER_WIDTH, BUFFER_HEIGHT));
+        }
+
+        self.scroll_offset = (self.scroll_offset + lines).min(self.scrollback.len());
+        self.repaint_scrollback();
+    }
+
+    /// Scroll the view forward towards the live screen by `lines`
+    pub fn scroll_down(&mut self, lines: usize) {
+        if self.scroll_offset == 0 {
+            return;
+        }
+
+        self.scroll_offset = self.scroll_offset.saturating_sub(lines);
+        if self.scroll_offset == 0 {
+            self.snap_to_bottom();
+        } else {
+            self.repaint_scrollback();
+        }
+    }
+
+    /// Leave the scrollback view and show the live screen again
+    pub fn snap_to_bottom(&mut self) {
+        if let Some(live) = self.live_screen.take() {
+            self.restore_region(&live);
+        }
+        self.scroll_offset = 0;
+    }
+
+    pub fn is_scrolled_back(&self) -> bool {
+        self.scroll_offset > 0
+    }
+
+    // Draw the window of history + live lines that ends `scroll_offset` above the bottom
+    fn repaint_scrollback(&mut self) {
+        let live = match &self.live_screen {
+            Some(live) => live,
+            None => return,
+        };
+
+        let history_len = self.scrollback.len();
+        let first_line = history_len - self.scroll_offset;
+
+        for row in 0..BUFFER_HEIGHT {
+            let line = first_line + row;
+            for col in 0..BUFFER_WIDTH {
+                let cell = if line < history_len {
+                    self.scrollback[line][col]
+                } else {
+                    live.cells[(line - history_len) * BUFFER_WIDTH + col]
+                };
+                self.buffer.chars[row][col] = cell;
+            }
+        }
+    }
+
+    fn clear_row(&mut self, row: usize) {
+        let blank = ScreenChar {
+            ascii_character: b' ',
+            color_code: self.color_code,
+        };
+        for col in 0..BUFFER_WIDTH {
+            self.buffer.chars[row][col] = blank;
+        }
+    }
+
     pub fn set_position(&mut self, x: usize, y: usize) {
-        self.x = x;
-        self.y = y;
+        self.column_position = x;
+        self.row_position = y;
         self.update_cursor();
     }
 
@@ -134,13 +633,343 @@ impl Writer {
         };
     }
 
+    /// Change the colors of a screen cell, keeping its character
+    pub fn set_color_at(&mut self, x: usize, y: usize, fg: Color, bg: Color) {
+        if x >= BUFFER_WIDTH || y >= BUFFER_HEIGHT {
+            return;
+        }
+
+        self.buffer.chars[y][x].color_code = ColorCode::new(fg, bg);
+    }
+
+    /// Read back the character stored at a screen cell
+    pub fn read_char_at(&self, x: usize, y: usize) -> Option<char> {
+        if x >= BUFFER_WIDTH || y >= BUFFER_HEIGHT {
+            return None;
+        }
+
+        Some(self.buffer.chars[y][x].ascii_character as char)
+    }
+
+    /// Read back the character and colors stored at a screen cell
+    pub fn read_cell_at(&self, x: usize, y: usize) -> Option<(u8, Color, Color)> {
+        if x >= BUFFER_WIDTH || y >= BUFFER_HEIGHT {
+            return None;
+        }
+
+        let cell = self.buffer.chars[y][x];
+        Some((cell.ascii_character, cell.color_code.foreground(), cell.color_code.background()))
+    }
+
     pub fn set_cursor_position(&mut self, x: usize, y: usize) {
-        self.x = x;
-        self.y = y;
+        self.column_position = x;
+        self.row_position = y;
         self.update_cursor();
     }
 
+    /// Copy the cells of a screen region so they can be put back later
+    pub fn save_region(&self, x: usize, y: usize, width: usize, height: usize) -> SavedRegion {
+        let width = width.min(BUFFER_WIDTH.saturating_sub(x));
+        let height = height.min(BUFFER_HEIGHT.saturating_sub(y));
+
+        let mut cells = Vec::with_capacity(width * height);
+        for row in y..y + height {
+            for col in x..x + width {
+                cells.push(self.buffer.chars[row][col]);
+            }
+        }
+
+        SavedRegion { x, y, width, height, cells }
+    }
+
+    /// Write a previously saved region back to the screen
+    pub fn restore_region(&mut self, region: &SavedRegion) {
+        for row in 0..region.height {
+            for col in 0..region.width {
+                self.buffer.chars[region.y + row][region.x + col] = region.cells[row * region.width + col];
+            }
+        }
+    }
+
+    /// Move the blinking hardware cursor to the logical position
     pub fn update_cursor(&mut self) {
-        // Implementation of update_cursor method
+        let position = (self.row_position * BUFFER_WIDTH + self.column_position.min(BUFFER_WIDTH - 1)) as u16;
+
+        interrupts::without_interrupts(|| {
+            let mut index = Port::<u8>::new(CRTC_INDEX_PORT);
+            let mut data = Port::<u8>::new(CRTC_DATA_PORT);
+            unsafe {
+                index.write(0x0F);
+                data.write((position & 0xFF) as u8);
+                index.write(0x0E);
+                data.write((position >> 8) as u8);
+            }
+        });
     }
-} 
\ No newline at end of file
+
+    /// Show the hardware cursor spanning the given scanlines (0-15).
+    /// 0..15 gives a block cursor, 14..15 an underline.
+    pub fn enable_cursor(&mut self, start_scanline: u8, end_scanline: u8) {
+        interrupts::without_interrupts(|| {
+            let mut index = Port::<u8>::new(CRTC_INDEX_PORT);
+            let mut data = Port::<u8>::new(CRTC_DATA_PORT);
+            unsafe {
+                index.write(0x0A);
+                let start = data.read();
+                data.write((start & 0xC0) | (start_scanline & 0x1F));
+                index.write(0x0B);
+                let end = data.read();
+                data.write((end & 0xE0) | (end_scanline & 0x1F));
+            }
+        });
+    }
+
+    /// Hide the hardware cursor
+    pub fn disable_cursor(&mut self) {
+        interrupts::without_interrupts(|| {
+            let mut index = Port::<u8>::new(CRTC_INDEX_PORT);
+            let mut data = Port::<u8>::new(CRTC_DATA_PORT);
+            unsafe {
+                index.write(0x0A);
+                data.write(0x20);
+            }
+        });
+    }
+}
+
+impl fmt::Write for Writer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_string(s);
+        Ok(())
+    }
+}
+
+// The next darker shade of a color, used for CRT scanlines
+fn dim_color(color: Color) -> Color {
+    let index = color as u8;
+    if index >= 8 {
+        Color::from_index(index - 8)
+    } else {
+        Color::Black
+    }
+}
+
+// Pull a palette color a quarter of the way towards gray and dim it slightly
+fn crt_color(r: u8, g: u8, b: u8) -> (u8, u8, u8) {
+    let gray = (r as u16 + g as u16 + b as u16) / 3;
+    let adjust = |c: u8| -> u8 {
+        let c = c as u16;
+        let desaturated = (c * 3 + gray) / 4;
+        (desaturated * 7 / 8) as u8
+    };
+    (adjust(r), adjust(g), adjust(b))
+}
+
+fn set_palette_entry(index: u8, (r, g, b): (u8, u8, u8)) {
+    interrupts::without_interrupts(|| {
+        let mut index_port = Port::<u8>::new(DAC_WRITE_INDEX_PORT);
+        let mut data_port = Port::<u8>::new(DAC_DATA_PORT);
+        unsafe {
+            index_port.write(index);
+            data_port.write(r);
+            data_port.write(g);
+            data_port.write(b);
+        }
+    });
+}
+
+/// Clear the screen using the current colors
+pub fn clear_screen() {
+    interrupts::without_interrupts(|| {
+        WRITER.lock().clear_screen();
+    });
+}
+
+/// Set the colors used for all subsequent output
+pub fn set_global_color(foreground: Color, background: Color) {
+    interrupts::without_interrupts(|| {
+        WRITER.lock().set_color(foreground, background);
+    });
+}
+
+/// Switch to one of the built-in color schemes and clear the screen
+pub fn change_theme(style: ThemeStyle) {
+    set_theme(Theme::from(style));
+    clear_screen();
+}
+
+/// Switch theme, repainting the text already on screen in the new colors
+pub fn set_theme(theme: Theme) {
+    let (foreground, background) = theme.colors();
+    CURRENT_THEME.store(theme as u8, Ordering::Relaxed);
+
+    interrupts::without_interrupts(|| {
+        let mut writer = WRITER.lock();
+        // Undim the scanlines first so recoloring sees the plain foreground
+        writer.set_crt_effect(false);
+        let old = writer.color_code;
+        writer.set_color(foreground, background);
+        writer.recolor_screen(old);
+        writer.set_crt_effect(theme.has_crt_effect());
+    });
+}
+
+pub fn get_current_theme() -> Theme {
+    Theme::from_index(CURRENT_THEME.load(Ordering::Relaxed)).unwrap_or(Theme::Classic)
+}
+
+pub fn get_theme_name(index: u8) -> &'static str {
+    match Theme::from_index(index) {
+        Some(Theme::Classic) => "DOS Classic",
+        Some(Theme::Modern) => "Modern",
+        Some(Theme::Green) => "Green CRT",
+        Some(Theme::Amber) => "Amber Terminal",
+        None => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn format_keeps_long_output() {
+        let line = "0123456789".repeat(30);
+        let formatted = crate::format!("[{}]", line);
+        assert_eq!(formatted.len(), 302);
+        assert!(formatted.as_str().ends_with("789]"));
+    }
+
+    #[test_case]
+    fn format_into_replaces_contents() {
+        let mut buf = SimpleString::new();
+        crate::format_into!(&mut buf, "{}", "x".repeat(300));
+        crate::format_into!(&mut buf, "Ln {}", 12);
+        assert_eq!(buf.as_str(), "Ln 12");
+    }
+
+    #[test_case]
+    fn ansi_colors_are_applied_and_reset() {
+        let mut writer = WRITER.lock();
+        let before = writer.get_color();
+        writer.write_string("\n\x1B[91mX\x1B[0mY");
+
+        let (col, row) = writer.get_position();
+        let line = writer.save_region(0, row, BUFFER_WIDTH, 1);
+        assert_eq!(writer.read_char_at(col - 2, row), Some('X'));
+        assert_eq!(line.color_at(col - 2, row).map(|(fg, _)| fg), Some(Color::LightRed));
+        assert_eq!(line.color_at(col - 1, row), Some(before));
+        assert_eq!(writer.get_color(), before);
+    }
+
+    #[test_case]
+    fn ansi_cursor_move_is_one_based() {
+        let mut writer = WRITER.lock();
+        writer.write_string("\x1B[3;7H");
+        assert_eq!(writer.get_position(), (6, 2));
+        // Out of range values stop at the screen edge
+        writer.write_string("\x1B[99;999H\n");
+        assert_eq!(writer.get_position().1, BUFFER_HEIGHT - 1);
+    }
+
+    #[test_case]
+    fn write_string_fills_cells_with_current_colors() {
+        let mut writer = WRITER.lock();
+        let before = writer.get_color();
+        writer.set_color(Color::Yellow, Color::Blue);
+        writer.set_position(10, 5);
+        writer.write_string("Hi");
+        writer.set_color(before.0, before.1);
+
+        assert_eq!(writer.read_cell_at(10, 5), Some((b'H', Color::Yellow, Color::Blue)));
+        assert_eq!(writer.read_cell_at(11, 5), Some((b'i', Color::Yellow, Color::Blue)));
+        assert_eq!(writer.get_position(), (12, 5));
+    }
+
+    #[test_case]
+    fn cells_are_addressed_column_then_row() {
+        let mut writer = WRITER.lock();
+        writer.write_char_at(3, 7, 'c', Color::White, Color::Black);
+        writer.write_char_at(7, 3, 'r', Color::White, Color::Black);
+        assert_eq!(writer.read_cell_at(3, 7).map(|(c, _, _)| c), Some(b'c'));
+        assert_eq!(writer.read_cell_at(7, 3).map(|(c, _, _)| c), Some(b'r'));
+        assert_eq!(writer.read_cell_at(BUFFER_WIDTH, 0), None);
+        assert_eq!(writer.read_cell_at(0, BUFFER_HEIGHT), None);
+    }
+
+    #[test_case]
+    fn new_line_on_last_row_scrolls_up() {
+        let mut writer = WRITER.lock();
+        writer.set_position(0, BUFFER_HEIGHT - 1);
+        writer.write_string("A\nB");
+
+        let last = BUFFER_HEIGHT - 1;
+        assert_eq!(writer.read_char_at(0, last - 1), Some('A'));
+        assert_eq!(writer.read_char_at(0, last), Some('B'));
+        assert!((1..BUFFER_WIDTH).all(|x| writer.read_char_at(x, last) == Some(' ')));
+        assert_eq!(writer.get_position(), (1, last));
+    }
+
+    #[test_case]
+    fn crt_refresh_only_touches_written_rows() {
+        let mut writer = WRITER.lock();
+        let old = writer.get_color();
+        writer.set_color(Color::LightGreen, Color::Black);
+        writer.clear_screen();
+        writer.set_crt_effect(true);
+        let (fg, bg) = writer.get_color();
+        // Drawn directly, so the odd row keeps its plain foreground
+        writer.write_char_at(0, 5, 'x', fg, bg);
+
+        writer.set_position(0, 3);
+        writer.write_string("y");
+        assert_eq!(writer.read_cell_at(0, 3), Some((b'y', Color::Green, bg)));
+        assert_eq!(writer.read_cell_at(0, 5), Some((b'x', fg, bg)));
+        writer.set_crt_effect(false);
+        writer.set_color(old.0, old.1);
+    }
+
+    #[test_case]
+    fn crt_themes_dim_alternate_rows() {
+        let old = get_current_theme();
+        for theme in [Theme::Green, Theme::Amber] {
+            set_theme(theme);
+            let mut writer = WRITER.lock();
+            writer.set_position(0, 6);
+            writer.write_string("a\nb");
+            let even = writer.buffer.chars[6][0].color_code;
+            let odd = writer.buffer.chars[7][0].color_code;
+            assert_ne!(even, odd);
+            assert_eq!(odd.foreground(), dim_color(theme.colors().0));
+        }
+        set_theme(old);
+    }
+
+    #[test_case]
+    fn clear_screen_blanks_every_cell() {
+        let mut writer = WRITER.lock();
+        writer.write_string("\nsome text");
+        writer.clear_screen();
+
+        let colors = writer.get_color();
+        for y in 0..BUFFER_HEIGHT {
+            for x in 0..BUFFER_WIDTH {
+                assert_eq!(writer.read_cell_at(x, y), Some((b' ', colors.0, colors.1)));
+            }
+        }
+        assert_eq!(writer.get_position(), (0, 0));
+    }
+
+    #[test_case]
+    fn long_line_wraps_at_last_column() {
+        let mut writer = WRITER.lock();
+        writer.set_position(0, 3);
+        let line = "x".repeat(BUFFER_WIDTH);
+        writer.write_string(&line);
+        writer.write_string("y");
+
+        assert_eq!(writer.read_char_at(BUFFER_WIDTH - 1, 3), Some('x'));
+        assert_eq!(writer.read_char_at(0, 4), Some('y'));
+        assert_eq!(writer.get_position(), (1, 4));
+    }
+}
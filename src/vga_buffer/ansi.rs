@@ -0,0 +1,199 @@
+// src/vga_buffer/ansi.rs
+// Parser for the few ANSI escape sequences the Writer understands
+
+use super::Color;
+
+// Parameters past this are read but dropped
+const MAX_PARAMS: usize = 8;
+
+const ESC: u8 = 0x1B;
+
+// VGA color for each ANSI color number: black, red, green, yellow, blue,
+// magenta, cyan and white. The bright variants add 8.
+const ANSI_TO_VGA: [u8; 8] = [0, 4, 2, 6, 1, 5, 3, 7];
+
+/// Numeric parameters of a control sequence, missing ones read as 0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnsiParams {
+    values: [u16; MAX_PARAMS],
+    len: usize,
+}
+
+impl AnsiParams {
+    const fn new() -> Self {
+        Self { values: [0; MAX_PARAMS], len: 0 }
+    }
+
+    /// Parameter `index`, or `default` if it is missing or 0
+    pub fn get_or(&self, index: usize, default: u16) -> u16 {
+        match self.values[..self.len].get(index) {
+            Some(&value) if value != 0 => value,
+            _ => default,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u16> + '_ {
+        self.values[..self.len].iter().copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// What the parser made of a byte
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnsiAction {
+    /// An ordinary byte to draw
+    Print(u8),
+    /// Part of an escape sequence, or a sequence that isn't supported
+    None,
+    /// `ESC [ ... m`, set colors
+    SetGraphics(AnsiParams),
+    /// `ESC [ row ; col H`, move the cursor. Both start at 1.
+    MoveCursor(AnsiParams),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Ground,
+    Escape,
+    ControlSequence,
+}
+
+/// Byte-at-a-time state machine, so a sequence may be split over several
+/// writes
+pub struct AnsiParser {
+    state: State,
+    params: AnsiParams,
+}
+
+impl AnsiParser {
+    pub const fn new() -> Self {
+        Self {
+            state: State::Ground,
+            params: AnsiParams::new(),
+        }
+    }
+
+    pub fn feed(&mut self, byte: u8) -> AnsiAction {
+        match self.state {
+            State::Ground if byte == ESC => {
+                self.state = State::Escape;
+                AnsiAction::None
+            }
+            State::Ground => AnsiAction::Print(byte),
+            State::Escape if byte == b'[' => {
+                self.state = State::ControlSequence;
+                self.params = AnsiParams::new();
+                AnsiAction::None
+            }
+            // Two-byte escapes aren't supported, drop them
+            State::Escape => {
+                self.state = State::Ground;
+                AnsiAction::None
+            }
+            State::ControlSequence => self.feed_sequence(byte),
+        }
+    }
+
+    fn feed_sequence(&mut self, byte: u8) -> AnsiAction {
+        let params = &mut self.params;
+        match byte {
+            b'0'..=b'9' => {
+                if params.len == 0 {
+                    params.len = 1;
+                }
+                if params.len <= MAX_PARAMS {
+                    let value = &mut params.values[params.len - 1];
+                    *value = value.saturating_mul(10).saturating_add(u16::from(byte - b'0'));
+                }
+                AnsiAction::None
+            }
+            b';' => {
+                // An empty first parameter still counts
+                if params.len == 0 {
+                    params.len = 1;
+                }
+                if params.len < MAX_PARAMS {
+                    params.len += 1;
+                }
+                AnsiAction::None
+            }
+            // Final byte ends the sequence
+            0x40..=0x7E => {
+                self.state = State::Ground;
+                match byte {
+                    b'm' => AnsiAction::SetGraphics(self.params),
+                    b'H' | b'f' => AnsiAction::MoveCursor(self.params),
+                    _ => AnsiAction::None,
+                }
+            }
+            // Intermediate bytes and anything unexpected are skipped
+            _ => AnsiAction::None,
+        }
+    }
+}
+
+/// VGA color for ANSI color number 0-7
+pub fn ansi_color(index: u16, bright: bool) -> Color {
+    let base = ANSI_TO_VGA[usize::from(index & 7)];
+    Color::from_index(if bright { base | 8 } else { base })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    fn feed_all(parser: &mut AnsiParser, bytes: &[u8]) -> Vec<AnsiAction> {
+        bytes.iter()
+            .map(|&byte| parser.feed(byte))
+            .filter(|action| *action != AnsiAction::None)
+            .collect()
+    }
+
+    #[test_case]
+    fn color_sequence_is_parsed() {
+        let mut parser = AnsiParser::new();
+        let actions = feed_all(&mut parser, b"a\x1B[1;91mb");
+        assert_eq!(actions.len(), 3);
+        assert_eq!(actions[0], AnsiAction::Print(b'a'));
+        match actions[1] {
+            AnsiAction::SetGraphics(params) => assert_eq!(params.iter().collect::<Vec<_>>(), [1, 91]),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(actions[2], AnsiAction::Print(b'b'));
+    }
+
+    #[test_case]
+    fn sequence_survives_split_writes() {
+        let mut parser = AnsiParser::new();
+        assert!(feed_all(&mut parser, b"\x1B[1").is_empty());
+        match feed_all(&mut parser, b"0;5H")[..] {
+            [AnsiAction::MoveCursor(params)] => {
+                assert_eq!(params.get_or(0, 1), 10);
+                assert_eq!(params.get_or(1, 1), 5);
+                assert_eq!(params.get_or(2, 1), 1);
+            }
+            ref other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test_case]
+    fn reset_has_no_params() {
+        let mut parser = AnsiParser::new();
+        match feed_all(&mut parser, b"\x1B[m")[..] {
+            [AnsiAction::SetGraphics(params)] => assert!(params.is_empty()),
+            ref other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test_case]
+    fn ansi_colors_map_to_vga() {
+        assert!(ansi_color(1, false) == Color::Red);
+        assert!(ansi_color(1, true) == Color::LightRed);
+        assert!(ansi_color(3, true) == Color::Yellow);
+        assert!(ansi_color(0, true) == Color::DarkGray);
+    }
+}
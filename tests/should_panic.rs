@@ -0,0 +1,27 @@
+// tests/should_panic.rs
+// Check that a failed assertion panics. Without unwinding the panic can't
+// be caught, so this runs as its own binary and the panic handler reports
+// success.
+
+#![no_std]
+#![no_main]
+
+use core::panic::PanicInfo;
+use screamos::{exit_qemu, serial_print, serial_println, QemuExitCode};
+
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    serial_print!("should_panic::failed_assertion_panics...\t");
+    assert_eq!(0, 1);
+
+    serial_println!("[test did not panic]");
+    exit_qemu(QemuExitCode::Failed);
+    screamos::hlt_loop();
+}
+
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    serial_println!("[ok]");
+    exit_qemu(QemuExitCode::Success);
+    screamos::hlt_loop();
+}